@@ -14,17 +14,18 @@ export async function deploy() {
     const stargazeWallet = await Wallet.create("stargaze", env);
     console.log(`Stargaze wallet address: ${stargazeWallet.address}, balance: ${await stargazeWallet.balance()}`);
 
-    await deployContract(neutronWallet, "warehouse");
+    await deployContract(neutronWallet, "warehouse", {
+        payment_chain_id: kujiraWallet.networkConfig.chain_id,
+    });
     await deployContract(kujiraWallet, "payment");
     await deployContract(stargazeWallet, "nft");
 }
 
-async function deployContract(wallet: Wallet, name: ContractName) {
+async function deployContract(wallet: Wallet, name: ContractName, msg: object = {}) {
     console.log(``);
     const {isNew} = await wallet.uploadContract(name, "deploy-always" /*"deploy-if-new"*/);
     if(isNew || !ONLY_IF_NEW) {
-        await wallet.instantiateContract(name, {
-        });
+        await wallet.instantiateContract(name, msg);
 
         await wallet.setIbcPort(name);
     } else {
@@ -14,13 +14,15 @@ export async function migrate() {
     const stargazeWallet = await Wallet.create("stargaze", env);
     console.log(`Stargaze wallet address: ${stargazeWallet.address}, balance: ${await stargazeWallet.balance()}`);
 
-    await migrateContract(neutronWallet, "warehouse");
+    await migrateContract(neutronWallet, "warehouse", {
+        payment_chain_id: kujiraWallet.networkConfig.chain_id,
+    });
     await migrateContract(kujiraWallet, "payment");
     await migrateContract(stargazeWallet, "nft");
 }
 
-async function migrateContract(wallet: Wallet, name: ContractName) {
+async function migrateContract(wallet: Wallet, name: ContractName, msg: object = {}) {
     console.log(``);
     const {hashHex, codeId} = await wallet.uploadContract(name, "migrate");
-    await wallet.migrateContract(name, codeId, hashHex, { });
+    await wallet.migrateContract(name, codeId, hashHex, msg);
 }
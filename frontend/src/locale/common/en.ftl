@@ -1 +1,2 @@
-button-remove = remove
\ No newline at end of file
+button-remove = remove
+button-confirm-delivery = confirm delivery
\ No newline at end of file
@@ -1,7 +1,7 @@
 use awsm_web::window;
 use cosmwasm_std::Coin;
 use dominator_helpers::futures::AsyncLoader;
-use shared::{msg::{contract::{payment::InfoResp, warehouse::{event::AddProductEvent, NewProduct, QueryMsg}}, product::{Product, ProductId}}, tx::CosmosResponseExt};
+use shared::{msg::{contract::{payment::InfoResp, warehouse::{event::AddProductEvent, NewProduct, PurchaseQuote, QueryMsg}}, product::{Product, ProductId}}, tx::CosmosResponseExt};
 
 use crate::{atoms::{buttons::Squareish1Button, input::{TextInput, TextInputKind}}, config::{ContractName, NetworkConfig, NETWORK_CONFIG}, prelude::*};
 
@@ -144,17 +144,33 @@ impl ProductCard {
                         log::info!("info: {:?}", info);
 
                         let quantity = 1u32;
-                        let amount = state.product.price.checked_mul(quantity.to_string().parse().unwrap_ext()).unwrap_ext();
+                        let quote:PurchaseQuote = Wallet::neutron().contract_query(ContractName::Warehouse, &WarehouseQueryMsg::QuotePurchase {
+                            product_id: state.product.id.clone(),
+                            variant_id: None,
+                            quantity,
+                            spender: Some(Wallet::kujira().address()),
+                            coupon: None,
+                        }).await.unwrap_ext();
+                        log::info!("quote: {:?}", quote);
+
+                        if let Some(err) = quote.error {
+                            web_sys::window().unwrap_ext().alert_with_message(&format!("Can't purchase: {}", err)).unwrap_throw();
+                            return;
+                        }
                         Wallet::kujira().contract_exec_funds(
                             ContractName::Payment, 
                             &PaymentExecuteMsg::Purchase {
                                 owner: Wallet::stargaze().address(),
                                 product_id: state.product.id.clone(),
+                                variant_id: None,
                                 quantity,
+                                hold_id: None,
+                                coupon: None,
+                                referrer: None,
                             },
                             &[Coin {
                                 denom: Wallet::kujira().denom().clone(),
-                                amount: amount.to_uint_ceil().to_string().parse().unwrap_ext()
+                                amount: quote.deposit,
                             }]
                         ).await.unwrap_throw();
                     }))
@@ -126,10 +126,7 @@ impl PurchaseCard {
                             .text(&format!("Current price: {}", self.group.cost_per_item()))
                         }),
                         html!("div", {
-                            .text(&format!("Shipping status: {}", match self.group.has_shipped {
-                                true => "Shipped",
-                                false => "Not shipped"
-                            }))
+                            .text(&format!("Shipping status: {}", self.group.status))
                         }),
                         OutlineButton::new(true).render(None, get_text!("button-remove"), clone!(state => move || {
                             let purchase_id = state.purchase.id;
@@ -142,6 +139,18 @@ impl PurchaseCard {
                             })
                         }))
                     ])
+                    .apply_if(self.group.has_shipped(), clone!(state => move |dom| {
+                        dom.child(OutlineButton::new(true).render(None, get_text!("button-confirm-delivery"), clone!(state => move || {
+                            let purchase_id = state.purchase.id;
+                            let list = state.list.clone();
+                            spawn_local(async move {
+                                Wallet::stargaze().contract_exec(ContractName::Nft, &NftExecuteMsg::ConfirmDelivery { 
+                                    token_id: purchase_id.to_string()
+                                }).await;
+                                list.loader.reload();
+                            })
+                        })))
+                    }))
                 }),
             ])
         })
@@ -1,8 +1,8 @@
 use awsm_web::window;
 use dominator_helpers::futures::AsyncLoader;
-use shared::{msg::{contract::warehouse::{event::AddProductEvent, NewProduct, QueryMsg}, product::{Product, ProductId}}, tx::CosmosResponseExt};
+use shared::{msg::{contract::warehouse::{event::AddProductEvent, Merchant, MerchantProfile, NewProduct, PayoutAddress, QueryMsg}, product::{Product, ProductId}}, tx::CosmosResponseExt};
 
-use crate::{atoms::{buttons::Squareish1Button, input::{TextInput, TextInputKind}}, config::ContractName, prelude::*};
+use crate::{atoms::{buttons::Squareish1Button, input::{TextInput, TextInputKind}}, config::{ContractName, Environment, CHAINENV, NETWORK_CONFIG}, prelude::*};
 
 pub struct ProductsPage {
     pub add: Arc<AddProduct>,
@@ -31,7 +31,10 @@ impl ProductsPage {
             .child(state.add.render(clone!(state => move || {
                 if let Some(product) = state.add.get_product() {
                     state.add.loader.load(clone!(state => async move {
+                        ensure_registered().await;
+
                         let resp = Wallet::neutron().contract_exec(ContractName::Warehouse, &WarehouseExecuteMsg::AddProduct {
+                            merchant: None,
                             product: product.clone(),
                         }).await.unwrap_ext();
 
@@ -105,6 +108,14 @@ impl AddProduct {
             name,
             price,
             stock,
+            group_capacity: None,
+            auto_ship_on_full: false,
+            purchase_rules: Default::default(),
+            variants: Vec::new(),
+            preorder_cap: None,
+            pricing: Default::default(),
+            referral_share: Default::default(),
+            cw20: None,
         })
     }
 }
@@ -233,4 +244,31 @@ async fn load_products() -> Result<Vec<Product>> {
     }
 
     Ok(products)
-}
\ No newline at end of file
+}
+
+// only registered merchants can list products, so register on the first one
+// settlements are paid out to this wallet on the payment chain
+async fn ensure_registered() {
+    let merchant:Result<Merchant> = Wallet::neutron().contract_query(ContractName::Warehouse, &WarehouseQueryMsg::GetMerchant {
+        merchant: Wallet::neutron().address(),
+    }).await;
+
+    if merchant.is_err() {
+        Wallet::neutron().contract_exec(ContractName::Warehouse, &WarehouseExecuteMsg::RegisterMerchant {
+            profile: MerchantProfile {
+                display_name: Wallet::neutron().address(),
+                contact: None,
+                logo_uri: None,
+                payout_addresses: vec![PayoutAddress {
+                    chain_id: match *CHAINENV {
+                        Environment::Local => NETWORK_CONFIG.kujira_local.chain_id.clone(),
+                        Environment::Testnet => NETWORK_CONFIG.kujira_testnet.chain_id.clone(),
+                    },
+                    address: Wallet::kujira().address(),
+                }],
+                coupon_pubkey: None,
+                payout_route: None,
+            },
+        }).await.unwrap_ext();
+    }
+}
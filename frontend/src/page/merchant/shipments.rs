@@ -130,12 +130,15 @@ impl GroupCard {
                     .text(&format!("Members: {}", self.group.count))
                 }),
                 html!("div", {
-                    .text(&format!("Has shipped: {}", self.group.has_shipped))
+                    .text(&format!("Status: {}", self.group.status))
                 }),
                 OutlineButton::new(true).render(None, "Ship".to_string(), clone!(state => move || {
                     spawn_local(clone!(state => async move {
                         Wallet::neutron().contract_exec(ContractName::Warehouse, &WarehouseExecuteMsg::ShipGroup {
                             group_id: state.group.id.clone(),
+                            carrier: None,
+                            tracking_ref: None,
+                            purchase_ids: None,
                         }).await;
                         state.list.reload();
                     }))
@@ -25,6 +25,8 @@ const OWNER_TO_TOKEN_IDS: Map<(Addr, String), u8> = Map::new("nft-owners");
 const TOKEN_IDS: Map<&str, u8> = Map::new("nft-ids");
 const TOKEN_META: Map<&str, Metadata> = Map::new("nft-metadata");
 const TOKEN_OWNER: Map<&str, Addr> = Map::new("nft-owner");
+// so the warehouse can refer to receipts by purchase
+const PURCHASE_TOKENS: Map<PurchaseId, String> = Map::new("nft-purchase-tokens");
 const DEFAULT_LIMIT: u32 = 10;
 const MAX_LIMIT: u32 = 100;
 
@@ -344,6 +346,18 @@ impl State<'_> {
             ExecuteMsg::Burn { token_id } => {
                 self.nft_burn(ctx, msg_sender, token_id)
             }, 
+            ExecuteMsg::ConfirmDelivery { token_id } => {
+                self.nft_confirm_delivery(ctx, msg_sender, token_id)
+            },
+            ExecuteMsg::OpenDispute { token_id, evidence } => {
+                self.nft_open_dispute(ctx, msg_sender, token_id, evidence)
+            },
+            ExecuteMsg::Review { token_id, rating, comment } => {
+                self.nft_review(ctx, msg_sender, token_id, rating, comment)
+            },
+            ExecuteMsg::ReducePurchase { token_id, quantity } => {
+                self.nft_reduce_purchase(ctx, msg_sender, token_id, quantity)
+            },
             ExecuteMsg::Approve {
                 spender,
                 token_id,
@@ -398,6 +412,9 @@ impl State<'_> {
         APPROVALS.save(ctx.store, &token_id, &Vec::new())?;
         TOKEN_OWNER.save(ctx.store, &token_id, &owner)?;
         TOKEN_META.save(ctx.store, &token_id, &meta)?;
+        if let Ok(purchase_id) = meta_purchase_id(&meta) {
+            PURCHASE_TOKENS.save(ctx.store, purchase_id, &token_id)?;
+        }
 
         self.nft_increment_tokens(ctx)?;
 
@@ -430,22 +447,110 @@ impl State<'_> {
 
         ctx.response_mut().add_event(BurnEvent { token_id });
 
-        let purchase_id = meta
-            .attributes
-            .and_then(|attributes| attributes
-                .iter()
-                .find_map(|t| {
-                    if t.trait_type == "purchase-id" {
-                        t.value.parse::<PurchaseId>().ok()
-                    } else {
-                        None
-                    }
-                })
-            )
-            .context("missing purchase_id")?;
+        let purchase_id = meta_purchase_id(&meta)?;
+        PURCHASE_TOKENS.remove(ctx.store, purchase_id);
 
         let msg = WarehouseIbcExecuteMsg::RemovePurchase { id: purchase_id };
 
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    pub(crate) fn nft_confirm_delivery(
+        &self,
+        ctx: &mut StateContext,
+        msg_sender: Addr,
+        token_id: String,
+    ) -> Result<()> {
+        let purchase_id = self.nft_holder_purchase_id(ctx.store, &msg_sender, &token_id)?;
+
+        let msg = WarehouseIbcExecuteMsg::ConfirmDelivery { id: purchase_id };
+
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    pub(crate) fn nft_open_dispute(
+        &self,
+        ctx: &mut StateContext,
+        msg_sender: Addr,
+        token_id: String,
+        evidence: String,
+    ) -> Result<()> {
+        let purchase_id = self.nft_holder_purchase_id(ctx.store, &msg_sender, &token_id)?;
+
+        let msg = WarehouseIbcExecuteMsg::OpenDispute { id: purchase_id, evidence };
+
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    pub(crate) fn nft_review(
+        &self,
+        ctx: &mut StateContext,
+        msg_sender: Addr,
+        token_id: String,
+        rating: u8,
+        comment: String,
+    ) -> Result<()> {
+        let purchase_id = self.nft_holder_purchase_id(ctx.store, &msg_sender, &token_id)?;
+
+        let msg = WarehouseIbcExecuteMsg::Review { id: purchase_id, rating, comment };
+
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    pub(crate) fn nft_reduce_purchase(
+        &self,
+        ctx: &mut StateContext,
+        msg_sender: Addr,
+        token_id: String,
+        quantity: u32,
+    ) -> Result<()> {
+        let purchase_id = self.nft_holder_purchase_id(ctx.store, &msg_sender, &token_id)?;
+        // receipts minted before the index existed won't be in it yet
+        PURCHASE_TOKENS.save(ctx.store, purchase_id, &token_id)?;
+
+        let msg = WarehouseIbcExecuteMsg::ReducePurchase { id: purchase_id, quantity };
+
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    // the warehouse has reduced the purchase, so the receipt follows
+    pub(crate) fn nft_set_purchase_quantity(
+        &self,
+        ctx: &mut StateContext,
+        purchase_id: PurchaseId,
+        quantity: u32,
+    ) -> Result<()> {
+        // the receipt may have been burned in the meantime
+        let token_id = match PURCHASE_TOKENS.may_load(ctx.store, purchase_id)? {
+            Some(token_id) => token_id,
+            None => return Ok(()),
+        };
+
+        TOKEN_META.update(ctx.store, &token_id, |meta| {
+            let mut meta = meta.context(format!("missing metadata for token {}", token_id))?;
+            for t in meta.attributes.iter_mut().flatten() {
+                if t.trait_type == "quantity" {
+                    t.value = quantity.to_string();
+                }
+            }
+            anyhow::Ok(meta)
+        })?;
+
+        Ok(())
+    }
+
+    // only the holder of the receipt can act on the purchase, not approved spenders or operators
+    fn nft_holder_purchase_id(&self, store: &dyn Storage, msg_sender: &Addr, token_id: &str) -> Result<PurchaseId> {
+        let owner = TOKEN_OWNER.load(store, token_id)?;
+        if owner != *msg_sender {
+            bail!("only the token owner can do this");
+        }
+
+        let meta = TOKEN_META.load(store, token_id)?;
+        meta_purchase_id(&meta)
+    }
+
+    fn send_warehouse_packet(&self, ctx: &mut StateContext, msg: &WarehouseIbcExecuteMsg) -> Result<()> {
         // outbound IBC message, where packet is then received on other chain
         let channel_id = self
             .get_ibc_channel(ctx.store)?
@@ -454,11 +559,10 @@ impl State<'_> {
 
         ctx.response_mut().add_message(IbcMsg::SendPacket {
             channel_id,
-            data: to_json_binary(&msg)?,
+            data: to_json_binary(msg)?,
             timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(TIMEOUT_SECONDS)),
         });
 
-
         Ok(())
     }
 
@@ -715,6 +819,23 @@ impl State<'_> {
     }
 }
 
+fn meta_purchase_id(meta: &Metadata) -> Result<PurchaseId> {
+    meta
+        .attributes
+        .as_ref()
+        .and_then(|attributes| attributes
+            .iter()
+            .find_map(|t| {
+                if t.trait_type == "purchase-id" {
+                    t.value.parse::<PurchaseId>().ok()
+                } else {
+                    None
+                }
+            })
+        )
+        .context("missing purchase_id")
+}
+
 fn filter_approvals(
     block: &BlockInfo,
     approvals: &[Approval],
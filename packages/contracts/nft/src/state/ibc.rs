@@ -62,6 +62,9 @@ impl State<'_> {
                 match msg {
                     IbcExecuteMsg::Mint{owner, metadata } => {
                         self.nft_mint(ctx, self.api.addr_validate(&owner)?, metadata)
+                    },
+                    IbcExecuteMsg::SetQuantity{purchase_id, quantity } => {
+                        self.nft_set_purchase_quantity(ctx, purchase_id, quantity)
                     }
                 }
             })
@@ -1,13 +1,13 @@
 use cosmwasm_std::{
-    entry_point, Deps, DepsMut, Empty, Env, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo, QueryResponse, Response
+    entry_point, Deps, DepsMut, Empty, Env, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo, QueryResponse, Reply, Response
 };
 use cw2::{get_contract_version, set_contract_version};
 use shared::{
-    msg::contract::payment::{ExecuteMsg, InfoResp, QueryMsg}, response::{QueryResponseExt, ResponseBuilder},
+    msg::contract::payment::{ExecuteMsg, InfoResp, QueryMsg, SudoMsg}, response::{QueryResponseExt, ResponseBuilder},
 };
 use anyhow::Result;
 
-use crate::state::{State, StateContext};
+use crate::state::{claims::PUSH_REPLY_ID, purchase::purchase_funds, transfer::TRANSFER_REPLY_ID, State, StateContext};
 // version info for migration info
 const CONTRACT_NAME: &str = "payment";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -15,13 +15,16 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    env: Env,
+    info: MessageInfo,
     _msg: Empty,
 ) -> Result<Response> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    Ok(Response::default())
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+    state.init_config(&mut ctx, info.sender)?;
+
+    Ok(ctx.response.into_response())
 }
 
 #[entry_point]
@@ -29,8 +32,47 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> R
     let (state, mut ctx) = StateContext::new(deps, env)?;
 
     match msg {
-        ExecuteMsg::Purchase { owner, product_id, quantity} => {
-            state.purchase_send(&mut ctx, owner, info, product_id, quantity)?;
+        ExecuteMsg::Purchase { owner, product_id, variant_id, quantity, hold_id, coupon, referrer } => {
+            state.purchase_send(&mut ctx, owner, info.sender.to_string(), purchase_funds(&info)?, None, product_id, variant_id, quantity, hold_id, coupon, referrer)?;
+        },
+        ExecuteMsg::PurchaseBatch { owner, items, atomic, referrer } => {
+            state.purchase_batch_send(&mut ctx, owner, info.sender.to_string(), purchase_funds(&info)?, None, items, atomic, referrer)?;
+        },
+        ExecuteMsg::Pledge { owner, demand_id, quantity } => {
+            state.pledge_send(&mut ctx, owner, info, demand_id, quantity)?;
+        },
+        ExecuteMsg::Subscribe { owner, product_id, variant_id, quantity, periods, period_seconds, per_period } => {
+            state.subscribe(&mut ctx, info, owner, product_id, variant_id, quantity, periods, period_seconds, per_period)?;
+        },
+        ExecuteMsg::CrankSubscriptions { limit } => {
+            state.crank_subscriptions(&mut ctx, limit)?;
+        },
+        ExecuteMsg::SkipSubscriptionPeriod { subscription_id } => {
+            state.skip_subscription_period(&mut ctx, info.sender, subscription_id)?;
+        },
+        ExecuteMsg::CancelSubscription { subscription_id } => {
+            state.cancel_subscription(&mut ctx, info.sender, subscription_id)?;
+        },
+        ExecuteMsg::WithdrawSubscriptionDeposit { subscription_id } => {
+            state.withdraw_subscription_deposit(&mut ctx, info.sender, subscription_id)?;
+        },
+        ExecuteMsg::Receive(receive) => {
+            state.receive_cw20(&mut ctx, info, receive)?;
+        },
+        ExecuteMsg::WithdrawFees { amount, cw20 } => {
+            state.withdraw_fees(&mut ctx, info.sender, amount, cw20)?;
+        },
+        ExecuteMsg::Withdraw { amount, cw20 } => {
+            state.withdraw(&mut ctx, info.sender, None, amount, cw20)?;
+        },
+        ExecuteMsg::WithdrawTo { recipient, amount, cw20 } => {
+            state.withdraw(&mut ctx, info.sender, Some(recipient), amount, cw20)?;
+        },
+        ExecuteMsg::UpdateCw20Allowlist { add, remove } => {
+            state.update_cw20_allowlist(&mut ctx, info.sender, add, remove)?;
+        },
+        ExecuteMsg::UpdateConfig { admin, fee_collector, auto_push } => {
+            state.update_config(&mut ctx, info.sender, admin, fee_collector, auto_push)?;
         }
     }
 
@@ -48,6 +90,34 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<QueryResponse> {
                 ibc_channel
             };
             info.query_result()
+        },
+        QueryMsg::Config {  } => {
+            let config = state.get_config(store)?;
+            config.query_result()
+        },
+        QueryMsg::FeeBalance { cw20 } => {
+            let balance = state.get_fee_balance(store, &cw20)?;
+            balance.query_result()
+        },
+        QueryMsg::Cw20Allowlist {  } => {
+            let allowlist = state.get_cw20_allowlist(store)?;
+            allowlist.query_result()
+        },
+        QueryMsg::GetSubscription { subscription_id } => {
+            let subscription = state.get_subscription(store, subscription_id)?;
+            subscription.query_result()
+        },
+        QueryMsg::ListSubscriptions { subscriber, start_after, limit } => {
+            let subscriptions = state.list_subscriptions(store, subscriber, start_after, limit)?;
+            subscriptions.query_result()
+        },
+        QueryMsg::Balances { address } => {
+            let balances = state.get_claimable_balances(store, &address)?;
+            balances.query_result()
+        },
+        QueryMsg::Claimable { address, cw20 } => {
+            let claimable = state.get_claimable(store, &address, &cw20)?;
+            claimable.query_result()
         }
     }
 }
@@ -55,6 +125,33 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<QueryResponse> {
 #[entry_point]
 pub fn migrate(deps: DepsMut, env: Env, msg: Empty) -> Result<Response> {
     let (state, mut ctx) = StateContext::new(deps, env)?;
+    state.migrate_config(&mut ctx)?;
+
+    Ok(ctx.response.into_response())
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response> {
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+
+    match reply.id {
+        TRANSFER_REPLY_ID => state.handle_transfer_reply(&mut ctx, reply)?,
+        PUSH_REPLY_ID => state.handle_push_reply(&mut ctx, reply)?,
+        id => anyhow::bail!("unknown reply id {}", id),
+    }
+
+    Ok(ctx.response.into_response())
+}
+
+#[entry_point]
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response> {
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+
+    match msg {
+        SudoMsg::IbcLifecycleComplete(complete) => {
+            state.handle_ibc_lifecycle_complete(&mut ctx, complete)?;
+        }
+    }
 
     Ok(ctx.response.into_response())
 }
@@ -107,8 +204,8 @@ pub fn ibc_packet_receive(
 
 #[entry_point]
 pub fn ibc_packet_ack(deps: DepsMut, env: Env, ack: IbcPacketAckMsg) -> Result<IbcBasicResponse> {
-    let (state, ctx) = StateContext::new(deps, env)?;
-    state.handle_ibc_packet_ack(ack)?;
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+    state.handle_ibc_packet_ack(&mut ctx, ack)?;
     Ok(ctx.response.into_ibc_response())
 }
 
@@ -118,7 +215,7 @@ pub fn ibc_packet_timeout(
     env: Env,
     msg: IbcPacketTimeoutMsg,
 ) -> Result<IbcBasicResponse> {
-    let (state, ctx) = StateContext::new(deps, env)?;
-    state.handle_ibc_packet_timeout(msg)?;
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+    state.handle_ibc_packet_timeout(&mut ctx, msg)?;
     Ok(ctx.response.into_ibc_response())
 }
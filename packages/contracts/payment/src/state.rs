@@ -1,5 +1,11 @@
 pub mod purchase;
 pub mod ibc;
+pub mod config;
+pub mod fees;
+pub mod cw20;
+pub mod transfer;
+pub mod claims;
+pub mod subscription;
 
 /// Generally speaking - all entry points get a State (read-only)
 /// instantiate/execute/migrate get that _and_ a StateContext (writable)
@@ -0,0 +1,100 @@
+use cosmwasm_std::{from_json, to_json_binary, Addr, Order, Reply, Storage, SubMsg, Uint128};
+use cw_storage_plus::Map;
+use shared::msg::contract::payment::{event::{CreditEvent, WithdrawEvent}, ClaimableBalance, Delivery};
+use anyhow::Result;
+
+use super::{purchase::{asset_key, funds_msg, PURCHASE_DENOM}, State, StateContext};
+
+pub const PUSH_REPLY_ID: u64 = 2;
+
+// refunds and payouts waiting to be withdrawn, keyed by (recipient, asset)
+const CLAIMABLE: Map<(&str, &str), Uint128> = Map::new("claimable");
+
+impl State<'_> {
+    // refunds and payouts are credited, so one bad recipient can't fail the whole packet
+    // with auto-push they're sent right away, and only credited if that fails
+    pub fn deliver(&self, ctx: &mut StateContext, recipient: String, amount: Uint128, cw20: Option<String>) -> Result<()> {
+        if amount.is_zero() {
+            return Ok(());
+        }
+
+        if self.get_config(ctx.store)?.auto_push {
+            let msg = funds_msg(recipient.clone(), amount, cw20.clone())?;
+            let delivery = Delivery { recipient, amount, cw20 };
+
+            ctx.response_mut().add_raw_submessage(
+                SubMsg::reply_on_error(msg, PUSH_REPLY_ID).with_payload(to_json_binary(&delivery)?)
+            );
+
+            Ok(())
+        } else {
+            self.credit_claimable(ctx, &recipient, amount, &cw20)
+        }
+    }
+
+    // only called on error, the push was reverted and the funds are still here
+    pub fn handle_push_reply(&self, ctx: &mut StateContext, reply: Reply) -> Result<()> {
+        let Delivery { recipient, amount, cw20 } = from_json(&reply.payload)?;
+
+        self.credit_claimable(ctx, &recipient, amount, &cw20)
+    }
+
+    pub fn credit_claimable(&self, ctx: &mut StateContext, recipient: &str, amount: Uint128, cw20: &Option<String>) -> Result<()> {
+        CLAIMABLE.update(ctx.store, (recipient, asset_key(cw20)), |balance| anyhow::Ok(balance.unwrap_or_default() + amount))?;
+
+        ctx.response_mut().add_event(CreditEvent {
+            recipient: recipient.to_string(),
+            amount,
+            cw20: cw20.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw(&self, ctx: &mut StateContext, msg_sender: Addr, recipient: Option<String>, amount: Option<Uint128>, cw20: Option<String>) -> Result<()> {
+        let recipient = match recipient {
+            Some(recipient) => self.api.addr_validate(&recipient)?,
+            None => msg_sender.clone(),
+        };
+
+        let balance = self.get_claimable(ctx.store, msg_sender.as_str(), &cw20)?;
+        let amount = amount.unwrap_or(balance);
+        if amount.is_zero() {
+            anyhow::bail!("nothing to withdraw");
+        }
+        if amount > balance {
+            anyhow::bail!("cannot withdraw {} of {}", amount, balance);
+        }
+
+        CLAIMABLE.save(ctx.store, (msg_sender.as_str(), asset_key(&cw20)), &(balance - amount))?;
+
+        self.send_funds(ctx, recipient.to_string(), amount, cw20.clone())?;
+
+        ctx.response_mut().add_event(WithdrawEvent {
+            recipient: recipient.to_string(),
+            amount,
+            cw20,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_claimable(&self, store: &dyn Storage, address: &str, cw20: &Option<String>) -> Result<Uint128> {
+        Ok(CLAIMABLE.may_load(store, (address, asset_key(cw20)))?.unwrap_or_default())
+    }
+
+    pub fn get_claimable_balances(&self, store: &dyn Storage, address: &str) -> Result<Vec<ClaimableBalance>> {
+        CLAIMABLE
+            .prefix(address)
+            .range(store, None, None, Order::Ascending)
+            .filter(|res| !matches!(res, Ok((_, amount)) if amount.is_zero()))
+            .map(|res| {
+                let (asset, amount) = res?;
+                Ok(ClaimableBalance {
+                    cw20: (asset != PURCHASE_DENOM).then_some(asset),
+                    amount,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,82 @@
+use cosmwasm_std::{Addr, Storage};
+use cw_storage_plus::Item;
+use serde::{Deserialize, Serialize};
+use shared::msg::contract::payment::Config;
+use anyhow::{Context, Result};
+
+use super::{State, StateContext};
+
+const CONFIG: Item<Config> = Item::new("config");
+// the same key, read leniently so a config stored before later fields were added can be filled in
+const STORED_CONFIG: Item<StoredConfig> = Item::new("config");
+
+impl State<'_> {
+    pub fn init_config(&self, ctx: &mut StateContext, admin: Addr) -> Result<()> {
+        CONFIG.save(ctx.store, &Config {
+            fee_collector: admin.clone(),
+            admin,
+            auto_push: false,
+        })?;
+
+        Ok(())
+    }
+
+    // contracts from before the config existed get one owned by the contract admin
+    pub fn migrate_config(&self, ctx: &mut StateContext) -> Result<()> {
+        let config = match STORED_CONFIG.may_load(ctx.store)? {
+            Some(stored) => Config {
+                fee_collector: stored.fee_collector.unwrap_or_else(|| stored.admin.clone()),
+                admin: stored.admin,
+                auto_push: stored.auto_push.unwrap_or_default(),
+            },
+            None => {
+                let admin = self
+                    .querier
+                    .query_wasm_contract_info(&self.env.contract.address)?
+                    .admin
+                    .context("contract has no admin to own the config")?;
+
+                Config {
+                    fee_collector: admin.clone(),
+                    admin,
+                    auto_push: false,
+                }
+            }
+        };
+
+        CONFIG.save(ctx.store, &config)?;
+
+        Ok(())
+    }
+
+    pub fn get_config(&self, store: &dyn Storage) -> Result<Config> {
+        CONFIG.load(store).map_err(|err| err.into())
+    }
+
+    pub fn update_config(&self, ctx: &mut StateContext, msg_sender: Addr, admin: Option<String>, fee_collector: Option<String>, auto_push: Option<bool>) -> Result<()> {
+        let mut config = self.get_config(ctx.store)?;
+        if msg_sender != config.admin {
+            anyhow::bail!("only the admin can do this");
+        }
+
+        if let Some(admin) = admin {
+            config.admin = self.api.addr_validate(&admin)?;
+        }
+        if let Some(fee_collector) = fee_collector {
+            config.fee_collector = self.api.addr_validate(&fee_collector)?;
+        }
+        if let Some(auto_push) = auto_push {
+            config.auto_push = auto_push;
+        }
+        CONFIG.save(ctx.store, &config)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredConfig {
+    admin: Addr,
+    fee_collector: Option<Addr>,
+    auto_push: Option<bool>,
+}
@@ -0,0 +1,53 @@
+use cosmwasm_std::{from_json, Addr, MessageInfo, Order, Storage};
+use cw_storage_plus::Map;
+use shared::msg::{contract::payment::Cw20HookMsg, cw20::Cw20ReceiveMsg};
+use anyhow::Result;
+
+use super::{State, StateContext};
+
+const CW20_ALLOWLIST: Map<&Addr, ()> = Map::new("cw20-allowlist");
+
+impl State<'_> {
+    // the sender is the cw20 contract, the tokens came from the holder in the receive msg
+    pub fn receive_cw20(&self, ctx: &mut StateContext, info: MessageInfo, receive: Cw20ReceiveMsg) -> Result<()> {
+        if !CW20_ALLOWLIST.has(ctx.store, &info.sender) {
+            anyhow::bail!("{} is not an accepted cw20 token", info.sender);
+        }
+        if receive.amount.is_zero() {
+            anyhow::bail!("must send some tokens to purchase");
+        }
+
+        let cw20 = Some(info.sender.to_string());
+
+        match from_json(&receive.msg)? {
+            Cw20HookMsg::Purchase { owner, product_id, variant_id, quantity, hold_id, coupon, referrer } => {
+                self.purchase_send(ctx, owner, receive.sender, receive.amount, cw20, product_id, variant_id, quantity, hold_id, coupon, referrer)
+            },
+            Cw20HookMsg::PurchaseBatch { owner, items, atomic, referrer } => {
+                self.purchase_batch_send(ctx, owner, receive.sender, receive.amount, cw20, items, atomic, referrer)
+            },
+        }
+    }
+
+    pub fn update_cw20_allowlist(&self, ctx: &mut StateContext, msg_sender: Addr, add: Vec<String>, remove: Vec<String>) -> Result<()> {
+        if msg_sender != self.get_config(ctx.store)?.admin {
+            anyhow::bail!("only the admin can do this");
+        }
+
+        for cw20 in add {
+            CW20_ALLOWLIST.save(ctx.store, &self.api.addr_validate(&cw20)?, &())?;
+        }
+        for cw20 in remove {
+            CW20_ALLOWLIST.remove(ctx.store, &self.api.addr_validate(&cw20)?);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_cw20_allowlist(&self, store: &dyn Storage) -> Result<Vec<Addr>> {
+        CW20_ALLOWLIST
+            .keys(store, None, None, Order::Ascending)
+            .map(|res| res.map_err(|err| err.into()))
+            .collect()
+    }
+}
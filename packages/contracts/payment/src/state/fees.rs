@@ -0,0 +1,74 @@
+use cosmwasm_std::{Addr, Storage, Uint128};
+use cw_storage_plus::Map;
+use shared::msg::contract::payment::{event::{FeesWithdrawnEvent, PayoutEvent}, Payout};
+use anyhow::Result;
+
+use super::{purchase::asset_key, State, StateContext};
+
+// platform fees withheld from payouts, waiting for the fee collector
+// keyed by cw20 contract, or the native denom
+const FEE_BALANCES: Map<&str, Uint128> = Map::new("fee-balances");
+
+impl State<'_> {
+    // pays the merchant, keeping the platform fee back in the ledger
+    pub fn send_payout(&self, ctx: &mut StateContext, payout: Payout) -> Result<()> {
+        let fee = payout.fee.min(payout.amount);
+        let amount = payout.amount - fee;
+
+        if !fee.is_zero() {
+            FEE_BALANCES.update(ctx.store, asset_key(&payout.cw20), |balance| anyhow::Ok(balance.unwrap_or_default() + fee))?;
+        }
+
+        // cw20 tokens can't go over a plain ICS-20 channel, so those are always paid here
+        let route = payout.route.filter(|_| payout.cw20.is_none());
+        let channel = route.as_ref().map(|route| route.channel_id.clone());
+
+        if !amount.is_zero() {
+            match route {
+                Some(route) => self.send_payout_transfer(ctx, payout.recipient.clone(), amount, route)?,
+                None => self.deliver(ctx, payout.recipient.clone(), amount, payout.cw20)?,
+            }
+        }
+
+        ctx.response_mut().add_event(PayoutEvent {
+            recipient: payout.recipient,
+            amount,
+            fee,
+            channel,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_fees(&self, ctx: &mut StateContext, msg_sender: Addr, amount: Option<Uint128>, cw20: Option<String>) -> Result<()> {
+        let fee_collector = self.get_config(ctx.store)?.fee_collector;
+        if msg_sender != fee_collector {
+            anyhow::bail!("only the fee collector can withdraw fees");
+        }
+
+        let balance = self.get_fee_balance(ctx.store, &cw20)?;
+        let amount = amount.unwrap_or(balance);
+        if amount.is_zero() {
+            anyhow::bail!("no fees to withdraw");
+        }
+        if amount > balance {
+            anyhow::bail!("cannot withdraw {} of {} in fees", amount, balance);
+        }
+
+        FEE_BALANCES.save(ctx.store, asset_key(&cw20), &(balance - amount))?;
+
+        self.send_funds(ctx, fee_collector.to_string(), amount, cw20)?;
+
+        ctx.response_mut().add_event(FeesWithdrawnEvent {
+            recipient: fee_collector.to_string(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_fee_balance(&self, store: &dyn Storage, cw20: &Option<String>) -> Result<Uint128> {
+        Ok(FEE_BALANCES.may_load(store, asset_key(cw20))?.unwrap_or_default())
+    }
+}
+
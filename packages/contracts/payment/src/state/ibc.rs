@@ -1,12 +1,13 @@
 use cosmwasm_std::{
-    from_binary, from_json, BankMsg, Coin, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, Storage
+    from_binary, from_json, BankMsg, Coin, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, Storage, Uint128
 };
 use cw_storage_plus::Item;
 use shared::{ibc::{
+    ack_result,
     event::{IbcChannelCloseEvent, IbcChannelConnectEvent},
     validate_ibc_channel_order_and_version,
-}, msg::contract::payment::IbcExecuteMsg};
-use anyhow::Result;
+}, msg::contract::{payment::{event::PurchaseBatchEvent, IbcExecuteMsg}, warehouse::{BatchItem, IbcExecuteMsg as WarehouseIbcExecuteMsg}}};
+use anyhow::{anyhow, Result};
 
 use super::{purchase::PURCHASE_DENOM, State, StateContext};
 
@@ -62,10 +63,14 @@ impl State<'_> {
                 match msg {
                     IbcExecuteMsg::Refund{ refunds } => {
                         for refund in refunds {
-                            ctx.response_mut().add_message(BankMsg::Send {
-                                to_address: refund.recipient,
-                                amount: vec![Coin::new(refund.amount, PURCHASE_DENOM)],
-                            })
+                            self.deliver(ctx, refund.recipient, refund.amount, refund.cw20)?;
+                        }
+
+                        Ok(())
+                    },
+                    IbcExecuteMsg::Settle{ payouts } => {
+                        for payout in payouts {
+                            self.send_payout(ctx, payout)?;
                         }
 
                         Ok(())
@@ -74,13 +79,63 @@ impl State<'_> {
             })
     }
 
-    pub fn handle_ibc_packet_ack(&self, _ack: IbcPacketAckMsg) -> Result<()> {
-        // TODO - decode the payload and see if we got an error, so we can refund users on failure
+    pub fn handle_ibc_packet_ack(&self, ctx: &mut StateContext, ack: IbcPacketAckMsg) -> Result<()> {
+        match from_json(&ack.original_packet.data) {
+            // batches get their per-item results back, anything rejected was already refunded by the warehouse
+            // but a rejected batch never made it in at all, so the whole deposit is still here
+            Ok(WarehouseIbcExecuteMsg::PurchaseBatch { spender, items, cw20, .. }) => {
+                match ack_result(&ack.acknowledgement.data) {
+                    Ok(data) => {
+                        let results = from_json(data)?;
+                        ctx.response_mut().add_event(PurchaseBatchEvent { spender, results });
+                    },
+                    Err(_) => self.credit_batch_deposit(ctx, &spender, &items, &cw20)?,
+                }
+            },
+            Ok(WarehouseIbcExecuteMsg::SubscriptionPurchase { subscription_id, fees, .. }) => {
+                self.handle_subscription_ack(ctx, subscription_id, fees, ack_result(&ack.acknowledgement.data))?;
+            },
+            // a rejected purchase or pledge never made it into the warehouse, so it will never refund it
+            Ok(WarehouseIbcExecuteMsg::Purchase { spender, fees, cw20, .. }) => {
+                if ack_result(&ack.acknowledgement.data).is_err() {
+                    self.credit_claimable(ctx, &spender, fees, &cw20)?;
+                }
+            },
+            Ok(WarehouseIbcExecuteMsg::Pledge { spender, fees, .. }) => {
+                if ack_result(&ack.acknowledgement.data).is_err() {
+                    self.credit_claimable(ctx, &spender, fees, &None)?;
+                }
+            },
+            _ => {}
+        }
+
         Ok(())
     }
 
-    pub fn handle_ibc_packet_timeout(&self, _msg: IbcPacketTimeoutMsg) -> Result<()> {
-        // TODO - decode the payload and see if we got an error, so we can refund users on failure
+    pub fn handle_ibc_packet_timeout(&self, ctx: &mut StateContext, msg: IbcPacketTimeoutMsg) -> Result<()> {
+        match from_json(&msg.packet.data) {
+            Ok(WarehouseIbcExecuteMsg::SubscriptionPurchase { subscription_id, fees, .. }) => {
+                self.handle_subscription_ack(ctx, subscription_id, fees, Err(anyhow!("purchase timed out")))?;
+            },
+            Ok(WarehouseIbcExecuteMsg::Purchase { spender, fees, cw20, .. }) => {
+                self.credit_claimable(ctx, &spender, fees, &cw20)?;
+            },
+            Ok(WarehouseIbcExecuteMsg::PurchaseBatch { spender, items, cw20, .. }) => {
+                self.credit_batch_deposit(ctx, &spender, &items, &cw20)?;
+            },
+            Ok(WarehouseIbcExecuteMsg::Pledge { spender, fees, .. }) => {
+                self.credit_claimable(ctx, &spender, fees, &None)?;
+            },
+            _ => {}
+        }
+
         Ok(())
     }
-}
\ No newline at end of file
+
+    // the deposits were checked to add up to the funds sent when the batch went out
+    fn credit_batch_deposit(&self, ctx: &mut StateContext, spender: &str, items: &[BatchItem], cw20: &Option<String>) -> Result<()> {
+        let deposit: Uint128 = items.iter().map(|item| item.deposit).sum();
+
+        self.credit_claimable(ctx, spender, deposit, cw20)
+    }
+}
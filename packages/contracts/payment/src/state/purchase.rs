@@ -1,6 +1,6 @@
-use cosmwasm_std::{to_json_binary, Coin, IbcMsg, IbcTimeout, MessageInfo, Storage};
+use cosmwasm_std::{to_json_binary, BankMsg, Coin, CosmosMsg, IbcMsg, IbcTimeout, MessageInfo, Storage, Uint128, WasmMsg};
 use cw_storage_plus::{Bound, Map};
-use shared::{ibc::TIMEOUT_SECONDS, msg::{contract::warehouse::{event::AddProductEvent, NewProduct}, product::{Product, ProductId}, purchase::{Purchase, PurchaseId}}};
+use shared::{ibc::TIMEOUT_SECONDS, msg::{contract::warehouse::{event::AddProductEvent, BatchItem, Coupon, DemandId, HoldId, NewProduct}, cw20::Cw20ExecuteMsg, product::{Product, ProductId, VariantId}, purchase::{Purchase, PurchaseId}}};
 use anyhow::{Result, anyhow};
 
 use super::{State, StateContext};
@@ -10,27 +10,73 @@ const PURCHASES: Map<PurchaseId, Purchase> = Map::new("purchases");
 pub const PURCHASE_DENOM: &str = "ukuji";
 
 impl State<'_> {
-    pub fn purchase_send(&self, ctx: &mut StateContext, owner: String, info: MessageInfo, product_id: ProductId, quantity: u32) -> Result<()> {
+    // fees are either the native funds sent, or the tokens of an allowlisted cw20 (see `receive_cw20`)
+    pub fn purchase_send(&self, ctx: &mut StateContext, owner: String, spender: String, fees: Uint128, cw20: Option<String>, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32, hold_id: Option<HoldId>, coupon: Option<Coupon>, referrer: Option<String>) -> Result<()> {
         // would be nice to use Interchain Queries to early-exit if there's not enough funds
         // it's just an optimization though, since the purchase should always be confirmed in the warehouse last-minute
         // and we should handle failures in the ack to return funds to the user if IBC fails anyway
 
-        let fees = info.funds.iter().find_map(|coin| {
-            if coin.denom == PURCHASE_DENOM {
-                Some(coin.amount)
-            } else {
-                None
-            }
-        }).ok_or_else(|| anyhow!(format!("must send {} to purchase", PURCHASE_DENOM)))?;
-
         let msg = shared::msg::contract::warehouse::IbcExecuteMsg::Purchase {
             owner,
-            spender: info.sender.to_string(),
+            spender,
             fees,
+            cw20,
             product_id,
+            variant_id,
+            quantity,
+            hold_id,
+            coupon,
+            referrer,
+        };
+
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    pub fn purchase_batch_send(&self, ctx: &mut StateContext, owner: String, spender: String, fees: Uint128, cw20: Option<String>, items: Vec<BatchItem>, atomic: bool, referrer: Option<String>) -> Result<()> {
+        if items.is_empty() {
+            anyhow::bail!("batch must have at least one item");
+        }
+        // each item's deposit is checked against its own cost, and refunded on its own if it fails
+        let deposits: Uint128 = items.iter().map(|item| item.deposit).sum();
+        if deposits != fees {
+            anyhow::bail!("sent {} but the items' deposits add up to {}", fees, deposits);
+        }
+
+        let msg = shared::msg::contract::warehouse::IbcExecuteMsg::PurchaseBatch {
+            owner,
+            spender,
+            items,
+            atomic,
+            referrer,
+            cw20,
+        };
+
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    pub fn pledge_send(&self, ctx: &mut StateContext, owner: String, info: MessageInfo, demand_id: DemandId, quantity: u32) -> Result<()> {
+        // as with purchases, the funds stay here until the warehouse settles or refunds them
+        let fees = purchase_funds(&info)?;
+
+        let msg = shared::msg::contract::warehouse::IbcExecuteMsg::Pledge {
+            owner,
+            spender: info.sender.to_string(),
+            demand_id,
             quantity,
+            fees,
         };
 
+        self.send_warehouse_packet(ctx, &msg)
+    }
+
+    // refunds and payouts go back in whatever the purchase was paid with
+    pub fn send_funds(&self, ctx: &mut StateContext, recipient: String, amount: Uint128, cw20: Option<String>) -> Result<()> {
+        ctx.response_mut().add_message(funds_msg(recipient, amount, cw20)?);
+
+        Ok(())
+    }
+
+    pub fn send_warehouse_packet(&self, ctx: &mut StateContext, msg: &shared::msg::contract::warehouse::IbcExecuteMsg) -> Result<()> {
         // outbound IBC message, where packet is then received on other chain
         let channel_id = self
             .get_ibc_channel(ctx.store)?
@@ -39,10 +85,39 @@ impl State<'_> {
 
         ctx.response_mut().add_message(IbcMsg::SendPacket {
             channel_id,
-            data: to_json_binary(&msg)?,
+            data: to_json_binary(msg)?,
             timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(TIMEOUT_SECONDS)),
         });
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// balances are kept per cw20 contract, or the native denom
+pub fn asset_key(cw20: &Option<String>) -> &str {
+    cw20.as_deref().unwrap_or(PURCHASE_DENOM)
+}
+
+pub fn purchase_funds(info: &MessageInfo) -> Result<Uint128> {
+    info.funds.iter().find_map(|coin| {
+        if coin.denom == PURCHASE_DENOM {
+            Some(coin.amount)
+        } else {
+            None
+        }
+    }).ok_or_else(|| anyhow!(format!("must send {} to purchase", PURCHASE_DENOM)))
+}
+
+pub fn funds_msg(recipient: String, amount: Uint128, cw20: Option<String>) -> Result<CosmosMsg> {
+    Ok(match cw20 {
+        Some(cw20) => WasmMsg::Execute {
+            contract_addr: cw20,
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer { recipient, amount })?,
+            funds: vec![],
+        }.into(),
+        None => BankMsg::Send {
+            to_address: recipient,
+            amount: vec![Coin::new(amount, PURCHASE_DENOM)],
+        }.into(),
+    })
+}
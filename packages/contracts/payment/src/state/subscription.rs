@@ -0,0 +1,202 @@
+use cosmwasm_std::{from_json, Addr, Binary, MessageInfo, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use shared::msg::{contract::{payment::{event::SubscriptionEvent, Subscription, SubscriptionId, SubscriptionStatus}, warehouse::IbcExecuteMsg as WarehouseIbcExecuteMsg}, product::{ProductId, VariantId}};
+use anyhow::{Context, Result};
+
+use super::{purchase::purchase_funds, State, StateContext};
+
+const SUBSCRIPTION_ID: Item<SubscriptionId> = Item::new("subscription-id");
+const SUBSCRIPTIONS: Map<SubscriptionId, Subscription> = Map::new("subscriptions");
+const SUBSCRIBER_SUBSCRIPTIONS: Map<(&Addr, SubscriptionId), ()> = Map::new("subscriber-subscriptions");
+// active subscriptions, keyed by when they're next due (in nanos)
+const DUE_SUBSCRIPTIONS: Map<(u64, SubscriptionId), ()> = Map::new("due-subscriptions");
+
+const DEFAULT_CRANK_LIMIT: u32 = 10;
+
+impl State<'_> {
+    pub fn subscribe(&self, ctx: &mut StateContext, info: MessageInfo, owner: String, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32, periods: u32, period_seconds: u64, per_period: Uint128) -> Result<SubscriptionId> {
+        if quantity == 0 || periods == 0 {
+            anyhow::bail!("must subscribe to at least one unit for at least one period");
+        }
+        if period_seconds == 0 {
+            anyhow::bail!("period must be at least one second");
+        }
+        if per_period.is_zero() {
+            anyhow::bail!("must pay something per period");
+        }
+
+        let deposit = purchase_funds(&info)?;
+        let expected = per_period.checked_mul(Uint128::from(periods))?;
+        if deposit != expected {
+            anyhow::bail!("deposit must be exactly {} ({} per period for {} periods), got {}", expected, per_period, periods, deposit);
+        }
+
+        let id = SUBSCRIPTION_ID.may_load(ctx.store)?.unwrap_or_default();
+        SUBSCRIPTION_ID.save(ctx.store, &(id + 1))?;
+
+        let subscription = Subscription {
+            id,
+            subscriber: info.sender,
+            owner,
+            product_id,
+            variant_id,
+            quantity,
+            period_seconds,
+            per_period,
+            periods_remaining: periods,
+            deposit,
+            // the first period can be placed right away
+            next_at: self.env.block.time,
+            status: SubscriptionStatus::Active,
+            purchase_ids: Vec::new(),
+            skipped: 0,
+            failed: 0,
+        };
+
+        SUBSCRIBER_SUBSCRIPTIONS.save(ctx.store, (&subscription.subscriber, id), &())?;
+        DUE_SUBSCRIPTIONS.save(ctx.store, (subscription.next_at.nanos(), id), &())?;
+        self.save_subscription(ctx, &subscription)?;
+
+        Ok(id)
+    }
+
+    // anyone can crank, the purchases are paid for out of each subscription's own deposit
+    pub fn crank_subscriptions(&self, ctx: &mut StateContext, limit: Option<u32>) -> Result<()> {
+        let now = self.env.block.time.nanos();
+
+        let due = DUE_SUBSCRIPTIONS
+            .keys(ctx.store, None, Some(Bound::inclusive((now, SubscriptionId::MAX))), Order::Ascending)
+            .take(limit.unwrap_or(DEFAULT_CRANK_LIMIT) as usize)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        if due.is_empty() {
+            anyhow::bail!("no subscriptions are due");
+        }
+
+        for (at, id) in due {
+            DUE_SUBSCRIPTIONS.remove(ctx.store, (at, id));
+
+            let mut subscription = SUBSCRIPTIONS.load(ctx.store, id)?;
+            subscription.deposit -= subscription.per_period;
+            subscription.periods_remaining -= 1;
+
+            self.send_warehouse_packet(ctx, &WarehouseIbcExecuteMsg::SubscriptionPurchase {
+                owner: subscription.owner.clone(),
+                spender: subscription.subscriber.to_string(),
+                subscription_id: id,
+                product_id: subscription.product_id,
+                variant_id: subscription.variant_id,
+                quantity: subscription.quantity,
+                fees: subscription.per_period,
+            })?;
+
+            self.schedule_next_period(ctx, &mut subscription)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn skip_subscription_period(&self, ctx: &mut StateContext, msg_sender: Addr, subscription_id: SubscriptionId) -> Result<()> {
+        let mut subscription = self.load_active_subscription(ctx.store, &msg_sender, subscription_id)?;
+
+        DUE_SUBSCRIPTIONS.remove(ctx.store, (subscription.next_at.nanos(), subscription_id));
+        subscription.periods_remaining -= 1;
+        subscription.skipped += 1;
+
+        self.schedule_next_period(ctx, &mut subscription)
+    }
+
+    pub fn cancel_subscription(&self, ctx: &mut StateContext, msg_sender: Addr, subscription_id: SubscriptionId) -> Result<()> {
+        let mut subscription = self.load_active_subscription(ctx.store, &msg_sender, subscription_id)?;
+
+        DUE_SUBSCRIPTIONS.remove(ctx.store, (subscription.next_at.nanos(), subscription_id));
+        subscription.periods_remaining = 0;
+        subscription.status = SubscriptionStatus::Cancelled;
+
+        // anything still in flight comes back to the deposit if it fails, and can be withdrawn from there
+        let deposit = std::mem::take(&mut subscription.deposit);
+        if !deposit.is_zero() {
+            self.credit_claimable(ctx, subscription.subscriber.as_str(), deposit, &None)?;
+        }
+
+        self.save_subscription(ctx, &subscription)
+    }
+
+    pub fn withdraw_subscription_deposit(&self, ctx: &mut StateContext, msg_sender: Addr, subscription_id: SubscriptionId) -> Result<()> {
+        let mut subscription = self.get_subscription(ctx.store, subscription_id)?;
+        if subscription.subscriber != msg_sender {
+            anyhow::bail!("only the subscriber can do this");
+        }
+
+        let unused = subscription.unused_deposit();
+        if unused.is_zero() {
+            anyhow::bail!("no unused deposit to withdraw");
+        }
+        subscription.deposit -= unused;
+
+        self.send_funds(ctx, subscription.subscriber.to_string(), unused, None)?;
+
+        self.save_subscription(ctx, &subscription)
+    }
+
+    // a period that didn't make it into the warehouse goes back to the deposit, and isn't retried
+    pub fn handle_subscription_ack(&self, ctx: &mut StateContext, subscription_id: SubscriptionId, fees: Uint128, result: Result<Binary>) -> Result<()> {
+        let mut subscription = self.get_subscription(ctx.store, subscription_id)?;
+
+        match result {
+            Ok(data) => subscription.purchase_ids.push(from_json(data)?),
+            Err(_) => {
+                subscription.deposit += fees;
+                subscription.failed += 1;
+            }
+        }
+
+        self.save_subscription(ctx, &subscription)
+    }
+
+    pub fn get_subscription(&self, store: &dyn Storage, subscription_id: SubscriptionId) -> Result<Subscription> {
+        SUBSCRIPTIONS.may_load(store, subscription_id)?.context(format!("subscription {} not found", subscription_id))
+    }
+
+    pub fn list_subscriptions(&self, store: &dyn Storage, subscriber: String, start_after: Option<SubscriptionId>, limit: Option<u32>) -> Result<Vec<Subscription>> {
+        let subscriber = self.api.addr_validate(&subscriber)?;
+
+        SUBSCRIBER_SUBSCRIPTIONS
+            .prefix(&subscriber)
+            .keys(store, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .map(|id| self.get_subscription(store, id?))
+            .collect()
+    }
+
+    fn schedule_next_period(&self, ctx: &mut StateContext, subscription: &mut Subscription) -> Result<()> {
+        if subscription.periods_remaining == 0 {
+            subscription.status = SubscriptionStatus::Completed;
+        } else {
+            // from the previous due time rather than now, so a late crank doesn't push the schedule back
+            subscription.next_at = subscription.next_at.plus_seconds(subscription.period_seconds);
+            DUE_SUBSCRIPTIONS.save(ctx.store, (subscription.next_at.nanos(), subscription.id), &())?;
+        }
+
+        self.save_subscription(ctx, subscription)
+    }
+
+    fn load_active_subscription(&self, store: &dyn Storage, msg_sender: &Addr, subscription_id: SubscriptionId) -> Result<Subscription> {
+        let subscription = self.get_subscription(store, subscription_id)?;
+        if subscription.subscriber != *msg_sender {
+            anyhow::bail!("only the subscriber can do this");
+        }
+        if subscription.status != SubscriptionStatus::Active {
+            anyhow::bail!("subscription {} is no longer active", subscription_id);
+        }
+
+        Ok(subscription)
+    }
+
+    fn save_subscription(&self, ctx: &mut StateContext, subscription: &Subscription) -> Result<()> {
+        SUBSCRIPTIONS.save(ctx.store, subscription.id, subscription)?;
+        ctx.response_mut().add_event(SubscriptionEvent::from(subscription));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use cosmwasm_std::{from_json, to_json_binary, Coin, IbcMsg, IbcTimeout, Reply, SubMsg, Uint128};
+use cw_storage_plus::Map;
+use shared::msg::contract::{payment::{event::PayoutTransferFailedEvent, IbcLifecycleComplete, InFlightTransfer}, warehouse::PayoutRoute};
+
+use super::{purchase::PURCHASE_DENOM, State, StateContext};
+
+pub const TRANSFER_REPLY_ID: u64 = 1;
+
+// relayers can be slow across hops, more generous than our own packets
+const TRANSFER_TIMEOUT_SECONDS: u64 = 60 * 10;
+
+// payout transfers waiting for their ack, keyed by (channel, sequence)
+const IN_FLIGHT_TRANSFERS: Map<(&str, u64), InFlightTransfer> = Map::new("in-flight-transfers");
+
+impl State<'_> {
+    // the ibc-hooks memo gets us a sudo callback with the outcome, the reply gets us the sequence to match it to
+    pub fn send_payout_transfer(&self, ctx: &mut StateContext, recipient: String, amount: Uint128, route: PayoutRoute) -> Result<()> {
+        let msg = IbcMsg::Transfer {
+            channel_id: route.channel_id.clone(),
+            to_address: route.receiver.clone(),
+            amount: Coin::new(amount, PURCHASE_DENOM),
+            timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(TRANSFER_TIMEOUT_SECONDS)),
+            memo: Some(format!(r#"{{"ibc_callback":"{}"}}"#, self.env.contract.address)),
+        };
+
+        let transfer = InFlightTransfer {
+            recipient,
+            amount,
+            channel_id: route.channel_id,
+            receiver: route.receiver,
+        };
+
+        ctx.response_mut().add_raw_submessage(
+            SubMsg::reply_always(msg, TRANSFER_REPLY_ID).with_payload(to_json_binary(&transfer)?)
+        );
+
+        Ok(())
+    }
+
+    pub fn handle_transfer_reply(&self, ctx: &mut StateContext, reply: Reply) -> Result<()> {
+        let transfer: InFlightTransfer = from_json(&reply.payload)?;
+
+        // the transfer was reverted (e.g. a closed channel) and the funds are still here
+        let result = match reply.result.into_result() {
+            Ok(result) => result,
+            Err(_) => return self.credit_claimable(ctx, &transfer.recipient, transfer.amount, &None),
+        };
+
+        let sequence = result
+            .events
+            .into_iter()
+            .find(|evt| evt.ty == "send_packet")
+            .and_then(|evt| evt.attributes.into_iter().find(|attr| attr.key == "packet_sequence"))
+            .and_then(|attr| attr.value.parse::<u64>().ok());
+
+        // erroring here would revert the whole settlement, and the warehouse has already marked it settled
+        // without a sequence we can't match the callback, so it's credited here instead
+        let sequence = match sequence {
+            Some(sequence) => sequence,
+            None => return self.credit_claimable(ctx, &transfer.recipient, transfer.amount, &None),
+        };
+
+        IN_FLIGHT_TRANSFERS.save(ctx.store, (&transfer.channel_id, sequence), &transfer)?;
+
+        Ok(())
+    }
+
+    pub fn handle_ibc_lifecycle_complete(&self, ctx: &mut StateContext, msg: IbcLifecycleComplete) -> Result<()> {
+        let (channel, sequence, failed) = match msg {
+            IbcLifecycleComplete::IbcAck { channel, sequence, success, .. } => (channel, sequence, !success),
+            IbcLifecycleComplete::IbcTimeout { channel, sequence } => (channel, sequence, true),
+        };
+
+        let transfer = match IN_FLIGHT_TRANSFERS.may_load(ctx.store, (&channel, sequence))? {
+            Some(transfer) => transfer,
+            None => anyhow::bail!("no payout transfer for {} sequence {}", channel, sequence),
+        };
+        IN_FLIGHT_TRANSFERS.remove(ctx.store, (&channel, sequence));
+
+        // the transfer module has already put the funds back in the contract
+        if failed {
+            self.credit_claimable(ctx, &transfer.recipient, transfer.amount, &None)?;
+
+            ctx.response_mut().add_event(PayoutTransferFailedEvent {
+                recipient: transfer.recipient,
+                amount: transfer.amount,
+                channel,
+                sequence,
+            });
+        }
+
+        Ok(())
+    }
+}
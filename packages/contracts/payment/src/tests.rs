@@ -0,0 +1,170 @@
+use anyhow::anyhow;
+use cosmwasm_std::{from_json, testing::{mock_dependencies, mock_env, mock_ibc_packet_ack, mock_ibc_packet_timeout, mock_info, MockApi, MockQuerier, MockStorage}, to_json_binary, Addr, Deps, Empty, Event, IbcAcknowledgement, OwnedDeps, Reply, SubMsgResponse, SubMsgResult, Uint128};
+use cw_storage_plus::Item;
+use serde::{Deserialize, Serialize};
+use shared::{ibc::{ack_fail, ack_success, ack_success_with_data}, msg::contract::{payment::{Config, InFlightTransfer, QueryMsg}, warehouse::{BatchItem, BatchItemResult, IbcExecuteMsg as WarehouseIbcExecuteMsg}}};
+
+use crate::{entry, state::transfer::TRANSFER_REPLY_ID};
+
+// the config as it was stored before the fee collector and auto push existed
+#[derive(Serialize, Deserialize)]
+struct LegacyConfig {
+    admin: Addr,
+}
+
+const WAREHOUSE_CHANNEL: &str = "channel-warehouse";
+
+fn instantiated() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    let admin = deps.api.addr_make("admin");
+    entry::instantiate(deps.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), Empty {}).unwrap();
+
+    deps
+}
+
+fn batch(cw20: Option<&str>) -> WarehouseIbcExecuteMsg {
+    let item = |product_id, deposit: u128| BatchItem {
+        product_id,
+        variant_id: None,
+        quantity: 1,
+        deposit: deposit.into(),
+        hold_id: None,
+        coupon: None,
+    };
+
+    WarehouseIbcExecuteMsg::PurchaseBatch {
+        owner: "buyer-owner".to_string(),
+        spender: "buyer".to_string(),
+        items: vec![item(0, 100), item(1, 250)],
+        atomic: true,
+        referrer: None,
+        cw20: cw20.map(str::to_string),
+    }
+}
+
+fn claimable(deps: Deps, address: &str, cw20: Option<&str>) -> Uint128 {
+    from_json(entry::query(deps, mock_env(), QueryMsg::Claimable { address: address.to_string(), cw20: cw20.map(str::to_string) }).unwrap()).unwrap()
+}
+
+#[test]
+fn migrate_fills_in_fields_missing_from_an_old_config() {
+    let mut deps = mock_dependencies();
+    let admin = deps.api.addr_make("admin");
+    cw2::set_contract_version(deps.as_mut().storage, "payment", "0.0.0").unwrap();
+    Item::new("config").save(deps.as_mut().storage, &LegacyConfig { admin: admin.clone() }).unwrap();
+
+    entry::migrate(deps.as_mut(), mock_env(), Empty {}).unwrap();
+
+    let config: Config = from_json(entry::query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config, Config {
+        admin: admin.clone(),
+        fee_collector: admin,
+        auto_push: false,
+    });
+}
+
+#[test]
+fn failed_payout_transfer_is_credited_to_the_recipient() {
+    let mut deps = instantiated();
+
+    let transfer = InFlightTransfer {
+        recipient: "merchant".to_string(),
+        amount: Uint128::new(100),
+        channel_id: "channel-0".to_string(),
+        receiver: "merchant-remote".to_string(),
+    };
+    entry::reply(deps.as_mut(), mock_env(), Reply {
+        id: TRANSFER_REPLY_ID,
+        payload: to_json_binary(&transfer).unwrap(),
+        gas_used: 0,
+        result: SubMsgResult::Err("channel is closed".to_string()),
+    }).unwrap();
+
+    assert_eq!(claimable(deps.as_ref(), "merchant", None), Uint128::new(100));
+}
+
+#[test]
+fn accepted_batch_reports_its_results_and_credits_nothing() {
+    let mut deps = instantiated();
+    let results = vec![BatchItemResult { product_id: 0, purchase_id: Some(0), error: None }];
+    let ack = IbcAcknowledgement::new(ack_success_with_data(to_json_binary(&results).unwrap()));
+
+    let resp = entry::ibc_packet_ack(deps.as_mut(), mock_env(), mock_ibc_packet_ack(WAREHOUSE_CHANNEL, &batch(None), ack).unwrap()).unwrap();
+
+    assert!(resp.events.iter().any(|evt| evt.ty == "purchase-batch"));
+    assert_eq!(claimable(deps.as_ref(), "buyer", None), Uint128::zero());
+}
+
+#[test]
+fn rejected_batch_credits_the_whole_deposit() {
+    let mut deps = instantiated();
+    let ack = IbcAcknowledgement::new(ack_fail(anyhow!("batch must have at least one item")));
+
+    let resp = entry::ibc_packet_ack(deps.as_mut(), mock_env(), mock_ibc_packet_ack(WAREHOUSE_CHANNEL, &batch(Some("kujira1token")), ack).unwrap()).unwrap();
+
+    assert!(!resp.events.iter().any(|evt| evt.ty == "purchase-batch"));
+    assert_eq!(claimable(deps.as_ref(), "buyer", Some("kujira1token")), Uint128::new(350));
+    assert_eq!(claimable(deps.as_ref(), "buyer", None), Uint128::zero());
+}
+
+#[test]
+fn timed_out_batch_credits_the_whole_deposit() {
+    let mut deps = instantiated();
+
+    entry::ibc_packet_timeout(deps.as_mut(), mock_env(), mock_ibc_packet_timeout(WAREHOUSE_CHANNEL, &batch(None)).unwrap()).unwrap();
+
+    assert_eq!(claimable(deps.as_ref(), "buyer", None), Uint128::new(350));
+}
+
+#[test]
+fn payout_transfer_without_a_sequence_is_credited_instead_of_failing_the_settlement() {
+    let mut deps = instantiated();
+
+    let transfer = InFlightTransfer {
+        recipient: "merchant".to_string(),
+        amount: Uint128::new(100),
+        channel_id: "channel-0".to_string(),
+        receiver: "merchant-remote".to_string(),
+    };
+    entry::reply(deps.as_mut(), mock_env(), Reply {
+        id: TRANSFER_REPLY_ID,
+        payload: to_json_binary(&transfer).unwrap(),
+        gas_used: 0,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![Event::new("send_packet").add_attribute("packet_sequence", "not-a-number")],
+            data: None,
+            msg_responses: Vec::new(),
+        }),
+    }).unwrap();
+
+    assert_eq!(claimable(deps.as_ref(), "merchant", None), Uint128::new(100));
+}
+
+#[test]
+fn rejected_or_timed_out_purchase_credits_its_deposit() {
+    let mut deps = instantiated();
+    let purchase = |cw20: Option<&str>| WarehouseIbcExecuteMsg::Purchase {
+        owner: "buyer-owner".to_string(),
+        spender: "buyer".to_string(),
+        product_id: 0,
+        variant_id: None,
+        quantity: 1,
+        hold_id: None,
+        coupon: None,
+        referrer: None,
+        cw20: cw20.map(str::to_string),
+        fees: Uint128::new(100),
+    };
+
+    let ack = IbcAcknowledgement::new(ack_fail(anyhow!("out of stock")));
+    entry::ibc_packet_ack(deps.as_mut(), mock_env(), mock_ibc_packet_ack(WAREHOUSE_CHANNEL, &purchase(Some("kujira1token")), ack).unwrap()).unwrap();
+    assert_eq!(claimable(deps.as_ref(), "buyer", Some("kujira1token")), Uint128::new(100));
+
+    entry::ibc_packet_timeout(deps.as_mut(), mock_env(), mock_ibc_packet_timeout(WAREHOUSE_CHANNEL, &purchase(None)).unwrap()).unwrap();
+    assert_eq!(claimable(deps.as_ref(), "buyer", None), Uint128::new(100));
+
+    // an accepted purchase is the warehouse's to settle or refund
+    let ack = IbcAcknowledgement::new(ack_success());
+    entry::ibc_packet_ack(deps.as_mut(), mock_env(), mock_ibc_packet_ack(WAREHOUSE_CHANNEL, &purchase(None), ack).unwrap()).unwrap();
+    assert_eq!(claimable(deps.as_ref(), "buyer", None), Uint128::new(100));
+}
@@ -1,13 +1,13 @@
 use cosmwasm_std::{
-    entry_point, Deps, DepsMut, Empty, Env, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo, QueryResponse, Response
+    entry_point, Addr, Deps, DepsMut, Empty, Env, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo, QueryResponse, Reply, Response
 };
 use cw2::{get_contract_version, set_contract_version};
 use shared::{
-    msg::contract::warehouse::{ExecuteMsg, GroupInfo, InfoResp, QueryMsg}, response::{QueryResponseExt, ResponseBuilder},
+    msg::contract::{payment::SudoMsg, warehouse::{ExecuteMsg, GroupInfo, InfoResp, InstantiateMsg, MigrateMsg, QueryMsg}}, response::{QueryResponseExt, ResponseBuilder},
 };
 use anyhow::Result;
 
-use crate::state::{ibc::IbcChannelKind, State, StateContext};
+use crate::state::{ibc::IbcChannelKind, slash::SLASH_REPLY_ID, State, StateContext};
 // version info for migration info
 const CONTRACT_NAME: &str = "warehouse";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -15,13 +15,16 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
-    _msg: Empty,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
 ) -> Result<Response> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    Ok(Response::default())
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+    state.init_config(&mut ctx, info.sender, msg.payment_chain_id)?;
+
+    Ok(ctx.response.into_response())
 }
 
 #[entry_point]
@@ -29,11 +32,101 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> R
     let (state, mut ctx) = StateContext::new(deps, env)?;
 
     match msg {
-        ExecuteMsg::AddProduct { product} => {
-            state.add_product(&mut ctx, info.sender, product)?;
+        ExecuteMsg::RegisterMerchant { profile } => {
+            state.register_merchant(&mut ctx, info.sender, profile)?;
+        },
+        ExecuteMsg::UpdateMerchantProfile { merchant, profile } => {
+            state.update_merchant_profile(&mut ctx, info.sender, merchant, profile)?;
+        },
+        ExecuteMsg::DepositBond { } => {
+            state.deposit_bond(&mut ctx, info.sender, info.funds)?;
+        },
+        ExecuteMsg::WithdrawBond { merchant, amount } => {
+            state.withdraw_bond(&mut ctx, info.sender, merchant, amount)?;
+        },
+        ExecuteMsg::GrantRoles { merchant, delegate, roles, expires } => {
+            state.grant_roles(&mut ctx, info.sender, merchant, delegate, roles, expires)?;
+        },
+        ExecuteMsg::RevokeRoles { merchant, delegate } => {
+            state.revoke_roles(&mut ctx, info.sender, merchant, delegate)?;
+        },
+        ExecuteMsg::SetMerchantVerified { merchant, verified } => {
+            state.set_merchant_verified(&mut ctx, info.sender, merchant, verified)?;
+        },
+        ExecuteMsg::SetMerchantTier { merchant, tier } => {
+            state.set_merchant_tier(&mut ctx, info.sender, merchant, tier)?;
+        },
+        ExecuteMsg::SlashBond { merchant, amount, purchase_id } => {
+            state.slash_bond(&mut ctx, info.sender, merchant, amount, purchase_id)?;
+        },
+        ExecuteMsg::ClaimSlashedBond { recipient } => {
+            state.claim_slashed_bond(&mut ctx, recipient)?;
+        },
+        ExecuteMsg::ClaimWithheldPayouts { merchant } => {
+            state.claim_withheld_payouts(&mut ctx, info.sender, merchant)?;
+        },
+        ExecuteMsg::AddProduct { merchant, product } => {
+            state.add_product(&mut ctx, info.sender, merchant, product)?;
+        },
+        ExecuteMsg::ProposeProductTransfer { product_id, new_owner } => {
+            state.propose_product_transfer(&mut ctx, info.sender, product_id, new_owner)?;
+        },
+        ExecuteMsg::AcceptProductTransfer { product_id } => {
+            state.accept_product_transfer(&mut ctx, info.sender, product_id)?;
+        },
+        ExecuteMsg::CancelProductTransfer { product_id } => {
+            state.cancel_product_transfer(&mut ctx, info.sender, product_id)?;
+        },
+        ExecuteMsg::Restock { product_id, variant_id, quantity } => {
+            state.restock(&mut ctx, info.sender, product_id, variant_id, quantity)?;
         },
-        ExecuteMsg::ShipGroup { group_id } => {
-            state.ship_group(&mut ctx, info.sender, group_id)?;
+        ExecuteMsg::PlaceHold { product_id, variant_id, quantity, spender } => {
+            state.place_hold(&mut ctx, info.sender, info.funds, product_id, variant_id, quantity, spender)?;
+        },
+        ExecuteMsg::ReleaseHold { id } => {
+            state.release_hold(&mut ctx, info.sender, id)?;
+        },
+        ExecuteMsg::SweepHolds { limit } => {
+            state.sweep_holds(&mut ctx, limit)?;
+        },
+        ExecuteMsg::PostDemand { name, quantity, max_price, bid_deadline, reveal_seconds } => {
+            state.post_demand(&mut ctx, info.sender, name, quantity, max_price, bid_deadline, reveal_seconds)?;
+        },
+        ExecuteMsg::SubmitBid { demand_id, merchant, bid } => {
+            state.submit_bid(&mut ctx, info.sender, demand_id, merchant, bid)?;
+        },
+        ExecuteMsg::RevealBid { demand_id, merchant, price, salt } => {
+            state.reveal_bid(&mut ctx, info.sender, demand_id, merchant, price, salt)?;
+        },
+        ExecuteMsg::SettleDemand { demand_id } => {
+            state.settle_demand(&mut ctx, demand_id)?;
+        },
+        ExecuteMsg::LockGroup { group_id } => {
+            state.lock_group(&mut ctx, info.sender, group_id)?;
+        },
+        ExecuteMsg::PackGroup { group_id } => {
+            state.pack_group(&mut ctx, info.sender, group_id)?;
+        },
+        ExecuteMsg::ShipGroup { group_id, carrier, tracking_ref, purchase_ids } => {
+            state.ship_group(&mut ctx, info.sender, group_id, carrier, tracking_ref, purchase_ids)?;
+        },
+        ExecuteMsg::DeliverGroup { group_id } => {
+            state.deliver_group(&mut ctx, info.sender, group_id)?;
+        },
+        ExecuteMsg::CompleteGroup { group_id } => {
+            state.complete_group(&mut ctx, info.sender, group_id)?;
+        },
+        ExecuteMsg::ReleaseGroup { group_id } => {
+            state.release_group(&mut ctx, group_id)?;
+        },
+        ExecuteMsg::ResolveDispute { purchase_id, resolution } => {
+            state.resolve_dispute(&mut ctx, info.sender, purchase_id, resolution)?;
+        },
+        ExecuteMsg::CloseDispute { purchase_id } => {
+            state.close_dispute(&mut ctx, info.sender, purchase_id)?;
+        },
+        ExecuteMsg::UpdateConfig { dispute_window_seconds, arbiter, payment_chain_id, hold_seconds, platform_fee, payment_transfer_channel, hold_deposit, hold_relayers } => {
+            state.update_config(&mut ctx, info.sender, dispute_window_seconds, arbiter, payment_chain_id, hold_seconds, platform_fee, payment_transfer_channel, hold_deposit, hold_relayers)?;
         }
     }
 
@@ -68,6 +161,94 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<QueryResponse> {
                 .collect::<Result<Vec<GroupInfo>>>()?
                 .query_result()
         }
+        QueryMsg::GetEscrows {ids} => {
+            let escrows = state.get_escrows(store, ids)?;
+            escrows.query_result()
+        },
+        QueryMsg::GetDisputes {ids} => {
+            let disputes = state.get_disputes(store, ids)?;
+            disputes.query_result()
+        },
+        QueryMsg::ListDisputes {unresolved_only, limit, start_after} => {
+            let disputes = state.list_disputes(store, unresolved_only.unwrap_or_default(), limit, start_after)?;
+            disputes.query_result()
+        },
+        QueryMsg::GetWithheldPayouts {merchant} => {
+            let payouts = state.get_withheld_payouts(store, &Addr::unchecked(merchant))?;
+            payouts.query_result()
+        },
+        QueryMsg::GetSlashClaimable {recipient} => {
+            let amount = state.get_slash_claimable(store, &recipient)?;
+            amount.query_result()
+        },
+        QueryMsg::GetMerchantReputation {merchant} => {
+            let reputation = state.get_merchant_reputation(store, &Addr::unchecked(merchant))?;
+            reputation.query_result()
+        },
+        QueryMsg::ListMerchantReviews {merchant, limit, start_after} => {
+            let reviews = state.list_merchant_reviews(store, &Addr::unchecked(merchant), limit, start_after)?;
+            reviews.query_result()
+        },
+        QueryMsg::GetMerchant {merchant} => {
+            let merchant = state.get_merchant(store, &Addr::unchecked(merchant))?;
+            merchant.query_result()
+        },
+        QueryMsg::GetProductTransfer {product_id} => {
+            let new_owner = state.get_product_transfer(store, product_id)?;
+            new_owner.query_result()
+        },
+        QueryMsg::GetHolds {ids} => {
+            let holds = state.get_holds(store, ids)?;
+            holds.query_result()
+        },
+        QueryMsg::ListHolds {spender, limit, start_after} => {
+            let holds = state.list_holds(store, spender, limit, start_after)?;
+            holds.query_result()
+        },
+        QueryMsg::GetDemands {ids} => {
+            let demands = state.get_demands(store, ids)?;
+            demands.query_result()
+        },
+        QueryMsg::ListDemands {open_only, limit, start_after} => {
+            let demands = state.list_demands(store, open_only.unwrap_or_default(), limit, start_after)?;
+            demands.query_result()
+        },
+        QueryMsg::ListBids {demand_id, limit, start_after} => {
+            let bids = state.list_bids(store, demand_id, limit, start_after)?;
+            bids.query_result()
+        },
+        QueryMsg::ListPledges {demand_id, limit, start_after} => {
+            let pledges = state.list_pledges(store, demand_id, limit, start_after)?;
+            pledges.query_result()
+        },
+        QueryMsg::QuotePurchase {product_id, variant_id, quantity, spender, coupon} => {
+            let quote = state.quote_purchase(store, product_id, variant_id, quantity, spender, coupon)?;
+            quote.query_result()
+        },
+        QueryMsg::ReferralLeaderboard {product_id, limit, start_after} => {
+            let stats = state.referral_leaderboard(store, product_id, limit, start_after)?;
+            stats.query_result()
+        },
+        QueryMsg::GetGroupReferrals {group_id} => {
+            let referrals = state.get_group_referrals(store, group_id)?;
+            referrals.query_result()
+        },
+        QueryMsg::GetCouponUses {merchant, nonce} => {
+            let uses = state.get_coupon_uses(store, &Addr::unchecked(merchant), nonce)?;
+            uses.query_result()
+        },
+        QueryMsg::ListDelegates {merchant, limit, start_after} => {
+            let delegates = state.list_delegates(store, &Addr::unchecked(merchant), limit, start_after)?;
+            delegates.query_result()
+        },
+        QueryMsg::ListMerchants {verified_only, limit, start_after} => {
+            let merchants = state.list_merchants(store, verified_only.unwrap_or_default(), limit, start_after)?;
+            merchants.query_result()
+        },
+        QueryMsg::Config {  } => {
+            let config = state.get_config(store)?;
+            config.query_result()
+        },
         QueryMsg::Info {  } => {
             let ibc_payment_channel = state.get_ibc_channel(store, IbcChannelKind::Payment).ok();
             let info = InfoResp {
@@ -79,8 +260,34 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<QueryResponse> {
 }
 
 #[entry_point]
-pub fn migrate(deps: DepsMut, env: Env, msg: Empty) -> Result<Response> {
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response> {
     let (state, mut ctx) = StateContext::new(deps, env)?;
+    state.migrate_config(&mut ctx, msg.payment_chain_id)?;
+
+    Ok(ctx.response.into_response())
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response> {
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+
+    match reply.id {
+        SLASH_REPLY_ID => state.handle_slash_reply(&mut ctx, reply)?,
+        id => anyhow::bail!("unknown reply id {}", id),
+    }
+
+    Ok(ctx.response.into_response())
+}
+
+#[entry_point]
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response> {
+    let (state, mut ctx) = StateContext::new(deps, env)?;
+
+    match msg {
+        SudoMsg::IbcLifecycleComplete(complete) => {
+            state.handle_ibc_lifecycle_complete(&mut ctx, complete)?;
+        }
+    }
 
     Ok(ctx.response.into_response())
 }
@@ -1,4 +1,6 @@
 #![allow(warnings)]
 
 mod entry;
-mod state;
\ No newline at end of file
+mod state;
+#[cfg(test)]
+mod tests;
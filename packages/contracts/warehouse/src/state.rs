@@ -2,6 +2,23 @@ pub mod product;
 pub mod ibc;
 pub mod purchases;
 pub mod group;
+pub mod rules;
+pub mod shipment;
+pub mod config;
+pub mod settlement;
+pub mod dispute;
+pub mod review;
+pub mod merchant;
+pub mod roles;
+pub mod backorder;
+pub mod hold;
+pub mod demand;
+pub mod coupon;
+pub mod referral;
+pub mod quote;
+pub mod overlay;
+pub mod batch;
+pub mod slash;
 
 /// Generally speaking - all entry points get a State (read-only)
 /// instantiate/execute/migrate get that _and_ a StateContext (writable)
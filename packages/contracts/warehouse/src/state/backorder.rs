@@ -0,0 +1,169 @@
+use cosmwasm_std::{Addr, Order, Storage};
+use cw_storage_plus::{Bound, Map};
+use shared::msg::{contract::warehouse::{event::BackorderFilledEvent, GroupId, MerchantRole}, product::{Product, ProductId, VariantId}, purchase::{Purchase, PurchaseId}};
+use anyhow::{Context, Result};
+
+use super::{State, StateContext};
+
+// purchases waiting on stock, in purchase order
+const BACKORDER_QUEUE: Map<(ProductId, PurchaseId), ()> = Map::new("backorder-queue");
+const PRODUCT_BACKORDERED: Map<ProductId, u32> = Map::new("product-backordered");
+const GROUP_BACKORDERED: Map<GroupId, u32> = Map::new("group-backordered");
+
+impl State<'_> {
+    // takes whatever stock is available, and backorders the rest if the product allows pre-orders
+    // returns the number of backordered units
+    pub fn take_stock_or_backorder(&self, ctx: &mut StateContext, product: &Product, variant_id: Option<VariantId>, quantity: u32) -> Result<u32> {
+        let backordered = match product.preorder_cap {
+            Some(cap) => {
+                let backordered = quantity.saturating_sub(product.available_stock(variant_id)?);
+                if backordered > 0 {
+                    let total = PRODUCT_BACKORDERED.may_load(ctx.store, product.id)?.unwrap_or_default() + backordered;
+                    if total > cap {
+                        anyhow::bail!("not enough stock, and pre-orders for product {} would exceed the cap (wanted: {}, cap: {})", product.id, total, cap);
+                    }
+                    PRODUCT_BACKORDERED.save(ctx.store, product.id, &total)?;
+                }
+                backordered
+            },
+            None => 0,
+        };
+
+        self.remove_product_stock(ctx, product.id, variant_id, quantity - backordered)?;
+
+        Ok(backordered)
+    }
+
+    // must be called once the purchase has its id and group
+    pub fn record_backorder(&self, ctx: &mut StateContext, purchase: &Purchase) -> Result<()> {
+        if purchase.backordered == 0 {
+            return Ok(());
+        }
+
+        BACKORDER_QUEUE.save(ctx.store, (purchase.product_id, purchase.id), &())?;
+        GROUP_BACKORDERED.update(ctx.store, purchase.group_id, |x| anyhow::Ok(x.unwrap_or_default() + purchase.backordered))?;
+
+        Ok(())
+    }
+
+    // a cancelled purchase gives up its place in the queue, and its backordered units never existed as stock
+    pub fn release_backorder(&self, ctx: &mut StateContext, purchase: &Purchase) -> Result<()> {
+        if purchase.backordered == 0 {
+            return Ok(());
+        }
+
+        BACKORDER_QUEUE.remove(ctx.store, (purchase.product_id, purchase.id));
+        self.reduce_backordered(ctx, purchase.product_id, purchase.group_id, purchase.backordered)?;
+
+        Ok(())
+    }
+
+    // removed units come out of the backorder first, since they were never in stock
+    // returns how many of the removed units were backordered
+    pub fn reduce_backorder(&self, ctx: &mut StateContext, purchase: &mut Purchase, removed: u32) -> Result<u32> {
+        let from_backorder = removed.min(purchase.backordered);
+        if from_backorder == 0 {
+            return Ok(0);
+        }
+
+        purchase.backordered -= from_backorder;
+        if purchase.backordered == 0 {
+            BACKORDER_QUEUE.remove(ctx.store, (purchase.product_id, purchase.id));
+        }
+        self.reduce_backordered(ctx, purchase.product_id, purchase.group_id, from_backorder)?;
+
+        Ok(from_backorder)
+    }
+
+    pub fn restock(&self, ctx: &mut StateContext, msg_sender: Addr, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32) -> Result<()> {
+        let owner = self.get_product_owner(ctx.store, product_id)?;
+        self.assert_merchant_role(ctx.store, &owner, &msg_sender, MerchantRole::Catalog)?;
+        self.get_product(ctx.store, product_id)?.variant(variant_id)?;
+
+        if quantity == 0 {
+            anyhow::bail!("restock quantity must be greater than zero");
+        }
+
+        let mut remaining = quantity;
+        let mut fulfilled_groups = Vec::new();
+        let mut start_after = None;
+        // one purchase at a time since the queue changes as we go, and a small restock only reaches the front of a long one
+        while remaining > 0 {
+            let purchase_id = match BACKORDER_QUEUE
+                .prefix(product_id)
+                .keys(ctx.store, start_after.map(Bound::exclusive), None, Order::Ascending)
+                .next()
+            {
+                Some(purchase_id) => purchase_id?,
+                None => break,
+            };
+            start_after = Some(purchase_id);
+
+            let mut purchase = self.try_get_purchase(ctx.store, purchase_id)?.context(format!("backordered purchase {} not found", purchase_id))?;
+            if purchase.variant_id != variant_id {
+                continue;
+            }
+
+            let filled = remaining.min(purchase.backordered);
+            remaining -= filled;
+            purchase.backordered -= filled;
+
+            if purchase.backordered == 0 {
+                BACKORDER_QUEUE.remove(ctx.store, (product_id, purchase_id));
+            }
+            if self.reduce_backordered(ctx, product_id, purchase.group_id, filled)? {
+                fulfilled_groups.push(purchase.group_id);
+            }
+            self.save_purchase(ctx, &purchase)?;
+
+            ctx.response_mut().add_event(BackorderFilledEvent {
+                purchase_id,
+                group_id: purchase.group_id,
+                filled,
+                remaining: purchase.backordered,
+            });
+        }
+
+        if remaining > 0 {
+            self.add_product_stock(ctx, product_id, variant_id, remaining)?;
+        }
+
+        // groups that filled up while waiting on stock can auto-ship now
+        for group_id in fulfilled_groups {
+            self.auto_ship_group(ctx, group_id)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn assert_group_fulfilled(&self, store: &dyn Storage, group_id: GroupId) -> Result<()> {
+        let backordered = self.get_group_backordered(store, group_id)?;
+        if backordered > 0 {
+            anyhow::bail!("group {} still has {} backordered units", group_id, backordered);
+        }
+        Ok(())
+    }
+
+    pub fn get_group_backordered(&self, store: &dyn Storage, group_id: GroupId) -> Result<u32> {
+        Ok(GROUP_BACKORDERED.may_load(store, group_id)?.unwrap_or_default())
+    }
+
+    // returns true if the group no longer has any backordered units
+    fn reduce_backordered(&self, ctx: &mut StateContext, product_id: ProductId, group_id: GroupId, units: u32) -> Result<bool> {
+        let product_backordered = PRODUCT_BACKORDERED.may_load(ctx.store, product_id)?.unwrap_or_default().saturating_sub(units);
+        if product_backordered == 0 {
+            PRODUCT_BACKORDERED.remove(ctx.store, product_id);
+        } else {
+            PRODUCT_BACKORDERED.save(ctx.store, product_id, &product_backordered)?;
+        }
+
+        let group_backordered = self.get_group_backordered(ctx.store, group_id)?.saturating_sub(units);
+        if group_backordered == 0 {
+            GROUP_BACKORDERED.remove(ctx.store, group_id);
+        } else {
+            GROUP_BACKORDERED.save(ctx.store, group_id, &group_backordered)?;
+        }
+
+        Ok(group_backordered == 0)
+    }
+}
@@ -0,0 +1,86 @@
+use cosmwasm_std::{to_json_binary, Uint128};
+use shared::msg::{contract::{payment::{IbcExecuteMsg as PaymentIbcExecuteMsg, Refund}, warehouse::{BatchItem, BatchItemResult}}, purchase::PurchaseId};
+use anyhow::Result;
+
+use super::{ibc::IbcChannelKind, State, StateContext};
+
+impl State<'_> {
+    // the per-item results become the ack, rejected items are refunded right away
+    pub fn purchase_batch(&self, ctx: &mut StateContext, owner: String, spender: String, items: Vec<BatchItem>, atomic: bool, referrer: Option<String>, cw20: Option<String>) -> Result<()> {
+        if items.is_empty() {
+            anyhow::bail!("batch must have at least one item");
+        }
+
+        let results = if atomic {
+            match self.isolated(ctx, |ctx| {
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        self.purchase_batch_item(ctx, &owner, &spender, item, &referrer, &cw20)
+                            .map_err(|err| err.context(format!("batch item {}", index)))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            }) {
+                Ok(results) => results,
+                Err(err) => items
+                    .iter()
+                    .map(|item| item_result(item, Err(&err)))
+                    .collect(),
+            }
+        } else {
+            items
+                .iter()
+                .map(|item| {
+                    let res = self.isolated(ctx, |ctx| self.purchase_batch_item(ctx, &owner, &spender, item, &referrer, &cw20));
+                    match res {
+                        Ok(result) => result,
+                        Err(err) => item_result(item, Err(&err)),
+                    }
+                })
+                .collect()
+        };
+
+        let refund: Uint128 = items
+            .iter()
+            .zip(results.iter())
+            .filter(|(_, result)| result.purchase_id.is_none())
+            .map(|(item, _)| item.deposit)
+            .sum();
+
+        if !refund.is_zero() {
+            let msg = PaymentIbcExecuteMsg::Refund { refunds: vec![
+                Refund {
+                    recipient: spender,
+                    amount: refund,
+                    cw20,
+                }
+            ]};
+            self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&msg)?)?;
+        }
+
+        ctx.response_mut().set_data(&results)
+    }
+
+    fn purchase_batch_item(&self, ctx: &mut StateContext, owner: &str, spender: &str, item: &BatchItem, referrer: &Option<String>, cw20: &Option<String>) -> Result<BatchItemResult> {
+        let purchase_id = self.make_purchase(ctx, spender.to_string(), item.product_id, item.variant_id, item.quantity, item.deposit, item.hold_id, item.coupon.clone(), referrer.clone(), cw20.clone())?;
+        self.mint_receipt(ctx, owner.to_string(), purchase_id, item.product_id, item.variant_id, item.quantity)?;
+
+        Ok(item_result(item, Ok(purchase_id)))
+    }
+}
+
+fn item_result(item: &BatchItem, res: Result<PurchaseId, &anyhow::Error>) -> BatchItemResult {
+    match res {
+        Ok(purchase_id) => BatchItemResult {
+            product_id: item.product_id,
+            purchase_id: Some(purchase_id),
+            error: None,
+        },
+        Err(err) => BatchItemResult {
+            product_id: item.product_id,
+            purchase_id: None,
+            error: Some(format!("{:#}", err)),
+        },
+    }
+}
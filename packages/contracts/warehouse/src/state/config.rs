@@ -0,0 +1,143 @@
+use cosmwasm_std::{Addr, Storage, Uint128};
+use cw_storage_plus::Item;
+use serde::{Deserialize, Serialize};
+use shared::{ibc::validate_channel_id, msg::contract::warehouse::{Config, PlatformFee}};
+use anyhow::{Context, Result};
+
+use super::{State, StateContext};
+
+const CONFIG: Item<Config> = Item::new("config");
+// the same key, read leniently so a config stored before later fields were added can be filled in
+const STORED_CONFIG: Item<StoredConfig> = Item::new("config");
+
+pub const DEFAULT_DISPUTE_WINDOW_SECONDS: u64 = 60 * 60 * 24 * 7; // 1 week
+pub const DEFAULT_HOLD_SECONDS: u64 = 60 * 15; // 15 minutes
+
+impl State<'_> {
+    pub fn init_config(&self, ctx: &mut StateContext, admin: Addr, payment_chain_id: String) -> Result<()> {
+        if payment_chain_id.is_empty() {
+            anyhow::bail!("payment chain id cannot be empty");
+        }
+
+        CONFIG.save(ctx.store, &Config {
+            arbiter: admin.clone(),
+            admin,
+            payment_chain_id,
+            payment_transfer_channel: None,
+            dispute_window_seconds: DEFAULT_DISPUTE_WINDOW_SECONDS,
+            hold_seconds: DEFAULT_HOLD_SECONDS,
+            hold_deposit: Uint128::zero(),
+            hold_relayers: Vec::new(),
+            platform_fee: PlatformFee::default(),
+        })?;
+
+        Ok(())
+    }
+
+    // contracts from before the config existed get one owned by the contract admin
+    pub fn migrate_config(&self, ctx: &mut StateContext, payment_chain_id: Option<String>) -> Result<()> {
+        let config = match STORED_CONFIG.may_load(ctx.store)? {
+            Some(stored) => Config {
+                arbiter: stored.arbiter.unwrap_or_else(|| stored.admin.clone()),
+                admin: stored.admin,
+                payment_chain_id: stored.payment_chain_id.or(payment_chain_id).context("payment chain id is required to migrate")?,
+                payment_transfer_channel: stored.payment_transfer_channel,
+                dispute_window_seconds: stored.dispute_window_seconds.unwrap_or(DEFAULT_DISPUTE_WINDOW_SECONDS),
+                hold_seconds: stored.hold_seconds.unwrap_or(DEFAULT_HOLD_SECONDS),
+                hold_deposit: stored.hold_deposit.unwrap_or_default(),
+                hold_relayers: stored.hold_relayers.unwrap_or_default(),
+                platform_fee: stored.platform_fee.unwrap_or_default(),
+            },
+            None => {
+                let admin = self
+                    .querier
+                    .query_wasm_contract_info(&self.env.contract.address)?
+                    .admin
+                    .context("contract has no admin to own the config")?;
+
+                Config {
+                    arbiter: admin.clone(),
+                    admin,
+                    payment_chain_id: payment_chain_id.context("payment chain id is required to migrate")?,
+                    payment_transfer_channel: None,
+                    dispute_window_seconds: DEFAULT_DISPUTE_WINDOW_SECONDS,
+                    hold_seconds: DEFAULT_HOLD_SECONDS,
+                    hold_deposit: Uint128::zero(),
+                    hold_relayers: Vec::new(),
+                    platform_fee: PlatformFee::default(),
+                }
+            }
+        };
+
+        CONFIG.save(ctx.store, &config)?;
+
+        Ok(())
+    }
+
+    pub fn get_config(&self, store: &dyn Storage) -> Result<Config> {
+        CONFIG.load(store).map_err(|err| err.into())
+    }
+
+    pub fn assert_admin(&self, store: &dyn Storage, msg_sender: &Addr) -> Result<()> {
+        if *msg_sender != self.get_config(store)?.admin {
+            anyhow::bail!("only the admin can do this");
+        }
+        Ok(())
+    }
+
+    pub fn update_config(&self, ctx: &mut StateContext, msg_sender: Addr, dispute_window_seconds: Option<u64>, arbiter: Option<String>, payment_chain_id: Option<String>, hold_seconds: Option<u64>, platform_fee: Option<PlatformFee>, payment_transfer_channel: Option<String>, hold_deposit: Option<Uint128>, hold_relayers: Option<Vec<String>>) -> Result<()> {
+        self.assert_admin(ctx.store, &msg_sender)?;
+
+        let mut config = self.get_config(ctx.store)?;
+        if let Some(dispute_window_seconds) = dispute_window_seconds {
+            config.dispute_window_seconds = dispute_window_seconds;
+        }
+        if let Some(arbiter) = arbiter {
+            config.arbiter = self.api.addr_validate(&arbiter)?;
+        }
+        if let Some(payment_chain_id) = payment_chain_id {
+            if payment_chain_id.is_empty() {
+                anyhow::bail!("payment chain id cannot be empty");
+            }
+            config.payment_chain_id = payment_chain_id;
+        }
+        if let Some(payment_transfer_channel) = payment_transfer_channel {
+            validate_channel_id(&payment_transfer_channel)?;
+            config.payment_transfer_channel = Some(payment_transfer_channel);
+        }
+        if let Some(hold_seconds) = hold_seconds {
+            config.hold_seconds = hold_seconds;
+        }
+        if let Some(hold_deposit) = hold_deposit {
+            config.hold_deposit = hold_deposit;
+        }
+        if let Some(hold_relayers) = hold_relayers {
+            config.hold_relayers = hold_relayers
+                .iter()
+                .map(|relayer| self.api.addr_validate(relayer))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(platform_fee) = platform_fee {
+            if platform_fee.fee_bps > 10_000 || platform_fee.tiers.iter().any(|tier| tier.fee_bps > 10_000) {
+                anyhow::bail!("platform fee cannot be more than 10000 bps");
+            }
+            config.platform_fee = platform_fee;
+        }
+        CONFIG.save(ctx.store, &config)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredConfig {
+    admin: Addr,
+    arbiter: Option<Addr>,
+    payment_chain_id: Option<String>,
+    payment_transfer_channel: Option<String>,
+    dispute_window_seconds: Option<u64>,
+    hold_seconds: Option<u64>,
+    hold_deposit: Option<Uint128>,
+    hold_relayers: Option<Vec<Addr>>,
+    platform_fee: Option<PlatformFee>,
+}
@@ -0,0 +1,56 @@
+use cosmwasm_std::{to_json_vec, Addr, Decimal256, Storage};
+use cw_storage_plus::Map;
+use sha2::{Digest, Sha256};
+use shared::msg::{contract::warehouse::{event::CouponRedeemedEvent, Coupon}, product::ProductId, purchase::PurchaseId};
+use anyhow::{Context, Result};
+
+use super::{State, StateContext};
+
+// (merchant, nonce) -> number of purchases that used the coupon
+const COUPON_USES: Map<(&Addr, u64), u32> = Map::new("coupon-uses");
+
+impl State<'_> {
+    // checks the merchant's signature and records the use, returns the discount for each unit
+    pub fn redeem_coupon(&self, ctx: &mut StateContext, coupon: &Coupon, purchase_id: PurchaseId, product_id: ProductId, unit_price: Decimal256) -> Result<Decimal256> {
+        let payload = &coupon.payload;
+
+        if payload.product_id != product_id {
+            anyhow::bail!("coupon is for a different product");
+        }
+        if self.env.block.time >= payload.expires {
+            anyhow::bail!("coupon expired at {}", payload.expires);
+        }
+
+        // the current owner, so coupons don't survive a product transfer
+        let merchant = self.get_product_owner(ctx.store, product_id)?;
+        let pubkey = self
+            .get_merchant(ctx.store, &merchant)?
+            .profile
+            .coupon_pubkey
+            .context(format!("merchant {} does not accept coupons", merchant))?;
+
+        let hash = Sha256::digest(to_json_vec(payload)?);
+        if !self.api.secp256k1_verify(&hash, &coupon.signature, &pubkey)? {
+            anyhow::bail!("coupon signature is not valid");
+        }
+
+        let uses = COUPON_USES.may_load(ctx.store, (&merchant, payload.nonce))?.unwrap_or_default() + 1;
+        if uses > payload.max_uses {
+            anyhow::bail!("coupon {} has already been used {} times", payload.nonce, payload.max_uses);
+        }
+        COUPON_USES.save(ctx.store, (&merchant, payload.nonce), &uses)?;
+
+        ctx.response_mut().add_event(CouponRedeemedEvent {
+            merchant,
+            nonce: payload.nonce,
+            purchase_id,
+            uses,
+        });
+
+        Ok(payload.discount.per_unit(unit_price))
+    }
+
+    pub fn get_coupon_uses(&self, store: &dyn Storage, merchant: &Addr, nonce: u64) -> Result<u32> {
+        Ok(COUPON_USES.may_load(store, (merchant, nonce))?.unwrap_or_default())
+    }
+}
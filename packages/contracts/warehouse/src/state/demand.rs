@@ -0,0 +1,357 @@
+use cosmwasm_std::{to_json_binary, Addr, Decimal256, Order, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use sha2::{Digest, Sha256};
+use shared::msg::{contract::{payment::{IbcExecuteMsg as PaymentIbcExecuteMsg, Refund}, warehouse::{event::{BidEvent, DemandPostedEvent, DemandSettledEvent, PledgeEvent}, Bid, Demand, DemandId, DemandStatus, GroupStatus, MerchantRole, NewBid, NewProduct, Pledge}}, product::PricingMode};
+use anyhow::{Context, Result};
+
+use super::{ibc::IbcChannelKind, State, StateContext};
+
+const DEMAND_ID: Item<DemandId> = Item::new("demand-id");
+const DEMANDS: Map<DemandId, Demand> = Map::new("demands");
+const DEMAND_BIDS: Map<(DemandId, &Addr), Bid> = Map::new("demand-bids");
+// pledges are numbered per demand, in the order they arrive
+const DEMAND_PLEDGES: Map<(DemandId, u32), Pledge> = Map::new("demand-pledges");
+const DEMAND_PLEDGE_COUNT: Map<DemandId, u32> = Map::new("demand-pledge-count");
+
+// every pledge becomes a purchase when the demand is awarded, so this keeps settling within gas limits
+const MAX_PLEDGES_PER_DEMAND: u32 = 100;
+// after this long past closing, the winner loses the award and the pledgers get their funds back
+const AWARD_TIMEOUT_SECONDS: u64 = 60 * 60 * 24 * 7; // 1 week
+
+impl State<'_> {
+    pub fn post_demand(&self, ctx: &mut StateContext, msg_sender: Addr, name: String, quantity: u32, max_price: Decimal256, bid_deadline: Timestamp, reveal_seconds: Option<u64>) -> Result<DemandId> {
+        if name.is_empty() {
+            anyhow::bail!("demand name cannot be empty");
+        }
+        if quantity == 0 {
+            anyhow::bail!("demand quantity must be greater than zero");
+        }
+        if max_price.is_zero() {
+            anyhow::bail!("demand max price must be greater than zero");
+        }
+        if bid_deadline <= self.env.block.time {
+            anyhow::bail!("bid deadline must be in the future");
+        }
+
+        let id = DEMAND_ID.may_load(ctx.store)?.unwrap_or_default();
+        DEMAND_ID.save(ctx.store, &(id + 1))?;
+
+        DEMANDS.save(ctx.store, id, &Demand {
+            id,
+            creator: msg_sender,
+            name: name.clone(),
+            quantity,
+            max_price,
+            bid_deadline,
+            reveal_deadline: reveal_seconds.map(|seconds| bid_deadline.plus_seconds(seconds)),
+            pledged: 0,
+            status: DemandStatus::Open,
+        })?;
+
+        ctx.response_mut().add_event(DemandPostedEvent {
+            demand_id: id,
+            name,
+            quantity,
+            max_price,
+            bid_deadline,
+        });
+
+        Ok(id)
+    }
+
+    pub fn add_pledge(&self, ctx: &mut StateContext, demand_id: DemandId, owner: String, spender: String, quantity: u32, fees: Uint128) -> Result<()> {
+        let mut demand = self.load_open_demand(ctx.store, demand_id)?;
+
+        if self.env.block.time >= demand.bid_deadline {
+            anyhow::bail!("demand {} is closed to pledges", demand_id);
+        }
+        if quantity == 0 {
+            anyhow::bail!("pledge quantity must be greater than zero");
+        }
+        if demand.pledged + quantity > demand.quantity {
+            anyhow::bail!("pledge of {} is more than demand {} needs (remaining: {})", quantity, demand_id, demand.quantity - demand.pledged);
+        }
+        // pledges cover the max price, whatever the winning bid doesn't need is refunded
+        if Decimal256::from_ratio(fees.u128(), 1u32) < demand.max_price * Decimal256::from_ratio(quantity, 1u32) {
+            anyhow::bail!("fees must cover the max price of the pledge");
+        }
+
+        let index = DEMAND_PLEDGE_COUNT.may_load(ctx.store, demand_id)?.unwrap_or_default();
+        if index >= MAX_PLEDGES_PER_DEMAND {
+            anyhow::bail!("demand {} already has the maximum of {} pledges", demand_id, MAX_PLEDGES_PER_DEMAND);
+        }
+        DEMAND_PLEDGE_COUNT.save(ctx.store, demand_id, &(index + 1))?;
+        DEMAND_PLEDGES.save(ctx.store, (demand_id, index), &Pledge {
+            owner,
+            spender: spender.clone(),
+            quantity,
+            fees,
+        })?;
+
+        demand.pledged += quantity;
+        DEMANDS.save(ctx.store, demand_id, &demand)?;
+
+        ctx.response_mut().add_event(PledgeEvent {
+            demand_id,
+            spender,
+            quantity,
+            pledged: demand.pledged,
+        });
+
+        Ok(())
+    }
+
+    pub fn submit_bid(&self, ctx: &mut StateContext, msg_sender: Addr, demand_id: DemandId, merchant: Option<String>, bid: NewBid) -> Result<()> {
+        let merchant = self.resolve_merchant(ctx.store, &msg_sender, merchant, MerchantRole::Catalog)?;
+        self.assert_registered_merchant(ctx.store, &merchant)?;
+        let demand = self.load_open_demand(ctx.store, demand_id)?;
+
+        if self.env.block.time >= demand.bid_deadline {
+            anyhow::bail!("demand {} is closed to bids", demand_id);
+        }
+
+        let (price, commitment) = match bid {
+            NewBid::Open { price } => {
+                if price > demand.max_price {
+                    anyhow::bail!("bid of {} is above the max price of {}", price, demand.max_price);
+                }
+                (Some(price), None)
+            },
+            NewBid::Sealed { commitment } => {
+                if demand.reveal_deadline.is_none() {
+                    anyhow::bail!("demand {} does not allow sealed bids", demand_id);
+                }
+                if commitment.is_empty() {
+                    anyhow::bail!("sealed bid must have a commitment");
+                }
+                (None, Some(commitment.to_lowercase()))
+            },
+        };
+
+        DEMAND_BIDS.save(ctx.store, (demand_id, &merchant), &Bid {
+            merchant: merchant.clone(),
+            price,
+            commitment,
+            submitted_at: self.env.block.time,
+        })?;
+
+        ctx.response_mut().add_event(BidEvent {
+            demand_id,
+            merchant,
+            price,
+        });
+
+        Ok(())
+    }
+
+    pub fn reveal_bid(&self, ctx: &mut StateContext, msg_sender: Addr, demand_id: DemandId, merchant: Option<String>, price: Decimal256, salt: String) -> Result<()> {
+        let merchant = self.resolve_merchant(ctx.store, &msg_sender, merchant, MerchantRole::Catalog)?;
+        let demand = self.load_open_demand(ctx.store, demand_id)?;
+        let reveal_deadline = demand.reveal_deadline.context(format!("demand {} does not allow sealed bids", demand_id))?;
+
+        if self.env.block.time < demand.bid_deadline || self.env.block.time >= reveal_deadline {
+            anyhow::bail!("bids on demand {} can only be revealed between {} and {}", demand_id, demand.bid_deadline, reveal_deadline);
+        }
+
+        let mut bid = DEMAND_BIDS
+            .may_load(ctx.store, (demand_id, &merchant))?
+            .context(format!("{} has not bid on demand {}", merchant, demand_id))?;
+
+        if bid.price.is_some() {
+            anyhow::bail!("bid is already revealed");
+        }
+        if bid.commitment.as_deref() != Some(bid_commitment(price, &salt).as_str()) {
+            anyhow::bail!("price and salt do not match the sealed bid");
+        }
+        if price > demand.max_price {
+            anyhow::bail!("bid of {} is above the max price of {}", price, demand.max_price);
+        }
+
+        bid.price = Some(price);
+        DEMAND_BIDS.save(ctx.store, (demand_id, &merchant), &bid)?;
+
+        ctx.response_mut().add_event(BidEvent {
+            demand_id,
+            merchant,
+            price: Some(price),
+        });
+
+        Ok(())
+    }
+
+    pub fn settle_demand(&self, ctx: &mut StateContext, demand_id: DemandId) -> Result<()> {
+        let mut demand = self.load_open_demand(ctx.store, demand_id)?;
+
+        if self.env.block.time < demand.closes_at() {
+            anyhow::bail!("demand {} can't be settled until {}", demand_id, demand.closes_at());
+        }
+
+        let timed_out = self.env.block.time >= demand.closes_at().plus_seconds(AWARD_TIMEOUT_SECONDS);
+
+        // lowest price wins, ties go to whoever bid first
+        let winner = DEMAND_BIDS
+            .prefix(demand_id)
+            .range(ctx.store, None, None, Order::Ascending)
+            .filter_map(|res| match res {
+                Ok((_, bid)) => bid.price.map(|price| Ok((price, bid.submitted_at, bid.merchant))),
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|(price, _, _)| *price <= demand.max_price)
+            .min_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        let pledges = DEMAND_PLEDGES
+            .prefix(demand_id)
+            .range(ctx.store, None, None, Order::Ascending)
+            .map(|res| res.map(|(_, pledge)| pledge))
+            .collect::<Result<Vec<Pledge>, _>>()?;
+
+        // a failed award (e.g. the winner is no longer a merchant) mustn't keep the pledges locked up
+        let award = match winner {
+            Some((price, _, merchant)) if demand.pledged > 0 && !timed_out => self
+                .isolated(ctx, |ctx| self.award_demand(ctx, &demand, merchant, price, &pledges))
+                .ok(),
+            _ => None,
+        };
+
+        let mut refunds = Vec::new();
+        demand.status = match award {
+            Some((status, award_refunds)) => {
+                refunds = award_refunds;
+                status
+            },
+            None => {
+                for pledge in pledges {
+                    refunds.push(Refund {
+                        recipient: pledge.spender,
+                        amount: pledge.fees,
+                        cw20: None,
+                    });
+                }
+                DemandStatus::Unfilled
+            }
+        };
+
+        DEMANDS.save(ctx.store, demand_id, &demand)?;
+
+        refunds.retain(|refund| !refund.amount.is_zero());
+        if !refunds.is_empty() {
+            let msg = PaymentIbcExecuteMsg::Refund { refunds };
+            self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&msg)?)?;
+        }
+
+        ctx.response_mut().add_event(DemandSettledEvent {
+            demand_id,
+            status: demand.status,
+        });
+
+        Ok(())
+    }
+
+    // gives the winner a product with every pledge as a purchase, returns the status and what each pledger overpaid
+    fn award_demand(&self, ctx: &mut StateContext, demand: &Demand, merchant: Addr, price: Decimal256, pledges: &[Pledge]) -> Result<(DemandStatus, Vec<Refund>)> {
+        let mut refunds = Vec::new();
+
+        let product = self.add_product(ctx, merchant.clone(), None, NewProduct {
+            name: demand.name.clone(),
+            price,
+            stock: demand.pledged,
+            group_capacity: None,
+            auto_ship_on_full: false,
+            purchase_rules: Default::default(),
+            variants: Vec::new(),
+            preorder_cap: None,
+            // the merchant bid this price, it's not discounted any further
+            pricing: PricingMode::Fixed,
+            referral_share: Decimal256::zero(),
+            // pledges are always in the native denom
+            cw20: None,
+        })?;
+
+        let mut group_id = None;
+        for pledge in pledges {
+            let purchase_id = self.make_purchase(ctx, pledge.spender.clone(), product.id, None, pledge.quantity, pledge.fees, None, None, None, None)?;
+            self.mint_receipt(ctx, pledge.owner.clone(), purchase_id, product.id, None, pledge.quantity)?;
+            group_id = self.try_get_purchase(ctx.store, purchase_id)?.map(|purchase| purchase.group_id);
+
+            let cost = price * Decimal256::from_ratio(pledge.quantity, 1u32);
+            let refund = Decimal256::from_ratio(pledge.fees.u128(), 1u32) - cost;
+            refunds.push(Refund {
+                recipient: pledge.spender.clone(),
+                amount: refund.to_uint_floor().to_string().parse()?,
+                cw20: None,
+            });
+        }
+        let group_id = group_id.context("awarded demand has no group")?;
+
+        // nobody else should join, the group is exactly what was pledged
+        self.transition_group_status(ctx, group_id, GroupStatus::Locked)?;
+
+        Ok((DemandStatus::Awarded {
+            merchant,
+            price,
+            product_id: product.id,
+            group_id,
+        }, refunds))
+    }
+
+    pub fn get_demands(&self, store: &dyn Storage, ids: Vec<DemandId>) -> Result<Vec<Demand>> {
+        ids
+            .into_iter()
+            .map(|id| DEMANDS.may_load(store, id)?.context(format!("demand {} not found", id)))
+            .collect()
+    }
+
+    pub fn list_demands(&self, store: &dyn Storage, open_only: bool, limit: Option<u32>, start_after: Option<DemandId>) -> Result<Vec<Demand>> {
+        DEMANDS
+            .range(store, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .map(|res| res.map(|(_, demand)| demand).map_err(anyhow::Error::from))
+            .filter(|res| match res {
+                Ok(demand) => !open_only || demand.status == DemandStatus::Open,
+                Err(_) => true,
+            })
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .collect()
+    }
+
+    pub fn list_bids(&self, store: &dyn Storage, demand_id: DemandId, limit: Option<u32>, start_after: Option<String>) -> Result<Vec<Bid>> {
+        let start_after = start_after.map(Addr::unchecked);
+
+        DEMAND_BIDS
+            .prefix(demand_id)
+            .range(store, start_after.as_ref().map(Bound::exclusive), None, Order::Ascending)
+            .map(|res| res.map(|(_, bid)| bid).map_err(|err| err.into()))
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .collect()
+    }
+
+    pub fn list_pledges(&self, store: &dyn Storage, demand_id: DemandId, limit: Option<u32>, start_after: Option<u32>) -> Result<Vec<Pledge>> {
+        DEMAND_PLEDGES
+            .prefix(demand_id)
+            .range(store, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .map(|res| res.map(|(_, pledge)| pledge).map_err(|err| err.into()))
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .collect()
+    }
+
+    fn load_open_demand(&self, store: &dyn Storage, demand_id: DemandId) -> Result<Demand> {
+        let demand = DEMANDS
+            .may_load(store, demand_id)?
+            .context(format!("demand {} not found", demand_id))?;
+
+        if demand.status != DemandStatus::Open {
+            anyhow::bail!("demand {} is already settled", demand_id);
+        }
+
+        Ok(demand)
+    }
+}
+
+// hex-encoded sha256 of "{price}:{salt}"
+fn bid_commitment(price: Decimal256, salt: &str) -> String {
+    Sha256::digest(format!("{}:{}", price, salt).as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,166 @@
+use cosmwasm_std::{to_json_binary, Addr, Order, Storage, Uint128};
+use cw_storage_plus::{Bound, Map};
+use shared::msg::{contract::{payment::{IbcExecuteMsg as PaymentIbcExecuteMsg, Refund}, warehouse::{event::{DisputeClosedEvent, DisputeOpenedEvent, DisputeResolvedEvent}, Dispute, DisputeResolution}}, purchase::PurchaseId};
+use anyhow::{Context, Result};
+
+use super::{ibc::IbcChannelKind, State, StateContext};
+
+const DISPUTES: Map<PurchaseId, Dispute> = Map::new("disputes");
+// disputes that haven't been closed yet, which lock the merchant's bond
+const MERCHANT_OPEN_DISPUTES: Map<&Addr, u32> = Map::new("merchant-open-disputes");
+
+const MAX_EVIDENCE_LEN: usize = 1024;
+
+impl State<'_> {
+    pub fn open_dispute(&self, ctx: &mut StateContext, purchase_id: PurchaseId, evidence: String) -> Result<()> {
+        let escrow = self
+            .get_escrow(ctx.store, purchase_id)?
+            .context(format!("purchase {} has not shipped", purchase_id))?;
+
+        if escrow.settled_at.is_some() {
+            anyhow::bail!("purchase {} has already been settled", purchase_id);
+        }
+        if DISPUTES.has(ctx.store, purchase_id) {
+            anyhow::bail!("purchase {} has already been disputed", purchase_id);
+        }
+        if evidence.len() > MAX_EVIDENCE_LEN {
+            anyhow::bail!("evidence is too long (max {} bytes)", MAX_EVIDENCE_LEN);
+        }
+
+        DISPUTES.save(ctx.store, purchase_id, &Dispute {
+            purchase_id,
+            evidence: evidence.clone(),
+            opened_at: self.env.block.time,
+            resolution: None,
+            resolved_at: None,
+            amount: escrow.amount,
+            slashed: None,
+            closed_at: None,
+        })?;
+
+        let merchant = self.get_group_owner(ctx.store, escrow.group_id)?;
+        MERCHANT_OPEN_DISPUTES.update(ctx.store, &merchant, |count| anyhow::Ok(count.unwrap_or_default() + 1))?;
+
+        ctx.response_mut().add_event(DisputeOpenedEvent { purchase_id, evidence });
+
+        Ok(())
+    }
+
+    pub fn resolve_dispute(&self, ctx: &mut StateContext, msg_sender: Addr, purchase_id: PurchaseId, resolution: DisputeResolution) -> Result<()> {
+        if msg_sender != self.get_config(ctx.store)?.arbiter {
+            anyhow::bail!("only the arbiter can resolve disputes");
+        }
+
+        let mut dispute = DISPUTES
+            .may_load(ctx.store, purchase_id)?
+            .context(format!("purchase {} is not disputed", purchase_id))?;
+        if dispute.resolution.is_some() {
+            anyhow::bail!("dispute for purchase {} was already resolved", purchase_id);
+        }
+
+        let mut escrow = self
+            .get_escrow(ctx.store, purchase_id)?
+            .context(format!("no escrow for purchase {}", purchase_id))?;
+
+        let refund_amount = match &resolution {
+            DisputeResolution::FullRefund => escrow.amount,
+            DisputeResolution::PartialRefund { amount } => {
+                if *amount > escrow.amount {
+                    anyhow::bail!("partial refund of {} is more than the escrowed {}", amount, escrow.amount);
+                }
+                *amount
+            },
+            DisputeResolution::ReleaseToMerchant => 0u128.into(),
+        };
+
+        dispute.resolution = Some(resolution.clone());
+        dispute.resolved_at = Some(self.env.block.time);
+        DISPUTES.save(ctx.store, purchase_id, &dispute)?;
+
+        ctx.response_mut().add_event(DisputeResolvedEvent { purchase_id, resolution });
+
+        if !refund_amount.is_zero() {
+            // refund the purchase to the original spender (not the nft owner)
+            let purchase = self.try_get_purchase(ctx.store, purchase_id)?.context("purchase not found")?;
+            let msg = PaymentIbcExecuteMsg::Refund { refunds: vec![
+                Refund {
+                    recipient: purchase.spender,
+                    amount: refund_amount,
+                    cw20: purchase.cw20,
+                }
+            ]};
+            self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&msg)?)?;
+        }
+
+        // whatever's left goes to the merchant, this also closes out the escrow
+        escrow.amount -= refund_amount;
+        self.settle_escrows(ctx, vec![escrow])
+    }
+
+    pub fn close_dispute(&self, ctx: &mut StateContext, msg_sender: Addr, purchase_id: PurchaseId) -> Result<()> {
+        if msg_sender != self.get_config(ctx.store)?.arbiter {
+            anyhow::bail!("only the arbiter can close disputes");
+        }
+
+        self.finish_dispute(ctx, purchase_id, None)
+    }
+
+    // the bond stays locked past the resolution, so the arbiter still has something to slash
+    // closing it, with or without a slash, is what frees it up
+    pub fn finish_dispute(&self, ctx: &mut StateContext, purchase_id: PurchaseId, slashed: Option<Uint128>) -> Result<()> {
+        let mut dispute = DISPUTES
+            .may_load(ctx.store, purchase_id)?
+            .context(format!("purchase {} is not disputed", purchase_id))?;
+        if dispute.resolution.is_none() {
+            anyhow::bail!("dispute for purchase {} has not been resolved", purchase_id);
+        }
+        if dispute.closed_at.is_some() {
+            anyhow::bail!("dispute for purchase {} was already closed", purchase_id);
+        }
+        if slashed.is_some_and(|slashed| slashed > dispute.amount) {
+            anyhow::bail!("cannot slash more than the disputed {}", dispute.amount);
+        }
+
+        dispute.slashed = slashed;
+        dispute.closed_at = Some(self.env.block.time);
+        DISPUTES.save(ctx.store, purchase_id, &dispute)?;
+
+        let purchase = self.try_get_purchase(ctx.store, purchase_id)?.context("purchase not found")?;
+        let merchant = self.get_group_owner(ctx.store, purchase.group_id)?;
+        MERCHANT_OPEN_DISPUTES.update(ctx.store, &merchant, |count| anyhow::Ok(count.unwrap_or_default().saturating_sub(1)))?;
+
+        ctx.response_mut().add_event(DisputeClosedEvent { purchase_id, slashed });
+
+        Ok(())
+    }
+
+    // an unresolved dispute freezes the escrow
+    pub fn is_disputed(&self, store: &dyn Storage, purchase_id: PurchaseId) -> Result<bool> {
+        Ok(DISPUTES
+            .may_load(store, purchase_id)?
+            .is_some_and(|dispute| dispute.resolution.is_none()))
+    }
+
+    pub fn get_merchant_open_disputes(&self, store: &dyn Storage, merchant: &Addr) -> Result<u32> {
+        Ok(MERCHANT_OPEN_DISPUTES.may_load(store, merchant)?.unwrap_or_default())
+    }
+
+    pub fn get_disputes(&self, store: &dyn Storage, ids: Vec<PurchaseId>) -> Result<Vec<Dispute>> {
+        ids
+            .into_iter()
+            .map(|id| DISPUTES.load(store, id).map_err(|err| err.into()))
+            .collect()
+    }
+
+    pub fn list_disputes(&self, store: &dyn Storage, unresolved_only: bool, limit: Option<u32>, start_after: Option<PurchaseId>) -> Result<Vec<Dispute>> {
+        DISPUTES
+            .range(store, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .filter(|res| match res {
+                Ok((_, dispute)) => !unresolved_only || dispute.resolution.is_none(),
+                Err(_) => true,
+            })
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .map(|res| res.map(|(_, dispute)| dispute).map_err(|err| err.into()))
+            .collect()
+    }
+}
@@ -1,7 +1,7 @@
 
-use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal256, IbcMsg, IbcTimeout, Order, Storage};
+use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal256, IbcMsg, IbcTimeout, Order, Storage, Uint128};
 use cw_storage_plus::{Bound, Item, Map, PrefixBound};
-use shared::{ibc::TIMEOUT_SECONDS, msg::{contract::{payment::Refund, warehouse::{event::{AddProductEvent, PurchaseEvent}, GroupId, GroupInfo, NewProduct}}, product::{self, Product, ProductId}, purchase::{Purchase, PurchaseId}}};
+use shared::{ibc::TIMEOUT_SECONDS, msg::{contract::{payment::Refund, warehouse::{event::{AddProductEvent, GroupFilledEvent, GroupSplitEvent, PurchaseEvent}, GroupId, GroupInfo, GroupPurchaseShipment, GroupStatus, MerchantRole, NewProduct}}, product::{self, PricingMode, Product, ProductId}, purchase::{Purchase, PurchaseId}}};
 use anyhow::{Context, Result};
 
 use super::{ibc::IbcChannelKind, State, StateContext};
@@ -10,85 +10,251 @@ const GROUP_ID: Item<GroupId> = Item::new("group-id");
 const PENDING_PRODUCT_GROUP: Map<ProductId, GroupId> = Map::new("pending-product-group");
 const GROUP_TO_PRODUCT: Map<GroupId, ProductId> = Map::new("group-to-product");
 const GROUP_OWNER: Map<GroupId, Addr> = Map::new("group-owner");
-const HAS_SHIPPED: Map<GroupId, bool> = Map::new("group-has-shipped");
 const GROUP_PURCHASES: Map<(GroupId, PurchaseId), ()> = Map::new("group-purchases");
 const GROUP_OWNER_LIST: Map<(Addr, GroupId), ()> = Map::new("group-owner-list");
 const GROUP_LEN: Map<GroupId, u32> = Map::new("group-len"); 
+const GROUP_UNITS: Map<GroupId, u32> = Map::new("group-units");
+// buyer count the discount is based on, kept across partial shipments
+const GROUP_TIER: Map<GroupId, u32> = Map::new("group-tier");
+// shipped group -> the group it was split off
+const GROUP_SPLIT_FROM: Map<GroupId, GroupId> = Map::new("group-split-from");
+// (original group, purchase) -> the group it was split into
+const GROUP_SPLIT_PURCHASES: Map<(GroupId, PurchaseId), GroupId> = Map::new("group-split-purchases");
 
 impl State<'_> {
     pub fn assert_group_not_shipped(&self, store: &dyn Storage, group_id: GroupId) -> Result<()> {
-        if HAS_SHIPPED.may_load(store, group_id)?.unwrap_or_default() {
+        if self.get_group_status(store, group_id)?.has_shipped() {
             anyhow::bail!("group {} has already shipped", group_id);
         }
         Ok(())
     }
 
-    pub fn ship_group(&self, ctx: &mut StateContext, msg_sender: Addr, group_id: GroupId) -> Result<()> {
+    // the group owner, or one of their delegates with the Shipping role
+    pub fn assert_group_manager(&self, store: &dyn Storage, group_id: GroupId, msg_sender: &Addr) -> Result<()> {
+        let group_owner = GROUP_OWNER.load(store, group_id)?;
+        self.assert_merchant_role(store, &group_owner, msg_sender, MerchantRole::Shipping)
+    }
+
+    pub fn ship_group(&self, ctx: &mut StateContext, msg_sender: Addr, group_id: GroupId, carrier: Option<String>, tracking_ref: Option<String>, purchase_ids: Option<Vec<PurchaseId>>) -> Result<()> {
+        self.assert_group_manager(ctx.store, group_id, &msg_sender)?;
+
+        let group_id = match purchase_ids {
+            Some(purchase_ids) => self.split_group(ctx, group_id, purchase_ids)?,
+            None => group_id,
+        };
+
+        self.send_group_shipment(ctx, group_id, carrier, tracking_ref)
+    }
+
+    // moves the given purchases into a new group (returned) so they can ship on their own
+    // both groups keep the original discount tier, so nobody loses out from the split
+    fn split_group(&self, ctx: &mut StateContext, group_id: GroupId, mut purchase_ids: Vec<PurchaseId>) -> Result<GroupId> {
         self.assert_group_not_shipped(ctx.store, group_id)?;
 
+        purchase_ids.sort_unstable();
+        purchase_ids.dedup();
+        if purchase_ids.is_empty() {
+            anyhow::bail!("must ship at least one purchase");
+        }
+
+        for purchase_id in purchase_ids.iter().copied() {
+            if !GROUP_PURCHASES.has(ctx.store, (group_id, purchase_id)) {
+                anyhow::bail!("purchase {} is not in group {}", purchase_id, group_id);
+            }
+        }
+
+        let count = self.get_group_count(ctx.store, group_id)?;
+        if purchase_ids.len() as u32 == count {
+            // everything is shipping anyway
+            return Ok(group_id);
+        }
+
+        let tier = GROUP_TIER.may_load(ctx.store, group_id)?.unwrap_or_default().max(count);
+        let product_id = GROUP_TO_PRODUCT.load(ctx.store, group_id)?;
         let group_owner = GROUP_OWNER.load(ctx.store, group_id)?;
-        if msg_sender != group_owner {
-            anyhow::bail!("only the group owner can ship the group");
+
+        let shipped_group_id = GROUP_ID.may_load(ctx.store)?.unwrap_or_default();
+        GROUP_ID.save(ctx.store, &(shipped_group_id + 1))?;
+        GROUP_TO_PRODUCT.save(ctx.store, shipped_group_id, &product_id)?;
+        GROUP_OWNER.save(ctx.store, shipped_group_id, &group_owner)?;
+        GROUP_OWNER_LIST.save(ctx.store, (group_owner, shipped_group_id), &())?;
+        GROUP_TIER.save(ctx.store, shipped_group_id, &tier)?;
+        GROUP_TIER.save(ctx.store, group_id, &tier)?;
+        GROUP_SPLIT_FROM.save(ctx.store, shipped_group_id, &group_id)?;
+        self.init_group_status(ctx, shipped_group_id)?;
+
+        for purchase_id in purchase_ids.iter().copied() {
+            let mut purchase = self.try_get_purchase(ctx.store, purchase_id)?.context("purchase not found")?;
+            if purchase.backordered > 0 {
+                anyhow::bail!("purchase {} still has {} backordered units", purchase_id, purchase.backordered);
+            }
+
+            self.move_group_units(ctx, &purchase, shipped_group_id)?;
+
+            GROUP_PURCHASES.remove(ctx.store, (group_id, purchase_id));
+            GROUP_PURCHASES.save(ctx.store, (shipped_group_id, purchase_id), &())?;
+            GROUP_SPLIT_PURCHASES.save(ctx.store, (group_id, purchase_id), &shipped_group_id)?;
+            GROUP_LEN.update(ctx.store, group_id, |x| anyhow::Ok(x.unwrap_or_default() - 1))?;
+            GROUP_LEN.update(ctx.store, shipped_group_id, |x| anyhow::Ok(x.unwrap_or_default() + 1))?;
+            GROUP_UNITS.update(ctx.store, group_id, |x| anyhow::Ok(x.unwrap_or_default() - purchase.quantity))?;
+            GROUP_UNITS.update(ctx.store, shipped_group_id, |x| anyhow::Ok(x.unwrap_or_default() + purchase.quantity))?;
+
+            purchase.group_id = shipped_group_id;
+            self.save_purchase(ctx, &purchase)?;
         }
 
+        ctx.response_mut().add_event(GroupSplitEvent {
+            group_id,
+            shipped_group_id,
+            purchase_ids,
+        });
+
+        Ok(shipped_group_id)
+    }
+
+    // ships the group without any ownership checks, sending the discount refunds to the payment chain
+    fn send_group_shipment(&self, ctx: &mut StateContext, group_id: GroupId, carrier: Option<String>, tracking_ref: Option<String>) -> Result<()> {
+        self.assert_group_not_shipped(ctx.store, group_id)?;
+        self.assert_group_fulfilled(ctx.store, group_id)?;
+
         let group_info = self.get_group_info(ctx.store, group_id)?;
 
+        let purchases = self.get_group_purchase_ids(ctx.store, group_id)?
+            .into_iter()
+            .map(|purchase_id| self.try_get_purchase(ctx.store, purchase_id)?.context(format!("purchase not found for group {}", group_id)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // dutch auctions can refund everyone down to the lowest price anyone in the group paid
+        let clearing_price = match group_info.product.pricing {
+            PricingMode::Dutch { refund_to_clearing: true, .. } => purchases
+                .iter()
+                .map(|purchase| purchase.price.unwrap_or(group_info.product.price))
+                .min(),
+            _ => None,
+        };
+
         let mut refunds = Vec::new();
-        for purchase_id in GROUP_PURCHASES.prefix(group_id).keys(ctx.store, None, None, Order::Ascending) {
-            let purchase_id = purchase_id?;
-            let purchase = self.try_get_purchase(ctx.store, purchase_id)?.context(format!("purchase not found for group {}", group_id))?;
-            let amount_spent = Decimal256::from_ratio(purchase.quantity, 1u32) * group_info.product.price;
-            let amount_shipped = Decimal256::from_ratio(purchase.quantity, 1u32) * group_info.cost_per_item();
+        for purchase in purchases {
+            self.close_open_purchase(ctx, &purchase)?;
+            let unit_price = purchase.unit_price(&group_info.product)?;
+            let shipped_price = match clearing_price {
+                // any coupon still comes off the clearing price
+                Some(clearing_price) => group_info.product.unit_price_from(clearing_price, purchase.variant_id)?.saturating_sub(purchase.discount),
+                None => group_info.discounted_price(unit_price),
+            };
+            let amount_spent = Decimal256::from_ratio(purchase.quantity, 1u32) * unit_price;
+            let amount_shipped = Decimal256::from_ratio(purchase.quantity, 1u32) * shipped_price;
             let refund = amount_spent - amount_shipped;
+            self.open_escrow(ctx, &purchase, amount_shipped.to_uint_floor().to_string().parse()?)?;
+            let mut refund: Uint128 = refund.to_uint_ceil().to_string().parse()?;
+            if let Some(referral) = self.pay_referral(ctx, &group_info.product, &purchase, refund)? {
+                refund -= referral.amount;
+                refunds.push(referral);
+            }
             refunds.push(Refund {
                 recipient: purchase.spender,
-                amount: refund.to_uint_ceil().to_string().parse()?,
+                amount: refund,
+                cw20: purchase.cw20,
             });
         }
-        PENDING_PRODUCT_GROUP.remove(ctx.store, group_info.product.id);
-
         let msg = shared::msg::contract::payment::IbcExecuteMsg::Refund { refunds };
 
-        // outbound IBC message, where packet is then received on other chain
-        let channel_id = self
-            .get_ibc_channel(ctx.store, IbcChannelKind::Payment)?
-            .endpoint
-            .channel_id;
+        self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&msg)?)?;
 
-        ctx.response_mut().add_message(IbcMsg::SendPacket {
-            channel_id,
-            data: to_json_binary(&msg)?,
-            timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(TIMEOUT_SECONDS)),
-        });
-
-        HAS_SHIPPED.save(ctx.store, group_id, &true)?;
+        self.transition_group_status(ctx, group_id, GroupStatus::Shipped { carrier, tracking_ref })?;
 
         Ok(())
     }
-    pub fn add_purchase_to_group(&self, ctx: &mut StateContext, purchase_id: PurchaseId, product_id: ProductId) -> Result<GroupId> {
+
+    pub fn add_purchase_to_group(&self, ctx: &mut StateContext, purchase_id: PurchaseId, product_id: ProductId, quantity: u32) -> Result<GroupId> {
         let group_owner = self.get_product_owner(ctx.store, product_id)?;
+        let product = self.get_product(ctx.store, product_id)?;
 
-        let group_id = match PENDING_PRODUCT_GROUP.may_load(ctx.store, product_id)? {
-            Some(group_id) => {
-                group_id
-            },
-            None => {
-                let group_id = GROUP_ID.may_load(ctx.store)?.unwrap_or_default();
-                GROUP_ID.save(ctx.store, &(group_id + 1))?;
-                PENDING_PRODUCT_GROUP.save(ctx.store, product_id, &group_id)?;
-                GROUP_TO_PRODUCT.save(ctx.store, group_id, &product_id)?;
-                GROUP_OWNER.save(ctx.store, group_id, &group_owner)?;
-                group_id
-            }
+        let mut group_id = match PENDING_PRODUCT_GROUP.may_load(ctx.store, product_id)? {
+            Some(group_id) => group_id,
+            None => self.open_group(ctx, product_id, &group_owner)?,
         };
 
+        if let Some(capacity) = product.group_capacity {
+            let count = GROUP_LEN.may_load(ctx.store, group_id)?.unwrap_or_default();
+            let units = GROUP_UNITS.may_load(ctx.store, group_id)?.unwrap_or_default();
+            if !capacity.fits(count, units, quantity) {
+                // too big for any group, not just this one
+                if !capacity.fits(0, 0, quantity) {
+                    anyhow::bail!("purchase of {} is more than a whole group can take ({})", quantity, capacity.remaining(0, 0));
+                }
+
+                // the rest of this group is too small for the purchase, so it's done taking buyers
+                self.transition_group_status(ctx, group_id, GroupStatus::Locked)?;
+                ctx.response_mut().add_event(GroupFilledEvent {
+                    group_id,
+                    product_id,
+                    count,
+                    units,
+                });
+
+                group_id = self.open_group(ctx, product_id, &group_owner)?;
+            }
+        }
+
         GROUP_PURCHASES.save(ctx.store, (group_id, purchase_id), &())?;
         GROUP_OWNER_LIST.save(ctx.store, (group_owner, group_id), &())?;
         GROUP_LEN.update(ctx.store, group_id, |x| anyhow::Ok(x.unwrap_or_default() + 1))?;
+        GROUP_UNITS.update(ctx.store, group_id, |x| anyhow::Ok(x.unwrap_or_default() + quantity))?;
+
+        Ok(group_id)
+    }
+
+    fn open_group(&self, ctx: &mut StateContext, product_id: ProductId, group_owner: &Addr) -> Result<GroupId> {
+        let group_id = GROUP_ID.may_load(ctx.store)?.unwrap_or_default();
+        GROUP_ID.save(ctx.store, &(group_id + 1))?;
+        PENDING_PRODUCT_GROUP.save(ctx.store, product_id, &group_id)?;
+        GROUP_TO_PRODUCT.save(ctx.store, group_id, &product_id)?;
+        GROUP_OWNER.save(ctx.store, group_id, group_owner)?;
+        self.init_group_status(ctx, group_id)?;
 
         Ok(group_id)
     }
 
+    // locks the group if it has reached its capacity, so that the next purchase opens a new group
+    // must be called after the purchase is stored, since auto-shipping reads all the group's purchases
+    pub fn lock_group_if_full(&self, ctx: &mut StateContext, group_id: GroupId) -> Result<()> {
+        let group_info = self.get_group_info(ctx.store, group_id)?;
+        if group_info.status != GroupStatus::Pending || group_info.remaining_capacity != Some(0) {
+            return Ok(());
+        }
+
+        self.transition_group_status(ctx, group_id, GroupStatus::Locked)?;
+
+        ctx.response_mut().add_event(GroupFilledEvent {
+            group_id,
+            product_id: group_info.product.id,
+            count: group_info.count,
+            units: group_info.units,
+        });
+
+        self.auto_ship_group(ctx, group_id)
+    }
+
+    // ships a full group if the product is configured for it, and nothing is waiting on a restock
+    pub fn auto_ship_group(&self, ctx: &mut StateContext, group_id: GroupId) -> Result<()> {
+        let group_info = self.get_group_info(ctx.store, group_id)?;
+        if !group_info.product.auto_ship_on_full || group_info.status != GroupStatus::Locked || group_info.remaining_capacity != Some(0) || group_info.backordered > 0 {
+            return Ok(());
+        }
+
+        self.send_group_shipment(ctx, group_id, None, None)
+    }
+
+    // a newer group may already be pending for this product, so only clear it if it's this one
+    pub fn clear_pending_group(&self, ctx: &mut StateContext, group_id: GroupId) -> Result<()> {
+        let product_id = GROUP_TO_PRODUCT.load(ctx.store, group_id)?;
+        if PENDING_PRODUCT_GROUP.may_load(ctx.store, product_id)? == Some(group_id) {
+            PENDING_PRODUCT_GROUP.remove(ctx.store, product_id);
+        }
+        Ok(())
+    }
+
     pub fn list_groups(&self, store: &dyn Storage, owner: Option<String>, limit: Option<u32>, start_after: Option<GroupId>) -> Result<Vec<GroupInfo>> {
         let groups = match owner {
             Some(owner) => {
@@ -118,14 +284,44 @@ impl State<'_> {
 
     pub fn get_group_info(&self, store: &dyn Storage, group_id: GroupId) -> Result<GroupInfo> {
         let count = GROUP_LEN.may_load(store, group_id)?.unwrap_or_default();
+        let units = GROUP_UNITS.may_load(store, group_id)?.unwrap_or_default();
         let product_id = GROUP_TO_PRODUCT.load(store, group_id)?;
         let product = self.get_product(store, product_id)?;
-        let has_shipped = HAS_SHIPPED.may_load(store, group_id)?.unwrap_or_default();
+        let status = self.get_group_status(store, group_id)?;
+        let status_history = self.get_group_status_history(store, group_id)?;
+        let remaining_capacity = product.group_capacity.map(|capacity| capacity.remaining(count, units));
+        let backordered = self.get_group_backordered(store, group_id)?;
+        let tier_count = GROUP_TIER.may_load(store, group_id)?.unwrap_or_default().max(count);
+        let split_from = GROUP_SPLIT_FROM.may_load(store, group_id)?;
+        let shipped = status.has_shipped();
+        let mut purchases = self
+            .get_group_purchase_ids(store, group_id)?
+            .into_iter()
+            .map(|purchase_id| GroupPurchaseShipment {
+                purchase_id,
+                shipped_group_id: shipped.then_some(group_id),
+            })
+            .collect::<Vec<_>>();
+        for res in GROUP_SPLIT_PURCHASES.prefix(group_id).range(store, None, None, Order::Ascending) {
+            let (purchase_id, shipped_group_id) = res?;
+            purchases.push(GroupPurchaseShipment {
+                purchase_id,
+                shipped_group_id: Some(shipped_group_id),
+            });
+        }
+        purchases.sort_unstable_by_key(|purchase| purchase.purchase_id);
         Ok(GroupInfo {
             id: group_id,
             count,
+            units,
             product,
-            has_shipped
+            status,
+            status_history,
+            remaining_capacity,
+            backordered,
+            tier_count,
+            split_from,
+            purchases,
         })
     }
 
@@ -139,18 +335,66 @@ impl State<'_> {
         }
 
         let len = GROUP_LEN.update(ctx.store, purchase.group_id, |x| anyhow::Ok(x.unwrap_or_default() - 1))?;
+        GROUP_UNITS.update(ctx.store, purchase.group_id, |x| anyhow::Ok(x.unwrap_or_default() - purchase.quantity))?;
         GROUP_PURCHASES.remove(ctx.store, (purchase.group_id, purchase_id));
 
         // slight optimization - if this was the last item in the group, remove the group
         if len == 0 {
             PENDING_PRODUCT_GROUP.remove(ctx.store, product_id);
+            let group_owner = GROUP_OWNER.load(ctx.store, purchase.group_id)?;
             GROUP_TO_PRODUCT.remove(ctx.store, purchase.group_id);
             GROUP_OWNER.remove(ctx.store, purchase.group_id);
+            GROUP_OWNER_LIST.remove(ctx.store, (group_owner, purchase.group_id));
+            GROUP_UNITS.remove(ctx.store, purchase.group_id);
+            GROUP_TIER.remove(ctx.store, purchase.group_id);
+            self.remove_group_status(ctx, purchase.group_id);
+        }
+
+        Ok(())
+    }
+
+    // moves the product's groups that haven't shipped yet over to the new owner
+    // shipped groups stay with the previous owner, since they're the one being settled and reviewed
+    pub fn transfer_unshipped_groups(&self, ctx: &mut StateContext, product_id: ProductId, from: &Addr, to: &Addr) -> Result<Vec<GroupId>> {
+        let group_ids = GROUP_OWNER_LIST
+            .prefix(from.clone())
+            .keys(ctx.store, None, None, Order::Ascending)
+            .collect::<Result<Vec<GroupId>, _>>()?;
+
+        let mut transferred = Vec::new();
+        for group_id in group_ids {
+            if GROUP_TO_PRODUCT.load(ctx.store, group_id)? != product_id || self.get_group_status(ctx.store, group_id)?.has_shipped() {
+                continue;
+            }
+
+            GROUP_OWNER.save(ctx.store, group_id, to)?;
+            GROUP_OWNER_LIST.remove(ctx.store, (from.clone(), group_id));
+            GROUP_OWNER_LIST.save(ctx.store, (to.clone(), group_id), &())?;
+            transferred.push(group_id);
         }
 
+        Ok(transferred)
+    }
+
+    pub fn reduce_purchase_in_group(&self, ctx: &mut StateContext, purchase: &Purchase, removed: u32) -> Result<()> {
+        self.assert_group_not_shipped(ctx.store, purchase.group_id)?;
+        GROUP_UNITS.update(ctx.store, purchase.group_id, |x| anyhow::Ok(x.unwrap_or_default() - removed))?;
+
         Ok(())
     }
 
+    pub fn get_group_owner(&self, store: &dyn Storage, group_id: GroupId) -> Result<Addr> {
+        GROUP_OWNER.load(store, group_id).map_err(|err| err.into())
+    }
+
+    pub fn get_group_purchase_ids(&self, store: &dyn Storage, group_id: GroupId) -> Result<Vec<PurchaseId>> {
+        GROUP_PURCHASES
+            .prefix(group_id)
+            .keys(store, None, None, Order::Ascending)
+            .collect::<Result<Vec<PurchaseId>, _>>()
+            .map_err(|err| err.into())
+    }
+
     pub fn get_group_count(&self, store: &dyn Storage, group_id: GroupId) -> Result<u32> {
         Ok(GROUP_LEN.may_load(store, group_id)?.unwrap_or_default())
     }
@@ -0,0 +1,182 @@
+use cosmwasm_std::{Addr, BankMsg, Coin, Order, Storage};
+use cw_storage_plus::{Bound, Item, Map};
+use shared::msg::{contract::warehouse::{event::{HoldEndedEvent, HoldPlacedEvent}, Hold, HoldId}, product::{ProductId, VariantId}, purchase::PurchaseId};
+use anyhow::{Context, Result};
+
+use super::{merchant::BOND_DENOM, State, StateContext};
+
+const HOLD_ID: Item<HoldId> = Item::new("hold-id");
+const HOLDS: Map<HoldId, Hold> = Map::new("holds");
+// (expiry in nanos, hold id), so sweeping only has to look at the front
+const HOLD_EXPIRIES: Map<(u64, HoldId), ()> = Map::new("hold-expiries");
+const HOLDER_OPEN_HOLDS: Map<&Addr, u32> = Map::new("holder-open-holds");
+
+const DEFAULT_SWEEP_LIMIT: u32 = 30;
+const MAX_OPEN_HOLDS_PER_HOLDER: u32 = 10;
+
+impl State<'_> {
+    pub fn place_hold(&self, ctx: &mut StateContext, msg_sender: Addr, funds: Vec<Coin>, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32, spender: String) -> Result<HoldId> {
+        if quantity == 0 {
+            anyhow::bail!("hold quantity must be greater than zero");
+        }
+        // it's an address on the payment chain, so we can't validate it here
+        if spender.is_empty() {
+            anyhow::bail!("hold must have a spender");
+        }
+
+        let config = self.get_config(ctx.store)?;
+        // the hold counts against the spender's purchase rules, so nobody else can lock them out of a product
+        if msg_sender.as_str() != spender && !config.hold_relayers.contains(&msg_sender) {
+            anyhow::bail!("only the spender or a hold relayer can place a hold for {}", spender);
+        }
+        let deposit = funds
+            .iter()
+            .find(|coin| coin.denom == BOND_DENOM)
+            .map(|coin| coin.amount)
+            .unwrap_or_default();
+        if deposit != config.hold_deposit {
+            anyhow::bail!("hold deposit must be exactly {}{} (sent: {}{})", config.hold_deposit, BOND_DENOM, deposit, BOND_DENOM);
+        }
+
+        let open_holds = HOLDER_OPEN_HOLDS.may_load(ctx.store, &msg_sender)?.unwrap_or_default() + 1;
+        if open_holds > MAX_OPEN_HOLDS_PER_HOLDER {
+            anyhow::bail!("{} already has {} open holds (max: {})", msg_sender, open_holds - 1, MAX_OPEN_HOLDS_PER_HOLDER);
+        }
+        HOLDER_OPEN_HOLDS.save(ctx.store, &msg_sender, &open_holds)?;
+
+        let product = self.get_product(ctx.store, product_id)?;
+        self.apply_hold_rules(ctx, &product, &spender, quantity)?;
+
+        self.remove_product_stock(ctx, product_id, variant_id, quantity)?;
+
+        let id = HOLD_ID.may_load(ctx.store)?.unwrap_or_default();
+        HOLD_ID.save(ctx.store, &(id + 1))?;
+
+        let hold = Hold {
+            id,
+            holder: msg_sender,
+            spender,
+            product_id,
+            variant_id,
+            quantity,
+            expires: self.env.block.time.plus_seconds(config.hold_seconds),
+            deposit,
+        };
+
+        HOLDS.save(ctx.store, id, &hold)?;
+        HOLD_EXPIRIES.save(ctx.store, (hold.expires.nanos(), id), &())?;
+
+        ctx.response_mut().add_event(HoldPlacedEvent { hold });
+
+        Ok(id)
+    }
+
+    pub fn release_hold(&self, ctx: &mut StateContext, msg_sender: Addr, id: HoldId) -> Result<()> {
+        let hold = self.load_hold(ctx.store, id)?;
+        if msg_sender != hold.holder {
+            anyhow::bail!("only the holder can release hold {}", id);
+        }
+
+        self.end_hold(ctx, &hold, hold.quantity, None)
+    }
+
+    pub fn sweep_holds(&self, ctx: &mut StateContext, limit: Option<u32>) -> Result<()> {
+        let now = self.env.block.time.nanos();
+        let expired = HOLD_EXPIRIES
+            .keys(ctx.store, None, Some(Bound::inclusive((now, HoldId::MAX))), Order::Ascending)
+            .take(limit.unwrap_or(DEFAULT_SWEEP_LIMIT) as usize)
+            .map(|key| key.map(|(_, id)| id))
+            .collect::<Result<Vec<HoldId>, _>>()?;
+
+        if expired.is_empty() {
+            anyhow::bail!("no expired holds to sweep");
+        }
+
+        for id in expired {
+            let hold = self.load_hold(ctx.store, id)?;
+            self.end_hold(ctx, &hold, hold.quantity, None)?;
+        }
+
+        Ok(())
+    }
+
+    // the purchase takes its stock from the hold, anything it doesn't need goes back to stock
+    pub fn consume_hold(&self, ctx: &mut StateContext, id: HoldId, purchase_id: PurchaseId, spender: &str, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32) -> Result<()> {
+        let hold = self.load_hold(ctx.store, id)?;
+
+        if self.env.block.time >= hold.expires {
+            anyhow::bail!("hold {} expired at {}", id, hold.expires);
+        }
+        if hold.spender != spender {
+            anyhow::bail!("hold {} belongs to a different spender", id);
+        }
+        if hold.product_id != product_id || hold.variant_id != variant_id {
+            anyhow::bail!("hold {} is for a different product", id);
+        }
+        if quantity > hold.quantity {
+            anyhow::bail!("purchase of {} is more than hold {} (held: {})", quantity, id, hold.quantity);
+        }
+
+        self.end_hold(ctx, &hold, hold.quantity - quantity, Some(purchase_id))
+    }
+
+    pub fn get_holds(&self, store: &dyn Storage, ids: Vec<HoldId>) -> Result<Vec<Hold>> {
+        ids
+            .into_iter()
+            .map(|id| self.load_hold(store, id))
+            .collect()
+    }
+
+    pub fn list_holds(&self, store: &dyn Storage, spender: Option<String>, limit: Option<u32>, start_after: Option<HoldId>) -> Result<Vec<Hold>> {
+        HOLDS
+            .range(store, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .map(|res| res.map(|(_, hold)| hold).map_err(anyhow::Error::from))
+            .filter(|res| match (res, &spender) {
+                (Ok(hold), Some(spender)) => hold.spender == *spender,
+                _ => true,
+            })
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .collect()
+    }
+
+    fn load_hold(&self, store: &dyn Storage, id: HoldId) -> Result<Hold> {
+        HOLDS
+            .may_load(store, id)?
+            .context(format!("hold {} not found", id))
+    }
+
+    fn end_hold(&self, ctx: &mut StateContext, hold: &Hold, returned_stock: u32, purchase_id: Option<PurchaseId>) -> Result<()> {
+        HOLDS.remove(ctx.store, hold.id);
+        HOLD_EXPIRIES.remove(ctx.store, (hold.expires.nanos(), hold.id));
+        match HOLDER_OPEN_HOLDS.may_load(ctx.store, &hold.holder)?.unwrap_or_default() {
+            0 | 1 => HOLDER_OPEN_HOLDS.remove(ctx.store, &hold.holder),
+            n => HOLDER_OPEN_HOLDS.save(ctx.store, &hold.holder, &(n - 1))?,
+        }
+        self.release_hold_rules(ctx, hold.product_id, &hold.spender)?;
+
+        // a hold that runs out instead of being used or released cost the merchant a sale, so they get the deposit
+        if !hold.deposit.is_zero() {
+            let recipient = if self.env.block.time >= hold.expires {
+                self.get_product_owner(ctx.store, hold.product_id)?
+            } else {
+                hold.holder.clone()
+            };
+
+            ctx.response_mut().add_message(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin::new(hold.deposit, BOND_DENOM)],
+            });
+        }
+
+        if returned_stock > 0 {
+            self.add_product_stock(ctx, hold.product_id, hold.variant_id, returned_stock)?;
+        }
+
+        ctx.response_mut().add_event(HoldEndedEvent {
+            hold_id: hold.id,
+            purchase_id,
+        });
+
+        Ok(())
+    }
+}
@@ -1,11 +1,11 @@
 use cosmwasm_std::{
-    from_binary, from_json, to_json_binary, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcMsg, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcTimeout, Storage
+    from_binary, from_json, to_json_binary, Binary, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcMsg, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcTimeout, Storage
 };
 use cw_storage_plus::Item;
 use shared::{ibc::{
     event::{IbcChannelCloseEvent, IbcChannelConnectEvent},
     validate_ibc_channel_order_and_version, TIMEOUT_SECONDS,
-}, msg::{contract::{nft::{Metadata, Trait}, warehouse::{IbcExecuteMsg, PurchaseError}}, product}};
+}, msg::{contract::{nft::{Metadata, Trait}, warehouse::{IbcExecuteMsg, PurchaseError}}, product::{self, ProductId, VariantId}, purchase::PurchaseId}};
 use anyhow::Result;
 
 use super::{State, StateContext};
@@ -26,6 +26,23 @@ impl State<'_> {
         }
     }
 
+    // outbound IBC message, where packet is then received on other chain
+    pub fn send_ibc_packet(&self, ctx: &mut StateContext, kind: IbcChannelKind, data: Binary) -> Result<()> {
+        let channel_id = self
+            .get_ibc_channel(ctx.store, kind)?
+            .endpoint
+            .channel_id;
+
+        ctx.response_mut().add_message(IbcMsg::SendPacket {
+            channel_id,
+            data,
+            // default timeout of two minutes.
+            timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(TIMEOUT_SECONDS)),
+        });
+
+        Ok(())
+    }
+
     pub fn handle_ibc_channel_open(&self, msg: IbcChannelOpenMsg) -> Result<()> {
         validate_ibc_channel_order_and_version(msg.channel(), msg.counterparty_version())?;
         Ok(())
@@ -74,57 +91,25 @@ impl State<'_> {
         ctx: &mut StateContext,
         msg: IbcPacketReceiveMsg,
     ) -> Result<()> {
+        let dest_channel_id = msg.packet.dest.channel_id;
+
         from_json(&msg.packet.data)
             .map_err(|err| err.into())
             .and_then(|msg| {
                 match msg {
-                    IbcExecuteMsg::Purchase{ owner, spender, product_id, quantity, fees} => {
-                        match self.make_purchase(ctx, spender.clone(), product_id, quantity, fees.clone()) {
+                    IbcExecuteMsg::Purchase{ owner, spender, product_id, variant_id, quantity, fees, hold_id, coupon, referrer, cw20 } => {
+                        match self.make_purchase(ctx, spender.clone(), product_id, variant_id, quantity, fees.clone(), hold_id, coupon, referrer, cw20) {
                             Ok(purchase_id) => {
-                                let mut metadata = Metadata::default();
-                                metadata.name = Some(format!("Purchase #{}", purchase_id));
-                                metadata.description = Some(format!("Product #{}", product_id));
-                                metadata.attributes = Some(vec![
-                                    Trait {
-                                        display_type: None,
-                                        trait_type: "product-id".to_string(),
-                                        value: product_id.to_string() 
-                                    },
-                                    Trait {
-                                        display_type: None,
-                                        trait_type: "purchase-id".to_string(),
-                                        value: purchase_id.to_string() 
-                                    },
-                                    Trait {
-                                        display_type: None,
-                                        trait_type: "quantity".to_string(),
-                                        value: quantity.to_string() 
-                                    },
-                                ]);
-                        
-                                let msg = shared::msg::contract::nft::IbcExecuteMsg::Mint { owner, metadata };
-                        
-                                // outbound IBC message, where packet is then received on other chain
-                                let channel_id = self
-                                    .get_ibc_channel(ctx.store, IbcChannelKind::Nft)?
-                                    .endpoint
-                                    .channel_id;
-                        
-                                ctx.response_mut().add_message(IbcMsg::SendPacket {
-                                    channel_id,
-                                    data: to_json_binary(&msg)?,
-                                    // default timeout of two minutes.
-                                    timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(TIMEOUT_SECONDS)),
-                                });
-
-                                Ok(())
+                                self.mint_receipt(ctx, owner, purchase_id, product_id, variant_id, quantity)
                             } 
                             Err(err) => {
                                 Err(anyhow::Error::new(PurchaseError {
                                     owner,
                                     spender,
                                     product_id,
+                                    variant_id,
                                     quantity,
+                                    hold_id,
                                     fees,
                                     reason: err.to_string() 
                                 }))
@@ -132,17 +117,96 @@ impl State<'_> {
                         }
                     },
 
+                    IbcExecuteMsg::PurchaseBatch { owner, spender, items, atomic, referrer, cw20 } => {
+                        self.purchase_batch(ctx, owner, spender, items, atomic, referrer, cw20)
+                    }
+
+                    IbcExecuteMsg::SubscriptionPurchase { owner, spender, subscription_id: _, product_id, variant_id, quantity, fees } => {
+                        self.subscription_purchase(ctx, owner, spender, product_id, variant_id, quantity, fees)
+                    }
+
+                    IbcExecuteMsg::Pledge { owner, spender, demand_id, quantity, fees } => {
+                        self.add_pledge(ctx, demand_id, owner, spender, quantity, fees)
+                    }
+
                     IbcExecuteMsg::RemovePurchase { id } => {
                         match self.remove_purchase(ctx, id) {
                             Ok(_) => Ok(()),
                             Err(err) => Err(err.into())
                         }
                     }
+
+                    IbcExecuteMsg::ReducePurchase { id, quantity } => {
+                        // only the receipt NFT holder can reduce, since the receipt is updated to match
+                        if dest_channel_id != self.get_ibc_channel(ctx.store, IbcChannelKind::Nft)?.endpoint.channel_id {
+                            anyhow::bail!("purchases can only be reduced from the nft channel");
+                        }
+                        self.reduce_purchase(ctx, id, quantity)
+                    }
+
+                    IbcExecuteMsg::ConfirmDelivery { id } => {
+                        // only the receipt NFT holder can confirm, which is checked on the nft side
+                        if dest_channel_id != self.get_ibc_channel(ctx.store, IbcChannelKind::Nft)?.endpoint.channel_id {
+                            anyhow::bail!("delivery can only be confirmed from the nft channel");
+                        }
+                        self.confirm_delivery(ctx, id)
+                    }
+
+                    IbcExecuteMsg::OpenDispute { id, evidence } => {
+                        // same as confirming delivery, only the receipt NFT holder can dispute
+                        if dest_channel_id != self.get_ibc_channel(ctx.store, IbcChannelKind::Nft)?.endpoint.channel_id {
+                            anyhow::bail!("disputes can only be opened from the nft channel");
+                        }
+                        self.open_dispute(ctx, id, evidence)
+                    }
+
+                    IbcExecuteMsg::Review { id, rating, comment } => {
+                        // only the receipt NFT holder can review
+                        if dest_channel_id != self.get_ibc_channel(ctx.store, IbcChannelKind::Nft)?.endpoint.channel_id {
+                            anyhow::bail!("reviews can only be sent from the nft channel");
+                        }
+                        self.add_review(ctx, id, rating, comment)
+                    }
                     
                 }
             })
     }
 
+    // sends the purchase receipt to the nft chain
+    pub fn mint_receipt(&self, ctx: &mut StateContext, owner: String, purchase_id: PurchaseId, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32) -> Result<()> {
+        let mut metadata = Metadata::default();
+        metadata.name = Some(format!("Purchase #{}", purchase_id));
+        metadata.description = Some(format!("Product #{}", product_id));
+        metadata.attributes = Some(vec![
+            Trait {
+                display_type: None,
+                trait_type: "product-id".to_string(),
+                value: product_id.to_string() 
+            },
+            Trait {
+                display_type: None,
+                trait_type: "purchase-id".to_string(),
+                value: purchase_id.to_string() 
+            },
+            Trait {
+                display_type: None,
+                trait_type: "quantity".to_string(),
+                value: quantity.to_string() 
+            },
+        ]);
+        if let Some(variant_id) = variant_id {
+            metadata.attributes.get_or_insert_with(Vec::new).push(Trait {
+                display_type: None,
+                trait_type: "variant-id".to_string(),
+                value: variant_id.to_string() 
+            });
+        }
+
+        let msg = shared::msg::contract::nft::IbcExecuteMsg::Mint { owner, metadata };
+
+        self.send_ibc_packet(ctx, IbcChannelKind::Nft, to_json_binary(&msg)?)
+    }
+
     pub fn handle_ibc_packet_ack(&self, _ack: IbcPacketAckMsg) -> Result<()> {
         // Nothing to do here. We don't keep any state about the other
         // chain, just deliver messages so nothing to update.
@@ -0,0 +1,218 @@
+use cosmwasm_std::{Addr, BankMsg, Coin, Order, Storage, Uint128};
+use cw_storage_plus::{Bound, Map};
+use shared::{ibc::validate_channel_id, msg::{purchase::PurchaseId, contract::warehouse::{event::{BondSlashedEvent, MerchantRegisteredEvent}, Merchant, MerchantProfile, MerchantRole, PayoutRoute}}};
+use anyhow::{anyhow, Context, Result};
+
+use super::{State, StateContext};
+
+const MERCHANTS: Map<&Addr, MerchantProfile> = Map::new("merchants");
+const MERCHANT_VERIFIED: Map<&Addr, bool> = Map::new("merchant-verified");
+const MERCHANT_BONDS: Map<&Addr, Uint128> = Map::new("merchant-bonds");
+const MERCHANT_TIERS: Map<&Addr, String> = Map::new("merchant-tiers");
+
+pub const BOND_DENOM: &str = "untrn";
+
+impl State<'_> {
+    pub fn register_merchant(&self, ctx: &mut StateContext, merchant: Addr, profile: MerchantProfile) -> Result<()> {
+        if MERCHANTS.has(ctx.store, &merchant) {
+            anyhow::bail!("merchant {} is already registered", merchant);
+        }
+        validate_profile(&profile)?;
+
+        MERCHANTS.save(ctx.store, &merchant, &profile)?;
+
+        ctx.response_mut().add_event(MerchantRegisteredEvent {
+            merchant,
+            display_name: profile.display_name,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_merchant_profile(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: Option<String>, profile: MerchantProfile) -> Result<()> {
+        let merchant = self.resolve_merchant(ctx.store, &msg_sender, merchant, MerchantRole::Admin)?;
+        self.assert_registered_merchant(ctx.store, &merchant)?;
+        validate_profile(&profile)?;
+
+        MERCHANTS.save(ctx.store, &merchant, &profile)?;
+
+        Ok(())
+    }
+
+    pub fn set_merchant_verified(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: String, verified: bool) -> Result<()> {
+        self.assert_admin(ctx.store, &msg_sender)?;
+        let merchant = self.api.addr_validate(&merchant)?;
+        self.assert_registered_merchant(ctx.store, &merchant)?;
+
+        MERCHANT_VERIFIED.save(ctx.store, &merchant, &verified)?;
+
+        Ok(())
+    }
+
+    pub fn set_merchant_tier(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: String, tier: Option<String>) -> Result<()> {
+        self.assert_admin(ctx.store, &msg_sender)?;
+        let merchant = self.api.addr_validate(&merchant)?;
+        self.assert_registered_merchant(ctx.store, &merchant)?;
+
+        match tier {
+            Some(tier) if tier.is_empty() => anyhow::bail!("tier cannot be empty"),
+            Some(tier) => MERCHANT_TIERS.save(ctx.store, &merchant, &tier)?,
+            None => MERCHANT_TIERS.remove(ctx.store, &merchant),
+        }
+
+        Ok(())
+    }
+
+    pub fn get_merchant_tier(&self, store: &dyn Storage, merchant: &Addr) -> Result<Option<String>> {
+        MERCHANT_TIERS.may_load(store, merchant).map_err(|err| err.into())
+    }
+
+    pub fn deposit_bond(&self, ctx: &mut StateContext, merchant: Addr, funds: Vec<Coin>) -> Result<()> {
+        self.assert_registered_merchant(ctx.store, &merchant)?;
+
+        let amount = funds.iter().find_map(|coin| {
+            if coin.denom == BOND_DENOM {
+                Some(coin.amount)
+            } else {
+                None
+            }
+        }).ok_or_else(|| anyhow!(format!("must send {} to deposit a bond", BOND_DENOM)))?;
+
+        MERCHANT_BONDS.update(ctx.store, &merchant, |bond| anyhow::Ok(bond.unwrap_or_default() + amount))?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_bond(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: Option<String>, amount: Uint128) -> Result<()> {
+        let merchant = self.resolve_merchant(ctx.store, &msg_sender, merchant, MerchantRole::Finance)?;
+
+        // otherwise a merchant could pull the bond out from under the arbiter
+        let open_disputes = self.get_merchant_open_disputes(ctx.store, &merchant)?;
+        if open_disputes > 0 {
+            anyhow::bail!("cannot withdraw bond with {} disputes that haven't been closed", open_disputes);
+        }
+
+        self.remove_bond(ctx, &merchant, amount)?;
+
+        ctx.response_mut().add_message(BankMsg::Send {
+            to_address: merchant.to_string(),
+            amount: vec![Coin::new(amount, BOND_DENOM)],
+        });
+
+        Ok(())
+    }
+
+    // the bond lives on this chain but the buyer is on the payment chain, so it goes over ICS-20
+    pub fn slash_bond(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: String, amount: Uint128, purchase_id: PurchaseId) -> Result<()> {
+        let config = self.get_config(ctx.store)?;
+        if msg_sender != config.arbiter {
+            anyhow::bail!("only the arbiter can slash bonds");
+        }
+        let channel_id = config.payment_transfer_channel.context("no transfer channel to the payment chain is configured")?;
+        let merchant = self.api.addr_validate(&merchant)?;
+
+        let purchase = self.try_get_purchase(ctx.store, purchase_id)?.context("purchase not found")?;
+        if self.get_group_owner(ctx.store, purchase.group_id)? != merchant {
+            anyhow::bail!("purchase {} was not sold by {}", purchase_id, merchant);
+        }
+
+        // one slash per dispute, capped at what was disputed
+        self.finish_dispute(ctx, purchase_id, Some(amount))?;
+        self.remove_bond(ctx, &merchant, amount)?;
+        self.send_slash_transfer(ctx, channel_id, purchase.spender.clone(), amount)?;
+
+        ctx.response_mut().add_event(BondSlashedEvent {
+            merchant,
+            amount,
+            purchase_id,
+            recipient: purchase.spender,
+        });
+
+        Ok(())
+    }
+
+    pub fn assert_registered_merchant(&self, store: &dyn Storage, merchant: &Addr) -> Result<()> {
+        if !MERCHANTS.has(store, merchant) {
+            anyhow::bail!("{} is not a registered merchant", merchant);
+        }
+        Ok(())
+    }
+
+    pub fn get_merchant(&self, store: &dyn Storage, merchant: &Addr) -> Result<Merchant> {
+        let profile = MERCHANTS
+            .may_load(store, merchant)?
+            .context(format!("{} is not a registered merchant", merchant))?;
+
+        Ok(Merchant {
+            address: merchant.clone(),
+            profile,
+            verified: MERCHANT_VERIFIED.may_load(store, merchant)?.unwrap_or_default(),
+            tier: MERCHANT_TIERS.may_load(store, merchant)?,
+            bond: MERCHANT_BONDS.may_load(store, merchant)?.unwrap_or_default(),
+        })
+    }
+
+    pub fn list_merchants(&self, store: &dyn Storage, verified_only: bool, limit: Option<u32>, start_after: Option<String>) -> Result<Vec<Merchant>> {
+        let start_after = start_after.map(Addr::unchecked);
+
+        MERCHANTS
+            .keys(store, start_after.as_ref().map(Bound::exclusive), None, Order::Ascending)
+            .map(|merchant| self.get_merchant(store, &merchant?))
+            .filter(|res| match res {
+                Ok(merchant) => !verified_only || merchant.verified,
+                Err(_) => true,
+            })
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .collect()
+    }
+
+    // the merchant's address on the payment chain, where settlements are sent
+    // None if they haven't set one, in which case settlements are withheld until they do
+    pub fn get_merchant_payout_address(&self, store: &dyn Storage, merchant: &Addr) -> Result<Option<String>> {
+        let chain_id = self.get_config(store)?.payment_chain_id;
+
+        Ok(self.get_merchant(store, merchant)?
+            .profile
+            .payout_addresses
+            .into_iter()
+            .find(|payout| payout.chain_id == chain_id)
+            .map(|payout| payout.address))
+    }
+
+    // where settlements are forwarded from the payment chain, if anywhere
+    pub fn get_merchant_payout_route(&self, store: &dyn Storage, merchant: &Addr) -> Result<Option<PayoutRoute>> {
+        let chain_id = self.get_config(store)?.payment_chain_id;
+
+        Ok(self.get_merchant(store, merchant)?
+            .profile
+            .payout_route
+            .filter(|route| route.chain_id != chain_id))
+    }
+
+    fn remove_bond(&self, ctx: &mut StateContext, merchant: &Addr, amount: Uint128) -> Result<()> {
+        let bond = MERCHANT_BONDS.may_load(ctx.store, merchant)?.unwrap_or_default();
+        if bond < amount {
+            anyhow::bail!("not enough bond (wanted: {}, available: {})", amount, bond);
+        }
+        MERCHANT_BONDS.save(ctx.store, merchant, &(bond - amount))?;
+
+        Ok(())
+    }
+}
+
+fn validate_profile(profile: &MerchantProfile) -> Result<()> {
+    if profile.display_name.is_empty() {
+        anyhow::bail!("display name cannot be empty");
+    }
+    // they're addresses on other chains, so we can't validate them here
+    if profile.payout_addresses.iter().any(|payout| payout.chain_id.is_empty() || payout.address.is_empty()) {
+        anyhow::bail!("payout addresses need both a chain id and an address");
+    }
+    if profile.payout_route.as_ref().is_some_and(|route| route.chain_id.is_empty() || route.channel_id.is_empty() || route.receiver.is_empty()) {
+        anyhow::bail!("payout route needs a chain id, a channel id and a receiver");
+    }
+    if let Some(route) = &profile.payout_route {
+        validate_channel_id(&route.channel_id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{cmp::Ordering, collections::BTreeMap, iter::Peekable, ops::Bound};
+
+use cosmwasm_std::{Order, Record, Storage};
+use anyhow::Result;
+
+use super::{State, StateContext};
+
+impl State<'_> {
+    // runs f against a copy of the storage and response, which only replace the real ones if it succeeds
+    // so that one failed step can be dropped without failing everything else in the transaction
+    pub fn isolated<T>(&self, ctx: &mut StateContext, f: impl FnOnce(&mut StateContext) -> Result<T>) -> Result<T> {
+        let mut overlay = StorageOverlay::new(&*ctx.store);
+        let mut isolated_ctx = StateContext {
+            store: &mut overlay,
+            response: ctx.response.clone(),
+        };
+
+        let res = f(&mut isolated_ctx);
+        let response = isolated_ctx.response;
+        let writes = overlay.writes;
+
+        if res.is_ok() {
+            for (key, value) in writes {
+                match value {
+                    Some(value) => ctx.store.set(&key, &value),
+                    None => ctx.store.remove(&key),
+                }
+            }
+            ctx.response = response;
+        }
+
+        res
+    }
+}
+
+// copy-on-write view of the contract storage, writes are kept in memory until applied (or dropped with it)
+pub struct StorageOverlay<'a> {
+    base: &'a dyn Storage,
+    // None marks a removed key
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<'a> StorageOverlay<'a> {
+    pub fn new(base: &'a dyn Storage) -> Self {
+        Self {
+            base,
+            writes: BTreeMap::new(),
+        }
+    }
+}
+
+impl Storage for StorageOverlay<'_> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.writes.get(key) {
+            Some(value) => value.clone(),
+            None => self.base.get(key),
+        }
+    }
+
+    fn range<'b>(&'b self, start: Option<&[u8]>, end: Option<&[u8]>, order: Order) -> Box<dyn Iterator<Item = Record> + 'b> {
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                return Box::new(std::iter::empty());
+            }
+        }
+
+        let bounds = (
+            start.map_or(Bound::Unbounded, |start| Bound::Included(start.to_vec())),
+            end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_vec())),
+        );
+        let writes: Box<dyn Iterator<Item = (&Vec<u8>, &Option<Vec<u8>>)> + 'b> = match order {
+            Order::Ascending => Box::new(self.writes.range(bounds)),
+            Order::Descending => Box::new(self.writes.range(bounds).rev()),
+        };
+
+        Box::new(MergedRange {
+            base: self.base.range(start, end, order).peekable(),
+            writes: writes.peekable(),
+            order,
+        })
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.writes.insert(key.to_vec(), Some(value.to_vec()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.writes.insert(key.to_vec(), None);
+    }
+}
+
+// walks the base range and the overlay's writes side by side, in the requested order
+// on the same key the write wins (and a removal hides the base record)
+struct MergedRange<'a> {
+    base: Peekable<Box<dyn Iterator<Item = Record> + 'a>>,
+    writes: Peekable<Box<dyn Iterator<Item = (&'a Vec<u8>, &'a Option<Vec<u8>>)> + 'a>>,
+    order: Order,
+}
+
+impl Iterator for MergedRange<'_> {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Record> {
+        loop {
+            let next = match (self.base.peek(), self.writes.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((base_key, _)), Some((write_key, _))) => match self.order {
+                    Order::Ascending => base_key.cmp(*write_key),
+                    Order::Descending => (*write_key).cmp(base_key),
+                },
+            };
+
+            if next == Ordering::Less {
+                return self.base.next();
+            }
+            if next == Ordering::Equal {
+                self.base.next();
+            }
+            if let Some((key, Some(value))) = self.writes.next() {
+                return Some((key.clone(), value.clone()));
+            }
+        }
+    }
+}
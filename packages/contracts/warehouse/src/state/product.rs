@@ -1,16 +1,55 @@
-use cosmwasm_std::{Addr, Storage};
+use cosmwasm_std::{Addr, Decimal256, Storage};
 use cw_storage_plus::{Bound, Map};
-use shared::msg::{contract::warehouse::{event::AddProductEvent, NewProduct}, product::{Product, ProductId}};
-use anyhow::Result;
+use shared::msg::{contract::warehouse::{event::{AddProductEvent, ProductTransferProposedEvent, ProductTransferredEvent}, MerchantRole, NewProduct}, product::{PricingMode, Product, ProductId, ProductVariant, VariantId}};
+use anyhow::{Context, Result};
 
 use super::{State, StateContext};
 
 const PRODUCTS: Map<ProductId, NewProduct> = Map::new("products");
 const PRODUCT_OWNERS: Map<ProductId, Addr> = Map::new("product-owners");
 const PRODUCT_OWNER_LIST: Map<(Addr, ProductId), ()> = Map::new("product-owner-list");
+// proposed new owner, waiting to accept
+const PRODUCT_TRANSFERS: Map<ProductId, Addr> = Map::new("product-transfers");
 
 impl State<'_> {
-    pub fn add_product(&self, ctx: &mut StateContext, owner: Addr, product: NewProduct) -> Result<Product> {
+    pub fn add_product(&self, ctx: &mut StateContext, msg_sender: Addr, owner: Option<String>, product: NewProduct) -> Result<Product> {
+        let owner = self.resolve_merchant(ctx.store, &msg_sender, owner, MerchantRole::Catalog)?;
+        self.assert_registered_merchant(ctx.store, &owner)?;
+
+        if product.group_capacity.is_some_and(|capacity| capacity.remaining(0, 0) == 0) {
+            anyhow::bail!("group capacity must be greater than zero");
+        }
+        if product.referral_share > Decimal256::one() {
+            anyhow::bail!("referral share cannot be more than 1");
+        }
+        if let PricingMode::Dutch { floor_price, start, end, .. } = &product.pricing {
+            if *floor_price > product.price {
+                anyhow::bail!("dutch auction floor price cannot be above the starting price");
+            }
+            if start >= end {
+                anyhow::bail!("dutch auction must end after it starts");
+            }
+        }
+
+        let mut product = product;
+        if !product.variants.is_empty() {
+            if product.stock != 0 {
+                anyhow::bail!("stock is tracked per variant, product stock must be zero");
+            }
+            if product.variants.iter().any(|variant| variant.name.is_empty()) {
+                anyhow::bail!("variant names cannot be empty");
+            }
+            // the lowest the base price can go, so a cheaper variant never ends up below zero
+            let min_price = match &product.pricing {
+                PricingMode::Dutch { floor_price, .. } => *floor_price,
+                _ => product.price,
+            };
+            for variant in &product.variants {
+                variant.price_from(min_price)?;
+            }
+            product.stock = product.variants.iter().map(|variant| variant.stock).sum();
+        }
+
         let id = PRODUCTS
             .keys(ctx.store, None, None, cosmwasm_std::Order::Descending)
             .next()
@@ -21,33 +60,103 @@ impl State<'_> {
         PRODUCT_OWNERS.save(ctx.store, id, &owner)?;
         PRODUCT_OWNER_LIST.save(ctx.store, (owner, id), &())?;
 
+        let product = product.into_product(id);
+
         ctx.response.add_event(AddProductEvent {
-            product: Product {
-                id,
-                name: product.name.clone(),
-                price: product.price,
-                stock: product.stock
-            }
+            product: product.clone()
+        });
+
+        Ok(product)
+    }
+
+    pub fn propose_product_transfer(&self, ctx: &mut StateContext, msg_sender: Addr, product_id: ProductId, new_owner: String) -> Result<()> {
+        let owner = self.get_product_owner(ctx.store, product_id)?;
+        self.assert_merchant_role(ctx.store, &owner, &msg_sender, MerchantRole::Admin)?;
+        let new_owner = self.api.addr_validate(&new_owner)?;
+
+        if new_owner == owner {
+            anyhow::bail!("product {} is already owned by {}", product_id, owner);
+        }
+        self.assert_registered_merchant(ctx.store, &new_owner)?;
+
+        PRODUCT_TRANSFERS.save(ctx.store, product_id, &new_owner)?;
+
+        ctx.response_mut().add_event(ProductTransferProposedEvent {
+            product_id,
+            owner,
+            new_owner,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_product_transfer(&self, ctx: &mut StateContext, msg_sender: Addr, product_id: ProductId) -> Result<()> {
+        let new_owner = PRODUCT_TRANSFERS
+            .may_load(ctx.store, product_id)?
+            .context(format!("no pending transfer for product {}", product_id))?;
+        self.assert_merchant_role(ctx.store, &new_owner, &msg_sender, MerchantRole::Admin)?;
+        // could have been deregistered in the meantime
+        self.assert_registered_merchant(ctx.store, &new_owner)?;
+
+        let previous_owner = self.get_product_owner(ctx.store, product_id)?;
+
+        PRODUCT_TRANSFERS.remove(ctx.store, product_id);
+        PRODUCT_OWNERS.save(ctx.store, product_id, &new_owner)?;
+        PRODUCT_OWNER_LIST.remove(ctx.store, (previous_owner.clone(), product_id));
+        PRODUCT_OWNER_LIST.save(ctx.store, (new_owner.clone(), product_id), &())?;
+
+        let group_ids = self.transfer_unshipped_groups(ctx, product_id, &previous_owner, &new_owner)?;
+
+        ctx.response_mut().add_event(ProductTransferredEvent {
+            product_id,
+            previous_owner,
+            owner: new_owner,
+            group_ids,
         });
 
-        Ok(Product {
-            id,
-            name: product.name,
-            price: product.price,
-            stock: product.stock
-        })
+        Ok(())
+    }
+
+    pub fn cancel_product_transfer(&self, ctx: &mut StateContext, msg_sender: Addr, product_id: ProductId) -> Result<()> {
+        let owner = self.get_product_owner(ctx.store, product_id)?;
+        self.assert_merchant_role(ctx.store, &owner, &msg_sender, MerchantRole::Admin)?;
+
+        if !PRODUCT_TRANSFERS.has(ctx.store, product_id) {
+            anyhow::bail!("no pending transfer for product {}", product_id);
+        }
+        PRODUCT_TRANSFERS.remove(ctx.store, product_id);
+
+        Ok(())
     }
 
-    pub fn add_product_stock(&self, ctx: &mut StateContext, id: ProductId, quantity: u32) -> Result<()> {
+    pub fn get_product_transfer(&self, store: &dyn Storage, product_id: ProductId) -> Result<Option<Addr>> {
+        PRODUCT_TRANSFERS
+            .may_load(store, product_id)
+            .map_err(|err| err.into())
+    }
+
+    // the product stock is kept as the total across variants, so both are updated together
+    pub fn add_product_stock(&self, ctx: &mut StateContext, id: ProductId, variant_id: Option<VariantId>, quantity: u32) -> Result<()> {
         let mut product = PRODUCTS.load(ctx.store, id)?;
+
+        if let Some(variant) = get_variant_mut(id, &mut product, variant_id)? {
+            variant.stock += quantity;
+        }
         product.stock += quantity;
         PRODUCTS.save(ctx.store, id, &product)?;
 
         Ok(())
     }
 
-    pub fn remove_product_stock(&self, ctx: &mut StateContext, id: ProductId, quantity: u32) -> Result<()> {
+    pub fn remove_product_stock(&self, ctx: &mut StateContext, id: ProductId, variant_id: Option<VariantId>, quantity: u32) -> Result<()> {
         let mut product = PRODUCTS.load(ctx.store, id)?;
+
+        if let Some(variant) = get_variant_mut(id, &mut product, variant_id)? {
+            if variant.stock < quantity {
+                anyhow::bail!("not enough stock to remove from variant {} (wanted: {}, available: {})", variant.name, quantity, variant.stock);
+            }
+            variant.stock -= quantity;
+        }
         if product.stock < quantity {
             anyhow::bail!("not enough stock to remove (wanted: {}, available: {})", quantity, product.stock);
         }
@@ -65,12 +174,7 @@ impl State<'_> {
                     .take(limit.unwrap_or(u32::MAX) as usize)
                     .map(|res| {
                         let (id, product) = res?;
-                        anyhow::Ok(Product {
-                            id,
-                            name: product.name.clone(),
-                            price: product.price,
-                            stock: product.stock
-                        })
+                        anyhow::Ok(product.into_product(id))
                     })
                     .collect::<Result<Vec<Product>, _>>()?;
 
@@ -83,13 +187,7 @@ impl State<'_> {
                     .take(limit.unwrap_or(u32::MAX) as usize)
                     .map(|id| {
                         let id = id?;
-                        let product = self.get_product(store, id)?;
-                        anyhow::Ok(Product {
-                            id,
-                            name: product.name.clone(),
-                            price: product.price,
-                            stock: product.stock
-                        })
+                        self.get_product(store, id)
                     })
                     .collect::<Result<Vec<Product>, _>>()?;
 
@@ -101,12 +199,7 @@ impl State<'_> {
     pub fn get_product(&self, store: &dyn Storage, id: ProductId) -> Result<Product> {
         PRODUCTS
             .load(store, id)
-            .map(|product| Product {
-                id,
-                name: product.name,
-                price: product.price,
-                stock: product.stock
-            })
+            .map(|product| product.into_product(id))
             .map_err(|err| err.into())
     }
 
@@ -121,14 +214,21 @@ impl State<'_> {
             .map(|id| {
                 PRODUCTS
                     .load(store, id)
-                    .map(|product| Product {
-                        id,
-                        name: product.name,
-                        price: product.price,
-                        stock: product.stock
-                    })
+                    .map(|product| product.into_product(id))
                     .map_err(|err| err.into())
             })
             .collect::<Result<Vec<Product>, _>>()
     }
-}
\ No newline at end of file
+}
+
+fn get_variant_mut(id: ProductId, product: &mut NewProduct, variant_id: Option<VariantId>) -> Result<Option<&mut ProductVariant>> {
+    match variant_id {
+        None if product.variants.is_empty() => Ok(None),
+        None => anyhow::bail!("product {} has variants, one must be chosen", id),
+        Some(variant_id) => product
+            .variants
+            .get_mut(variant_id as usize)
+            .map(Some)
+            .context(format!("product {} has no variant {}", id, variant_id)),
+    }
+}
@@ -1,6 +1,6 @@
 use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal256, IbcMsg, IbcTimeout, Storage, Uint128};
 use cw_storage_plus::{Bound, Item, Map};
-use shared::{ibc::TIMEOUT_SECONDS, msg::{contract::{payment::{IbcExecuteMsg as PaymentIbcExecuteMsg, Refund}, warehouse::{event::{AddProductEvent, PurchaseEvent}, NewProduct}}, product::{Product, ProductId}, purchase::{Purchase, PurchaseId}}};
+use shared::{ibc::TIMEOUT_SECONDS, msg::{contract::{payment::{IbcExecuteMsg as PaymentIbcExecuteMsg, Refund}, nft::IbcExecuteMsg as NftIbcExecuteMsg, warehouse::{event::{AddProductEvent, PurchaseEvent, PurchaseReducedEvent}, Coupon, HoldId, NewProduct}}, product::{Product, ProductId, VariantId}, purchase::{Purchase, PurchaseId}}};
 use anyhow::Result;
 
 use super::{ibc::IbcChannelKind, State, StateContext};
@@ -18,39 +18,101 @@ impl State<'_> {
         Ok(purchases)
     }
 
-    pub fn make_purchase(&self, ctx: &mut StateContext, spender: String, product_id: ProductId, quantity: u32, fees: Uint128) -> Result<PurchaseId> {
+    pub fn make_purchase(&self, ctx: &mut StateContext, spender: String, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32, fees: Uint128, hold_id: Option<HoldId>, coupon: Option<Coupon>, referrer: Option<String>, cw20: Option<String>) -> Result<PurchaseId> {
         let product = self.get_product(ctx.store, product_id)?;
+        if cw20 != product.cw20 {
+            let asset = |cw20: &Option<String>| cw20.clone().unwrap_or_else(|| "the native denom".to_string());
+            anyhow::bail!("product {} is priced in {}, not {}", product_id, asset(&product.cw20), asset(&cw20));
+        }
+        // it's an address on the payment chain, so we can't validate it here
+        if referrer.as_ref().is_some_and(|referrer| referrer.is_empty() || *referrer == spender) {
+            anyhow::bail!("referrer must be someone other than the spender");
+        }
         let fees = Decimal256::from_ratio(fees.u128(), 1u32);
-        let cost = product.price * Decimal256::from_ratio(quantity, 1u32);
+        let price = product.current_price(self.env.block.time);
+        let unit_price = product.unit_price_from(price, variant_id)?;
+
+        let id = PURCHASE_ID.may_load(ctx.store)?.unwrap_or_default();
+        PURCHASE_ID.save(ctx.store, &(id + 1))?;
+
+        let discount = match &coupon {
+            Some(coupon) => self.redeem_coupon(ctx, coupon, id, product_id, unit_price)?,
+            None => Decimal256::zero(),
+        };
+
+        let cost = (unit_price - discount) * Decimal256::from_ratio(quantity, 1u32);
         if fees < cost {
             anyhow::bail!("fees must cover the cost of the purchase");
         }
 
-        self.remove_product_stock(ctx, product_id, quantity)?;
+        // a held purchase already has its stock set aside
+        let backordered = match hold_id {
+            Some(hold_id) => {
+                self.consume_hold(ctx, hold_id, id, &spender, product_id, variant_id, quantity)?;
+                0
+            },
+            None => self.take_stock_or_backorder(ctx, &product, variant_id, quantity)?,
+        };
 
-        let id = PURCHASE_ID.may_load(ctx.store)?.unwrap_or_default();
-        PURCHASE_ID.save(ctx.store, &(id + 1))?;
+        let group_id = self.add_purchase_to_group(ctx, id, product_id, quantity)?;
 
-        let group_id = self.add_purchase_to_group(ctx, id, product_id)?;
+        self.apply_purchase_rules(ctx, &product, &spender, group_id, quantity)?;
 
         let purchase = Purchase {
             id,
             product_id,
+            variant_id,
             quantity,
+            backordered,
             spender,
-            group_id
+            group_id,
+            price: (price != product.price).then_some(price),
+            discount,
+            referrer,
+            cw20,
         };
 
 
         PURCHASES.save(ctx.store, id, &purchase)?;
+        self.record_backorder(ctx, &purchase)?;
 
 
         ctx.response_mut()
             .add_event(PurchaseEvent{ purchase});
 
+        self.lock_group_if_full(ctx, group_id)?;
+
         Ok(id)
     }
 
+    // a subscription sends the same fees every period, so the difference from today's price goes back
+    pub fn subscription_purchase(&self, ctx: &mut StateContext, owner: String, spender: String, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32, fees: Uint128) -> Result<()> {
+        let purchase_id = self.make_purchase(ctx, spender.clone(), product_id, variant_id, quantity, fees, None, None, None, None)?;
+        self.mint_receipt(ctx, owner, purchase_id, product_id, variant_id, quantity)?;
+
+        let purchase = PURCHASES.load(ctx.store, purchase_id)?;
+        let product = self.get_product(ctx.store, product_id)?;
+        let cost: Uint128 = (purchase.unit_price(&product)? * Decimal256::from_ratio(quantity, 1u32)).to_uint_ceil().to_string().parse()?;
+
+        let refund = fees.saturating_sub(cost);
+        if !refund.is_zero() {
+            let msg = PaymentIbcExecuteMsg::Refund { refunds: vec![
+                Refund {
+                    recipient: spender,
+                    amount: refund,
+                    cw20: None,
+                }
+            ]};
+            self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&msg)?)?;
+        }
+
+        ctx.response_mut().set_data(&purchase_id)
+    }
+
+    pub fn save_purchase(&self, ctx: &mut StateContext, purchase: &Purchase) -> Result<()> {
+        PURCHASES.save(ctx.store, purchase.id, purchase).map_err(|e| e.into())
+    }
+
     pub fn try_get_purchase(&self, store: &dyn Storage, id: PurchaseId) -> Result<Option<Purchase>> {
         PURCHASES.may_load(store, id).map_err(|e| e.into())
     }
@@ -60,33 +122,71 @@ impl State<'_> {
         let product = self.get_product(ctx.store, purchase.product_id)?;
 
         self.remove_purchase_from_group(ctx, purchase.id, purchase.product_id)?;
+        self.release_cancelled_purchase_rules(ctx, &purchase)?;
+        self.release_backorder(ctx, &purchase)?;
 
         PURCHASES.remove(ctx.store, id);
 
-        self.add_product_stock(ctx, product.id, purchase.quantity)?;
+        // backordered units were never taken from stock
+        self.add_product_stock(ctx, product.id, purchase.variant_id, purchase.quantity - purchase.backordered)?;
 
         // refund the purchase to the original spender (not the nft owner)
-        let refund = product.price * Decimal256::from_ratio(purchase.quantity, 1u32);
+        let refund = purchase.unit_price(&product)? * Decimal256::from_ratio(purchase.quantity, 1u32);
 
         let msg = PaymentIbcExecuteMsg::Refund { refunds: vec![
             Refund {
                 recipient: purchase.spender,
-                amount: refund.to_uint_floor().to_string().parse()?
+                amount: refund.to_uint_floor().to_string().parse()?,
+                cw20: purchase.cw20,
             }
         ]};
 
-        let channel_id = self
-            .get_ibc_channel(ctx.store, IbcChannelKind::Payment)?
-            .endpoint
-            .channel_id;
+        self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&msg)?)?;
 
-        ctx.response_mut().add_message(IbcMsg::SendPacket {
-            channel_id,
-            data: to_json_binary(&msg)?,
-            timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(TIMEOUT_SECONDS)),
-        });
+        Ok(())
+    }
+
+    pub fn reduce_purchase(&self, ctx: &mut StateContext, id: PurchaseId, removed: u32) -> Result<()> {
+        let mut purchase = PURCHASES.load(ctx.store, id)?;
+        let product = self.get_product(ctx.store, purchase.product_id)?;
 
+        if removed == 0 {
+            anyhow::bail!("must remove at least one unit");
+        }
+        if removed >= purchase.quantity {
+            anyhow::bail!("cannot remove {} of {} units, cancel the purchase instead", removed, purchase.quantity);
+        }
+
+        self.reduce_purchase_in_group(ctx, &purchase, removed)?;
+        self.release_reduced_purchase_rules(ctx, &purchase, removed)?;
+        let from_backorder = self.reduce_backorder(ctx, &mut purchase, removed)?;
+        self.add_product_stock(ctx, product.id, purchase.variant_id, removed - from_backorder)?;
+
+        purchase.quantity -= removed;
+        PURCHASES.save(ctx.store, id, &purchase)?;
+
+        // refund the removed units to the original spender (not the nft owner)
+        let refund: Uint128 = (purchase.unit_price(&product)? * Decimal256::from_ratio(removed, 1u32)).to_uint_floor().to_string().parse()?;
+
+        let msg = PaymentIbcExecuteMsg::Refund { refunds: vec![
+            Refund {
+                recipient: purchase.spender.clone(),
+                amount: refund,
+                cw20: purchase.cw20.clone(),
+            }
+        ]};
+        self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&msg)?)?;
+
+        let msg = NftIbcExecuteMsg::SetQuantity { purchase_id: id, quantity: purchase.quantity };
+        self.send_ibc_packet(ctx, IbcChannelKind::Nft, to_json_binary(&msg)?)?;
+
+        ctx.response_mut().add_event(PurchaseReducedEvent {
+            purchase_id: id,
+            removed,
+            quantity: purchase.quantity,
+            refund,
+        });
 
         Ok(())
     }
-}
\ No newline at end of file
+}
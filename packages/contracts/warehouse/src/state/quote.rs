@@ -0,0 +1,72 @@
+use cosmwasm_std::{Decimal256, Storage, Uint128};
+use shared::{msg::{contract::warehouse::{Coupon, GroupInfo, GroupStatus, PurchaseQuote}, product::{ProductId, VariantId}}, response::ResponseBuilder};
+use anyhow::{Context, Result};
+
+use super::{overlay::StorageOverlay, State, StateContext};
+
+impl State<'_> {
+    // runs the real make_purchase against a throwaway copy of the storage, so a quote can't drift from what a purchase does
+    pub fn quote_purchase(&self, store: &dyn Storage, product_id: ProductId, variant_id: Option<VariantId>, quantity: u32, spender: Option<String>, coupon: Option<Coupon>) -> Result<PurchaseQuote> {
+        let product = self.get_product(store, product_id)?;
+        let platform_fee = self.get_config(store)?.platform_fee;
+        let tier = self.get_merchant_tier(store, &self.get_product_owner(store, product_id)?)?;
+        let platform_fee_bps = platform_fee.fee_bps(tier.as_deref());
+        let units = Decimal256::from_ratio(quantity, 1u32);
+
+        let mut quote_store = StorageOverlay::new(store);
+        let mut ctx = StateContext {
+            store: &mut quote_store,
+            response: ResponseBuilder::new_mute_events(),
+        };
+
+        // the deposit comes out of the quote, so don't let the fee check be what rejects it
+        let purchase_id = match self.make_purchase(&mut ctx, spender.unwrap_or_default(), product_id, variant_id, quantity, Uint128::MAX, None, coupon, None, product.cw20.clone()) {
+            Ok(purchase_id) => purchase_id,
+            Err(err) => {
+                let unit_price = product.unit_price_from(product.current_price(self.env.block.time), variant_id).unwrap_or(product.price);
+                return Ok(PurchaseQuote {
+                    deposit: (unit_price * units).to_uint_ceil().to_string().parse()?,
+                    unit_price,
+                    group_id: None,
+                    group_price: None,
+                    next_tier_price: None,
+                    remaining_stock: product.available_stock(variant_id).unwrap_or(product.stock),
+                    backordered: 0,
+                    platform_fee_bps,
+                    platform_fee: Uint128::zero(),
+                    error: Some(err.to_string()),
+                });
+            }
+        };
+
+        let purchase = self.try_get_purchase(ctx.store, purchase_id)?.context("quoted purchase not found")?;
+        let product = self.get_product(ctx.store, product_id)?;
+        let group_info = self.get_group_info(ctx.store, purchase.group_id)?;
+
+        let unit_price = purchase.unit_price(&product)?;
+        let group_price = group_info.discounted_price(unit_price);
+
+        // nobody else can join once the group is locked or full
+        let next_tier_price = if group_info.status == GroupStatus::Pending && group_info.remaining_capacity != Some(0) {
+            Some(GroupInfo {
+                tier_count: group_info.tier_count + 1,
+                ..group_info.clone()
+            }.discounted_price(unit_price))
+        } else {
+            None
+        };
+
+        Ok(PurchaseQuote {
+            deposit: (unit_price * units).to_uint_ceil().to_string().parse()?,
+            unit_price,
+            group_id: Some(purchase.group_id),
+            group_price: Some(group_price),
+            next_tier_price,
+            remaining_stock: product.available_stock(variant_id)?,
+            backordered: purchase.backordered,
+            platform_fee_bps,
+            platform_fee: platform_fee.fee((group_price * units).to_uint_floor().to_string().parse()?, tier.as_deref()),
+            error: None,
+        })
+    }
+}
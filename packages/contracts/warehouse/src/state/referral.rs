@@ -0,0 +1,107 @@
+use cosmwasm_std::{Decimal256, Order, Storage, Uint128};
+use cw_storage_plus::{Bound, Map};
+use shared::msg::{contract::{payment::Refund, warehouse::{event::ReferralRewardEvent, GroupId, GroupReferral, ReferralStats}}, product::{Product, ProductId}, purchase::Purchase};
+use anyhow::{Context, Result};
+
+use super::{State, StateContext};
+
+// (product id, referrer) -> totals across the product's shipped purchases
+const PRODUCT_REFERRALS: Map<(ProductId, &str), ReferralStats> = Map::new("product-referrals");
+// (product id, (earned, units), referrer), so the leaderboard reads straight off the top in descending order
+const PRODUCT_REFERRAL_RANKS: Map<(ProductId, (u128, u32), &str), ()> = Map::new("product-referral-ranks");
+
+const DEFAULT_LEADERBOARD_LIMIT: u32 = 30;
+
+impl State<'_> {
+    // called at ship time with the buyer's discount refund, returns the referrer's cut of it (if any)
+    pub fn pay_referral(&self, ctx: &mut StateContext, product: &Product, purchase: &Purchase, refund: Uint128) -> Result<Option<Refund>> {
+        let referrer = match &purchase.referrer {
+            Some(referrer) => referrer,
+            None => return Ok(None),
+        };
+
+        let amount: Uint128 = (Decimal256::from_ratio(refund.u128(), 1u32) * product.referral_share.min(Decimal256::one()))
+            .to_uint_floor()
+            .to_string()
+            .parse()?;
+
+        let mut stats = PRODUCT_REFERRALS
+            .may_load(ctx.store, (product.id, referrer))?
+            .unwrap_or_else(|| ReferralStats {
+                referrer: referrer.clone(),
+                purchases: 0,
+                units: 0,
+                earned: Uint128::zero(),
+            });
+        PRODUCT_REFERRAL_RANKS.remove(ctx.store, (product.id, (stats.earned.u128(), stats.units), referrer));
+        stats.purchases += 1;
+        stats.units += purchase.quantity;
+        stats.earned += amount;
+        PRODUCT_REFERRALS.save(ctx.store, (product.id, referrer), &stats)?;
+        PRODUCT_REFERRAL_RANKS.save(ctx.store, (product.id, (stats.earned.u128(), stats.units), referrer), &())?;
+
+        if amount.is_zero() {
+            return Ok(None);
+        }
+
+        ctx.response_mut().add_event(ReferralRewardEvent {
+            purchase_id: purchase.id,
+            referrer: referrer.clone(),
+            amount,
+        });
+
+        Ok(Some(Refund {
+            recipient: referrer.clone(),
+            amount,
+            cw20: purchase.cw20.clone(),
+        }))
+    }
+
+    pub fn referral_leaderboard(&self, store: &dyn Storage, product_id: ProductId, limit: Option<u32>, start_after: Option<String>) -> Result<Vec<ReferralStats>> {
+        let start_after = match &start_after {
+            Some(referrer) => {
+                let stats = PRODUCT_REFERRALS
+                    .may_load(store, (product_id, referrer))?
+                    .context(format!("{} has no referrals for product {}", referrer, product_id))?;
+                Some(((stats.earned.u128(), stats.units), referrer.as_str()))
+            },
+            None => None,
+        };
+
+        PRODUCT_REFERRAL_RANKS
+            .sub_prefix(product_id)
+            .keys(store, None, start_after.map(Bound::exclusive), Order::Descending)
+            .take(limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT) as usize)
+            .map(|res| {
+                let (_, referrer) = res?;
+                PRODUCT_REFERRALS.load(store, (product_id, &referrer)).map_err(|err| err.into())
+            })
+            .collect()
+    }
+
+    pub fn get_group_referrals(&self, store: &dyn Storage, group_id: GroupId) -> Result<Vec<GroupReferral>> {
+        let mut referrals: Vec<GroupReferral> = Vec::new();
+
+        for purchase_id in self.get_group_purchase_ids(store, group_id)? {
+            let purchase = self.try_get_purchase(store, purchase_id)?.context(format!("purchase not found for group {}", group_id))?;
+            let referrer = match purchase.referrer {
+                Some(referrer) => referrer,
+                None => continue,
+            };
+
+            match referrals.iter_mut().find(|referral| referral.referrer == referrer) {
+                Some(referral) => {
+                    referral.purchase_ids.push(purchase.id);
+                    referral.units += purchase.quantity;
+                },
+                None => referrals.push(GroupReferral {
+                    referrer,
+                    purchase_ids: vec![purchase.id],
+                    units: purchase.quantity,
+                }),
+            }
+        }
+
+        Ok(referrals)
+    }
+}
@@ -0,0 +1,77 @@
+use cosmwasm_std::{Addr, Order, Storage};
+use cw_storage_plus::{Bound, Map};
+use shared::msg::{contract::warehouse::{event::ReviewEvent, MerchantReputation, Review}, purchase::PurchaseId};
+use anyhow::{Context, Result};
+
+use super::{State, StateContext};
+
+const REVIEWS: Map<PurchaseId, Review> = Map::new("reviews");
+const MERCHANT_REVIEWS: Map<(&Addr, PurchaseId), ()> = Map::new("merchant-reviews");
+const MERCHANT_REPUTATION: Map<&Addr, MerchantReputation> = Map::new("merchant-reputation");
+
+const MAX_COMMENT_LEN: usize = 280;
+
+impl State<'_> {
+    pub fn add_review(&self, ctx: &mut StateContext, purchase_id: PurchaseId, rating: u8, comment: String) -> Result<()> {
+        if !(1..=5).contains(&rating) {
+            anyhow::bail!("rating must be between 1 and 5");
+        }
+        if comment.len() > MAX_COMMENT_LEN {
+            anyhow::bail!("review is too long (max {} bytes)", MAX_COMMENT_LEN);
+        }
+
+        let purchase = self.try_get_purchase(ctx.store, purchase_id)?.context("purchase not found")?;
+        if !self.get_group_status(ctx.store, purchase.group_id)?.has_shipped() {
+            anyhow::bail!("purchase {} can only be reviewed once it has shipped", purchase_id);
+        }
+        if REVIEWS.has(ctx.store, purchase_id) {
+            anyhow::bail!("purchase {} was already reviewed", purchase_id);
+        }
+
+        // shipped groups stay with the merchant who shipped them, even if the product has moved on since
+        let merchant = self.get_group_owner(ctx.store, purchase.group_id)?;
+
+        REVIEWS.save(ctx.store, purchase_id, &Review {
+            purchase_id,
+            product_id: purchase.product_id,
+            merchant: merchant.clone(),
+            rating,
+            comment,
+            timestamp: self.env.block.time,
+        })?;
+        MERCHANT_REVIEWS.save(ctx.store, (&merchant, purchase_id), &())?;
+        MERCHANT_REPUTATION.update(ctx.store, &merchant, |reputation| {
+            let mut reputation = reputation.unwrap_or_default();
+            reputation.review_count += 1;
+            reputation.rating_total += rating as u64;
+            reputation.average_rating = reputation.average_rating();
+            anyhow::Ok(reputation)
+        })?;
+
+        ctx.response_mut().add_event(ReviewEvent {
+            purchase_id,
+            merchant,
+            rating,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_merchant_reputation(&self, store: &dyn Storage, merchant: &Addr) -> Result<MerchantReputation> {
+        let mut reputation = MERCHANT_REPUTATION.may_load(store, merchant)?.unwrap_or_default();
+        reputation.average_rating = reputation.average_rating();
+        Ok(reputation)
+    }
+
+    pub fn list_merchant_reviews(&self, store: &dyn Storage, merchant: &Addr, limit: Option<u32>, start_after: Option<PurchaseId>) -> Result<Vec<Review>> {
+        MERCHANT_REVIEWS
+            .prefix(merchant)
+            .keys(store, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .map(|purchase_id| {
+                let purchase_id = purchase_id?;
+                REVIEWS.load(store, purchase_id).map_err(|err| err.into())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+use cosmwasm_std::{Addr, Order, Storage, Timestamp};
+use cw_storage_plus::{Bound, Map};
+use shared::msg::contract::warehouse::{event::{RolesGrantedEvent, RolesRevokedEvent}, Delegate, MerchantRole};
+use anyhow::Result;
+
+use super::{State, StateContext};
+
+// (merchant, delegate) -> grant
+const DELEGATES: Map<(&Addr, &Addr), Delegate> = Map::new("merchant-delegates");
+
+impl State<'_> {
+    // every merchant-only action goes through here: the merchant itself can do anything,
+    // delegates need an unexpired grant with the role (or Admin)
+    pub fn assert_merchant_role(&self, store: &dyn Storage, merchant: &Addr, msg_sender: &Addr, role: MerchantRole) -> Result<()> {
+        if msg_sender == merchant {
+            return Ok(());
+        }
+
+        match DELEGATES.may_load(store, (merchant, msg_sender))? {
+            Some(delegate) if delegate.has_role(role, self.env.block.time) => Ok(()),
+            _ => anyhow::bail!("{} does not have the {:?} role for merchant {}", msg_sender, role, merchant),
+        }
+    }
+
+    // resolves who the sender is acting on behalf of (themselves if not set), and checks they're allowed to
+    pub fn resolve_merchant(&self, store: &dyn Storage, msg_sender: &Addr, merchant: Option<String>, role: MerchantRole) -> Result<Addr> {
+        let merchant = match merchant {
+            Some(merchant) => self.api.addr_validate(&merchant)?,
+            None => msg_sender.clone(),
+        };
+
+        self.assert_merchant_role(store, &merchant, msg_sender, role)?;
+
+        Ok(merchant)
+    }
+
+    pub fn grant_roles(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: Option<String>, delegate: String, roles: Vec<MerchantRole>, expires: Option<Timestamp>) -> Result<()> {
+        let merchant = self.resolve_merchant(ctx.store, &msg_sender, merchant, MerchantRole::Admin)?;
+        self.assert_registered_merchant(ctx.store, &merchant)?;
+        let delegate = self.api.addr_validate(&delegate)?;
+
+        if delegate == merchant {
+            anyhow::bail!("a merchant cannot delegate to itself");
+        }
+        if roles.is_empty() {
+            anyhow::bail!("must grant at least one role, use RevokeRoles to remove a delegate");
+        }
+        if expires.is_some_and(|expires| expires <= self.env.block.time) {
+            anyhow::bail!("grant would already be expired");
+        }
+
+        DELEGATES.save(ctx.store, (&merchant, &delegate), &Delegate {
+            address: delegate.clone(),
+            roles: roles.clone(),
+            expires,
+        })?;
+
+        ctx.response_mut().add_event(RolesGrantedEvent {
+            merchant,
+            delegate,
+            roles,
+            expires,
+        });
+
+        Ok(())
+    }
+
+    pub fn revoke_roles(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: Option<String>, delegate: String) -> Result<()> {
+        let merchant = self.resolve_merchant(ctx.store, &msg_sender, merchant, MerchantRole::Admin)?;
+        let delegate = self.api.addr_validate(&delegate)?;
+
+        if !DELEGATES.has(ctx.store, (&merchant, &delegate)) {
+            anyhow::bail!("{} is not a delegate of merchant {}", delegate, merchant);
+        }
+        DELEGATES.remove(ctx.store, (&merchant, &delegate));
+
+        ctx.response_mut().add_event(RolesRevokedEvent {
+            merchant,
+            delegate,
+        });
+
+        Ok(())
+    }
+
+    pub fn list_delegates(&self, store: &dyn Storage, merchant: &Addr, limit: Option<u32>, start_after: Option<String>) -> Result<Vec<Delegate>> {
+        let start_after = start_after.map(Addr::unchecked);
+
+        DELEGATES
+            .prefix(merchant)
+            .range(store, start_after.as_ref().map(Bound::exclusive), None, Order::Ascending)
+            .map(|res| res.map(|(_, delegate)| delegate).map_err(|err| err.into()))
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .collect()
+    }
+}
@@ -0,0 +1,132 @@
+use cosmwasm_std::Timestamp;
+use cw_storage_plus::Map;
+use shared::msg::{contract::warehouse::GroupId, product::{Product, ProductId}, purchase::Purchase};
+use anyhow::Result;
+
+use super::{State, StateContext};
+
+const SPENDER_GROUP_UNITS: Map<(GroupId, &str), u32> = Map::new("spender-group-units");
+const SPENDER_OPEN_PURCHASES: Map<(ProductId, &str), u32> = Map::new("spender-open-purchases");
+const SPENDER_LAST_CANCELLATION: Map<(ProductId, &str), Timestamp> = Map::new("spender-last-cancellation");
+const SPENDER_OPEN_HOLDS: Map<(ProductId, &str), u32> = Map::new("spender-open-holds");
+
+impl State<'_> {
+    // checks the product's purchase rules for a new purchase, and records it against the spender's limits
+    pub fn apply_purchase_rules(&self, ctx: &mut StateContext, product: &Product, spender: &str, group_id: GroupId, quantity: u32) -> Result<()> {
+        let rules = &product.purchase_rules;
+
+        self.assert_cancellation_cooldown(ctx, product, spender)?;
+
+        let units = SPENDER_GROUP_UNITS.may_load(ctx.store, (group_id, spender))?.unwrap_or_default() + quantity;
+        if let Some(max) = rules.max_units_per_buyer {
+            if units > max {
+                anyhow::bail!("spender {} would hold {} units in group {} (max per buyer: {})", spender, units, group_id, max);
+            }
+        }
+
+        let open_purchases = SPENDER_OPEN_PURCHASES.may_load(ctx.store, (product.id, spender))?.unwrap_or_default() + 1;
+        if let Some(max) = rules.max_open_purchases {
+            if open_purchases > max {
+                anyhow::bail!("spender {} already has {} open purchases (max: {})", spender, open_purchases - 1, max);
+            }
+        }
+
+        SPENDER_GROUP_UNITS.save(ctx.store, (group_id, spender), &units)?;
+        SPENDER_OPEN_PURCHASES.save(ctx.store, (product.id, spender), &open_purchases)?;
+
+        Ok(())
+    }
+
+    // the same limits as a purchase, so holds can't be used to get around them
+    // the group isn't known until the purchase, so the unit limit is only checked against the hold itself
+    pub fn apply_hold_rules(&self, ctx: &mut StateContext, product: &Product, spender: &str, quantity: u32) -> Result<()> {
+        let rules = &product.purchase_rules;
+
+        self.assert_cancellation_cooldown(ctx, product, spender)?;
+
+        if let Some(max) = rules.max_units_per_buyer {
+            if quantity > max {
+                anyhow::bail!("spender {} would hold {} units (max per buyer: {})", spender, quantity, max);
+            }
+        }
+
+        let open_holds = SPENDER_OPEN_HOLDS.may_load(ctx.store, (product.id, spender))?.unwrap_or_default() + 1;
+        if let Some(max) = rules.max_open_purchases {
+            let open_purchases = SPENDER_OPEN_PURCHASES.may_load(ctx.store, (product.id, spender))?.unwrap_or_default();
+            if open_purchases + open_holds > max {
+                anyhow::bail!("spender {} already has {} open purchases and holds (max: {})", spender, open_purchases + open_holds - 1, max);
+            }
+        }
+
+        SPENDER_OPEN_HOLDS.save(ctx.store, (product.id, spender), &open_holds)?;
+
+        Ok(())
+    }
+
+    pub fn release_hold_rules(&self, ctx: &mut StateContext, product_id: ProductId, spender: &str) -> Result<()> {
+        match SPENDER_OPEN_HOLDS.may_load(ctx.store, (product_id, spender))?.unwrap_or_default() {
+            0 | 1 => SPENDER_OPEN_HOLDS.remove(ctx.store, (product_id, spender)),
+            n => SPENDER_OPEN_HOLDS.save(ctx.store, (product_id, spender), &(n - 1))?,
+        }
+
+        Ok(())
+    }
+
+    // releases the purchase from the spender's limits when it's cancelled, and starts the cooldown
+    pub fn release_cancelled_purchase_rules(&self, ctx: &mut StateContext, purchase: &Purchase) -> Result<()> {
+        self.release_group_units(ctx, purchase, purchase.quantity)?;
+        self.close_open_purchase(ctx, purchase)?;
+        SPENDER_LAST_CANCELLATION.save(ctx.store, (purchase.product_id, &purchase.spender), &self.env.block.time)?;
+
+        Ok(())
+    }
+
+    // the purchase stays open, only the removed units stop counting (and no cooldown, since it wasn't cancelled)
+    pub fn release_reduced_purchase_rules(&self, ctx: &mut StateContext, purchase: &Purchase, removed: u32) -> Result<()> {
+        self.release_group_units(ctx, purchase, removed)
+    }
+
+    // the purchase's units follow it into another group (e.g. when split off for shipping)
+    pub fn move_group_units(&self, ctx: &mut StateContext, purchase: &Purchase, to_group_id: GroupId) -> Result<()> {
+        self.release_group_units(ctx, purchase, purchase.quantity)?;
+        SPENDER_GROUP_UNITS.update(ctx.store, (to_group_id, purchase.spender.as_str()), |x| anyhow::Ok(x.unwrap_or_default() + purchase.quantity))?;
+
+        Ok(())
+    }
+
+    // a shipped purchase no longer counts towards the spender's open purchases
+    pub fn close_open_purchase(&self, ctx: &mut StateContext, purchase: &Purchase) -> Result<()> {
+        let key = (purchase.product_id, purchase.spender.as_str());
+        match SPENDER_OPEN_PURCHASES.may_load(ctx.store, key)?.unwrap_or_default() {
+            0 | 1 => SPENDER_OPEN_PURCHASES.remove(ctx.store, key),
+            n => SPENDER_OPEN_PURCHASES.save(ctx.store, key, &(n - 1))?,
+        }
+
+        Ok(())
+    }
+
+    fn assert_cancellation_cooldown(&self, ctx: &StateContext, product: &Product, spender: &str) -> Result<()> {
+        if let Some(cooldown) = product.purchase_rules.cancellation_cooldown_seconds {
+            if let Some(last_cancellation) = SPENDER_LAST_CANCELLATION.may_load(ctx.store, (product.id, spender))? {
+                let available_at = last_cancellation.plus_seconds(cooldown);
+                if self.env.block.time < available_at {
+                    anyhow::bail!("spender {} cancelled recently and must wait until {} to purchase again", spender, available_at);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn release_group_units(&self, ctx: &mut StateContext, purchase: &Purchase, released: u32) -> Result<()> {
+        let key = (purchase.group_id, purchase.spender.as_str());
+        let units = SPENDER_GROUP_UNITS.may_load(ctx.store, key)?.unwrap_or_default().saturating_sub(released);
+        if units == 0 {
+            SPENDER_GROUP_UNITS.remove(ctx.store, key);
+        } else {
+            SPENDER_GROUP_UNITS.save(ctx.store, key, &units)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use cosmwasm_std::{to_json_binary, Addr, Order, Storage, Uint128};
+use cw_storage_plus::Map;
+use shared::msg::{contract::{payment::{IbcExecuteMsg as PaymentIbcExecuteMsg, Payout}, warehouse::{event::{DeliveryConfirmedEvent, PayoutWithheldEvent, SettlementEvent}, GroupId, MerchantRole, PurchaseEscrow, WithheldPayout}}, purchase::{Purchase, PurchaseId}};
+use anyhow::{Context, Result};
+
+use super::{ibc::IbcChannelKind, State, StateContext};
+
+const ESCROWS: Map<PurchaseId, PurchaseEscrow> = Map::new("purchase-escrows");
+// settlements for merchants without a payout address on the payment chain, paid once they set one
+const WITHHELD_PAYOUTS: Map<(&Addr, PurchaseId), WithheldPayout> = Map::new("withheld-payouts");
+
+impl State<'_> {
+    // called when the group ships, the merchant's share stays on the payment chain until released
+    pub fn open_escrow(&self, ctx: &mut StateContext, purchase: &Purchase, amount: Uint128) -> Result<()> {
+        let dispute_window_seconds = self.get_config(ctx.store)?.dispute_window_seconds;
+
+        ESCROWS.save(ctx.store, purchase.id, &PurchaseEscrow {
+            purchase_id: purchase.id,
+            group_id: purchase.group_id,
+            amount,
+            release_at: self.env.block.time.plus_seconds(dispute_window_seconds),
+            confirmed_at: None,
+            settled_at: None,
+        })?;
+
+        Ok(())
+    }
+
+    pub fn confirm_delivery(&self, ctx: &mut StateContext, purchase_id: PurchaseId) -> Result<()> {
+        let mut escrow = ESCROWS
+            .may_load(ctx.store, purchase_id)?
+            .context(format!("purchase {} has not shipped", purchase_id))?;
+
+        if escrow.confirmed_at.is_some() {
+            anyhow::bail!("purchase {} was already confirmed", purchase_id);
+        }
+        if self.is_disputed(ctx.store, purchase_id)? {
+            anyhow::bail!("purchase {} is disputed, only the arbiter can release it", purchase_id);
+        }
+        escrow.confirmed_at = Some(self.env.block.time);
+
+        ctx.response_mut().add_event(DeliveryConfirmedEvent { purchase_id });
+
+        if escrow.settled_at.is_some() {
+            // the dispute window already expired, nothing left to release
+            ESCROWS.save(ctx.store, purchase_id, &escrow)?;
+            Ok(())
+        } else {
+            self.settle_escrows(ctx, vec![escrow])
+        }
+    }
+
+    pub fn release_group(&self, ctx: &mut StateContext, group_id: GroupId) -> Result<()> {
+        let mut escrows = Vec::new();
+        for purchase_id in self.get_group_purchase_ids(ctx.store, group_id)? {
+            let escrow = ESCROWS
+                .may_load(ctx.store, purchase_id)?
+                .context(format!("group {} has not shipped", group_id))?;
+
+            // disputed purchases are frozen until the arbiter resolves them
+            if escrow.settled_at.is_none() && !self.is_disputed(ctx.store, purchase_id)? {
+                if self.env.block.time < escrow.release_at {
+                    anyhow::bail!("dispute window for group {} is open until {}", group_id, escrow.release_at);
+                }
+                escrows.push(escrow);
+            }
+        }
+
+        if escrows.is_empty() {
+            anyhow::bail!("nothing left to release for group {}", group_id);
+        }
+
+        self.settle_escrows(ctx, escrows)
+    }
+
+    pub fn get_escrow(&self, store: &dyn Storage, id: PurchaseId) -> Result<Option<PurchaseEscrow>> {
+        ESCROWS.may_load(store, id).map_err(|err| err.into())
+    }
+
+    pub fn get_escrows(&self, store: &dyn Storage, ids: Vec<PurchaseId>) -> Result<Vec<PurchaseEscrow>> {
+        ids
+            .into_iter()
+            .map(|id| ESCROWS.load(store, id).map_err(|err| err.into()))
+            .collect()
+    }
+
+    // pays the merchant for each escrow, in one packet to the payment chain
+    pub fn settle_escrows(&self, ctx: &mut StateContext, escrows: Vec<PurchaseEscrow>) -> Result<()> {
+        let platform_fee = self.get_config(ctx.store)?.platform_fee;
+        let mut payouts = Vec::new();
+
+        for mut escrow in escrows {
+            escrow.settled_at = Some(self.env.block.time);
+
+            // e.g. fully refunded after a dispute, nothing to pay out
+            if escrow.amount.is_zero() {
+                ESCROWS.save(ctx.store, escrow.purchase_id, &escrow)?;
+                continue;
+            }
+
+            let merchant = self.get_group_owner(ctx.store, escrow.group_id)?;
+            // paid out in the same asset the purchase was paid with
+            let cw20 = self.try_get_purchase(ctx.store, escrow.purchase_id)?.and_then(|purchase| purchase.cw20);
+            let fee = platform_fee.fee(escrow.amount, self.get_merchant_tier(ctx.store, &merchant)?.as_deref());
+
+            ESCROWS.save(ctx.store, escrow.purchase_id, &escrow)?;
+
+            let payout = WithheldPayout {
+                purchase_id: escrow.purchase_id,
+                amount: escrow.amount,
+                fee,
+                cw20,
+            };
+
+            match self.get_merchant_payout_address(ctx.store, &merchant)? {
+                Some(recipient) => payouts.push(self.payout(ctx, &merchant, recipient, payout)?),
+                None => {
+                    // the buyer shouldn't be blocked by the merchant's missing setup
+                    WITHHELD_PAYOUTS.save(ctx.store, (&merchant, payout.purchase_id), &payout)?;
+
+                    ctx.response_mut().add_event(PayoutWithheldEvent {
+                        purchase_id: payout.purchase_id,
+                        merchant,
+                        amount: payout.amount,
+                    });
+                }
+            }
+        }
+
+        if !payouts.is_empty() {
+            self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&PaymentIbcExecuteMsg::Settle { payouts })?)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn claim_withheld_payouts(&self, ctx: &mut StateContext, msg_sender: Addr, merchant: Option<String>) -> Result<()> {
+        let merchant = self.resolve_merchant(ctx.store, &msg_sender, merchant, MerchantRole::Finance)?;
+        let recipient = self
+            .get_merchant_payout_address(ctx.store, &merchant)?
+            .context(format!("merchant {} has no payout address on the payment chain", merchant))?;
+
+        let withheld = self.get_withheld_payouts(ctx.store, &merchant)?;
+        if withheld.is_empty() {
+            anyhow::bail!("no withheld payouts for {}", merchant);
+        }
+
+        let mut payouts = Vec::new();
+        for payout in withheld {
+            WITHHELD_PAYOUTS.remove(ctx.store, (&merchant, payout.purchase_id));
+            payouts.push(self.payout(ctx, &merchant, recipient.clone(), payout)?);
+        }
+
+        self.send_ibc_packet(ctx, IbcChannelKind::Payment, to_json_binary(&PaymentIbcExecuteMsg::Settle { payouts })?)
+    }
+
+    pub fn get_withheld_payouts(&self, store: &dyn Storage, merchant: &Addr) -> Result<Vec<WithheldPayout>> {
+        WITHHELD_PAYOUTS
+            .prefix(merchant)
+            .range(store, None, None, Order::Ascending)
+            .map(|res| res.map(|(_, payout)| payout).map_err(|err| err.into()))
+            .collect()
+    }
+
+    fn payout(&self, ctx: &mut StateContext, merchant: &Addr, recipient: String, payout: WithheldPayout) -> Result<Payout> {
+        let route = self.get_merchant_payout_route(ctx.store, merchant)?;
+
+        ctx.response_mut().add_event(SettlementEvent {
+            purchase_id: payout.purchase_id,
+            recipient: recipient.clone(),
+            amount: payout.amount,
+            fee: payout.fee,
+        });
+
+        Ok(Payout {
+            recipient,
+            amount: payout.amount,
+            fee: payout.fee,
+            cw20: payout.cw20,
+            route,
+        })
+    }
+}
@@ -0,0 +1,106 @@
+use cosmwasm_std::{Addr, Storage};
+use cw_storage_plus::Map;
+use shared::msg::contract::warehouse::{event::{GroupCompletedEvent, GroupDeliveredEvent, GroupLockedEvent, GroupPackedEvent, GroupShippedEvent}, GroupId, GroupStatus, GroupStatusChange};
+use anyhow::Result;
+
+use super::{State, StateContext};
+
+const GROUP_STATUS: Map<GroupId, GroupStatus> = Map::new("group-status");
+const GROUP_STATUS_HISTORY: Map<GroupId, Vec<GroupStatusChange>> = Map::new("group-status-history");
+// the shipped flag groups had before the lifecycle, only read for groups that have no status yet
+const LEGACY_HAS_SHIPPED: Map<GroupId, bool> = Map::new("group-has-shipped");
+
+impl State<'_> {
+    pub fn lock_group(&self, ctx: &mut StateContext, msg_sender: Addr, group_id: GroupId) -> Result<()> {
+        self.assert_group_manager(ctx.store, group_id, &msg_sender)?;
+        self.transition_group_status(ctx, group_id, GroupStatus::Locked)
+    }
+
+    pub fn pack_group(&self, ctx: &mut StateContext, msg_sender: Addr, group_id: GroupId) -> Result<()> {
+        self.assert_group_manager(ctx.store, group_id, &msg_sender)?;
+        self.transition_group_status(ctx, group_id, GroupStatus::Packed)
+    }
+
+    pub fn deliver_group(&self, ctx: &mut StateContext, msg_sender: Addr, group_id: GroupId) -> Result<()> {
+        self.assert_group_manager(ctx.store, group_id, &msg_sender)?;
+        self.transition_group_status(ctx, group_id, GroupStatus::Delivered)
+    }
+
+    pub fn complete_group(&self, ctx: &mut StateContext, msg_sender: Addr, group_id: GroupId) -> Result<()> {
+        self.assert_group_manager(ctx.store, group_id, &msg_sender)?;
+        self.transition_group_status(ctx, group_id, GroupStatus::Completed)
+    }
+
+    pub fn init_group_status(&self, ctx: &mut StateContext, group_id: GroupId) -> Result<()> {
+        GROUP_STATUS.save(ctx.store, group_id, &GroupStatus::Pending)?;
+        GROUP_STATUS_HISTORY.save(ctx.store, group_id, &vec![GroupStatusChange {
+            status: GroupStatus::Pending,
+            timestamp: self.env.block.time,
+        }])?;
+
+        Ok(())
+    }
+
+    pub fn remove_group_status(&self, ctx: &mut StateContext, group_id: GroupId) {
+        GROUP_STATUS.remove(ctx.store, group_id);
+        GROUP_STATUS_HISTORY.remove(ctx.store, group_id);
+        LEGACY_HAS_SHIPPED.remove(ctx.store, group_id);
+    }
+
+    pub fn get_group_status(&self, store: &dyn Storage, group_id: GroupId) -> Result<GroupStatus> {
+        if let Some(status) = GROUP_STATUS.may_load(store, group_id)? {
+            return Ok(status);
+        }
+
+        // groups from before the lifecycle only know whether they shipped
+        self.get_group_owner(store, group_id)?;
+        Ok(match LEGACY_HAS_SHIPPED.may_load(store, group_id)?.unwrap_or_default() {
+            true => GroupStatus::Shipped { carrier: None, tracking_ref: None },
+            false => GroupStatus::Pending,
+        })
+    }
+
+    pub fn get_group_status_history(&self, store: &dyn Storage, group_id: GroupId) -> Result<Vec<GroupStatusChange>> {
+        Ok(GROUP_STATUS_HISTORY.may_load(store, group_id)?.unwrap_or_default())
+    }
+
+    // moves the group forward in its lifecycle, stages may be skipped but never past shipping
+    // since that's where the buyers get refunded (see `ship_group`)
+    pub fn transition_group_status(&self, ctx: &mut StateContext, group_id: GroupId, status: GroupStatus) -> Result<()> {
+        let current = self.get_group_status(ctx.store, group_id)?;
+
+        if status.stage() <= current.stage() {
+            anyhow::bail!("group {} cannot move from {} to {}", group_id, current, status);
+        }
+        if status.has_shipped() && !current.has_shipped() && !matches!(status, GroupStatus::Shipped { .. }) {
+            anyhow::bail!("group {} must ship before it can be {}", group_id, status);
+        }
+
+        if current == GroupStatus::Pending {
+            self.clear_pending_group(ctx, group_id)?;
+        }
+
+        GROUP_STATUS.save(ctx.store, group_id, &status)?;
+        GROUP_STATUS_HISTORY.update(ctx.store, group_id, |history| {
+            let mut history = history.unwrap_or_default();
+            history.push(GroupStatusChange {
+                status: status.clone(),
+                timestamp: self.env.block.time,
+            });
+            anyhow::Ok(history)
+        })?;
+
+        let timestamp = self.env.block.time;
+        match status {
+            // never a transition target, it's always the first stage
+            GroupStatus::Pending => {},
+            GroupStatus::Locked => ctx.response_mut().add_event(GroupLockedEvent { group_id, timestamp }),
+            GroupStatus::Packed => ctx.response_mut().add_event(GroupPackedEvent { group_id, timestamp }),
+            GroupStatus::Shipped { carrier, tracking_ref } => ctx.response_mut().add_event(GroupShippedEvent { group_id, carrier, tracking_ref, timestamp }),
+            GroupStatus::Delivered => ctx.response_mut().add_event(GroupDeliveredEvent { group_id, timestamp }),
+            GroupStatus::Completed => ctx.response_mut().add_event(GroupCompletedEvent { group_id, timestamp }),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use cosmwasm_std::{from_json, to_json_binary, Coin, IbcMsg, IbcTimeout, Reply, Storage, SubMsg, Uint128};
+use cw_storage_plus::Map;
+use shared::msg::contract::{payment::IbcLifecycleComplete, warehouse::{event::SlashTransferFailedEvent, InFlightSlash}};
+use anyhow::{Context, Result};
+
+use super::{merchant::BOND_DENOM, State, StateContext};
+
+pub const SLASH_REPLY_ID: u64 = 1;
+
+// relayers can be slow across hops, more generous than our own packets
+const BOND_TRANSFER_TIMEOUT_SECONDS: u64 = 60 * 10;
+
+// slash transfers waiting for their ack, keyed by (channel, sequence)
+const IN_FLIGHT_SLASHES: Map<(&str, u64), InFlightSlash> = Map::new("in-flight-slashes");
+// slashed bond that didn't make it, keyed by the recipient on the payment chain
+const SLASH_CLAIMABLE: Map<&str, Uint128> = Map::new("slash-claimable");
+
+impl State<'_> {
+    // same as payouts on the payment chain: the ibc-hooks memo gets us a sudo callback with the outcome,
+    // the reply gets us the sequence to match it to, and anything that fails is credited to the recipient
+    pub fn send_slash_transfer(&self, ctx: &mut StateContext, channel_id: String, recipient: String, amount: Uint128) -> Result<()> {
+        let msg = IbcMsg::Transfer {
+            channel_id: channel_id.clone(),
+            to_address: recipient.clone(),
+            amount: Coin::new(amount, BOND_DENOM),
+            timeout: IbcTimeout::with_timestamp(self.env.block.time.plus_seconds(BOND_TRANSFER_TIMEOUT_SECONDS)),
+            memo: Some(format!(r#"{{"ibc_callback":"{}"}}"#, self.env.contract.address)),
+        };
+
+        let slash = InFlightSlash {
+            recipient,
+            amount,
+            channel_id,
+        };
+
+        ctx.response_mut().add_raw_submessage(
+            SubMsg::reply_always(msg, SLASH_REPLY_ID).with_payload(to_json_binary(&slash)?)
+        );
+
+        Ok(())
+    }
+
+    pub fn handle_slash_reply(&self, ctx: &mut StateContext, reply: Reply) -> Result<()> {
+        let slash: InFlightSlash = from_json(&reply.payload)?;
+
+        // the transfer was reverted (e.g. a closed channel) and the funds are still here
+        let result = match reply.result.into_result() {
+            Ok(result) => result,
+            Err(_) => return self.credit_slash(ctx, slash.recipient, slash.amount),
+        };
+
+        let sequence = result
+            .events
+            .into_iter()
+            .find(|evt| evt.ty == "send_packet")
+            .and_then(|evt| evt.attributes.into_iter().find(|attr| attr.key == "packet_sequence"))
+            .and_then(|attr| attr.value.parse::<u64>().ok());
+
+        // erroring here would undo the slash, without a sequence we can't match the callback so it's credited instead
+        let sequence = match sequence {
+            Some(sequence) => sequence,
+            None => return self.credit_slash(ctx, slash.recipient, slash.amount),
+        };
+
+        IN_FLIGHT_SLASHES.save(ctx.store, (&slash.channel_id, sequence), &slash)?;
+
+        Ok(())
+    }
+
+    pub fn handle_ibc_lifecycle_complete(&self, ctx: &mut StateContext, msg: IbcLifecycleComplete) -> Result<()> {
+        let (channel, sequence, failed) = match msg {
+            IbcLifecycleComplete::IbcAck { channel, sequence, success, .. } => (channel, sequence, !success),
+            IbcLifecycleComplete::IbcTimeout { channel, sequence } => (channel, sequence, true),
+        };
+
+        let slash = match IN_FLIGHT_SLASHES.may_load(ctx.store, (&channel, sequence))? {
+            Some(slash) => slash,
+            None => anyhow::bail!("no slash transfer for {} sequence {}", channel, sequence),
+        };
+        IN_FLIGHT_SLASHES.remove(ctx.store, (&channel, sequence));
+
+        // the transfer module has already put the funds back in the contract
+        if failed {
+            self.credit_slash(ctx, slash.recipient, slash.amount)?;
+        }
+
+        Ok(())
+    }
+
+    // resent in full, anyone can do it since it only ever goes to the recipient
+    pub fn claim_slashed_bond(&self, ctx: &mut StateContext, recipient: String) -> Result<()> {
+        let amount = self.get_slash_claimable(ctx.store, &recipient)?;
+        if amount.is_zero() {
+            anyhow::bail!("no slashed bond to claim for {}", recipient);
+        }
+        let channel_id = self
+            .get_config(ctx.store)?
+            .payment_transfer_channel
+            .context("no transfer channel to the payment chain is configured")?;
+
+        SLASH_CLAIMABLE.remove(ctx.store, &recipient);
+
+        self.send_slash_transfer(ctx, channel_id, recipient, amount)
+    }
+
+    pub fn get_slash_claimable(&self, store: &dyn Storage, recipient: &str) -> Result<Uint128> {
+        Ok(SLASH_CLAIMABLE.may_load(store, recipient)?.unwrap_or_default())
+    }
+
+    fn credit_slash(&self, ctx: &mut StateContext, recipient: String, amount: Uint128) -> Result<()> {
+        SLASH_CLAIMABLE.update(ctx.store, &recipient, |balance| anyhow::Ok(balance.unwrap_or_default() + amount))?;
+
+        ctx.response_mut().add_event(SlashTransferFailedEvent { recipient, amount });
+
+        Ok(())
+    }
+}
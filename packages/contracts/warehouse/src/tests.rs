@@ -0,0 +1,12 @@
+mod helpers;
+mod split;
+mod demand;
+mod referral;
+mod overlay;
+mod batch;
+mod escrow;
+mod asset;
+mod merchant;
+mod review;
+mod backorder;
+mod hold;
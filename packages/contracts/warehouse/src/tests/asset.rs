@@ -0,0 +1,62 @@
+use cosmwasm_std::Decimal256;
+use shared::msg::contract::warehouse::{IbcExecuteMsg, NewProduct};
+use shared::msg::product::ProductId;
+
+use super::helpers::{Harness, PAYMENT_CHANNEL};
+
+const CW20: &str = "kujira1token";
+
+fn cw20_product(harness: &mut Harness) -> ProductId {
+    harness.add_custom_product(NewProduct {
+        name: "product".to_string(),
+        price: Decimal256::from_ratio(100u32, 1u32),
+        stock: 10,
+        group_capacity: None,
+        auto_ship_on_full: false,
+        purchase_rules: Default::default(),
+        variants: Vec::new(),
+        preorder_cap: None,
+        pricing: Default::default(),
+        referral_share: Decimal256::zero(),
+        cw20: Some(CW20.to_string()),
+    })
+}
+
+fn purchase_in(harness: &mut Harness, product_id: ProductId, cw20: Option<&str>) -> anyhow::Result<()> {
+    harness.receive(PAYMENT_CHANNEL, &IbcExecuteMsg::Purchase {
+        owner: "buyer-owner".to_string(),
+        spender: "buyer".to_string(),
+        product_id,
+        variant_id: None,
+        quantity: 1,
+        hold_id: None,
+        coupon: None,
+        referrer: None,
+        cw20: cw20.map(str::to_string),
+        fees: 100u128.into(),
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn purchase_in_the_listed_asset_is_accepted() {
+    let mut harness = Harness::new();
+    let product_id = cw20_product(&mut harness);
+
+    purchase_in(&mut harness, product_id, Some(CW20)).unwrap();
+}
+
+#[test]
+fn purchase_in_another_asset_is_rejected() {
+    let mut harness = Harness::new();
+    let cw20_product_id = cw20_product(&mut harness);
+    let native_product_id = harness.add_product(100, 10);
+
+    // native funds for a cw20 product
+    assert!(purchase_in(&mut harness, cw20_product_id, None).is_err());
+    // some other token for a cw20 product
+    assert!(purchase_in(&mut harness, cw20_product_id, Some("kujira1other")).is_err());
+    // a token for a native product
+    assert!(purchase_in(&mut harness, native_product_id, Some(CW20)).is_err());
+}
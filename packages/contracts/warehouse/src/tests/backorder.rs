@@ -0,0 +1,53 @@
+use cosmwasm_std::Decimal256;
+use shared::msg::{contract::warehouse::{ExecuteMsg, NewProduct, QueryMsg}, product::{Product, ProductId}, purchase::{Purchase, PurchaseId}};
+
+use super::helpers::Harness;
+
+fn preorder_product(harness: &mut Harness) -> ProductId {
+    harness.add_custom_product(NewProduct {
+        name: "product".to_string(),
+        price: Decimal256::from_ratio(100u32, 1u32),
+        stock: 0,
+        group_capacity: None,
+        auto_ship_on_full: false,
+        purchase_rules: Default::default(),
+        variants: Vec::new(),
+        preorder_cap: Some(100),
+        pricing: Default::default(),
+        referral_share: Decimal256::zero(),
+        cw20: None,
+    })
+}
+
+fn restock(harness: &mut Harness, product_id: ProductId, quantity: u32) {
+    let merchant = harness.merchant.clone();
+    harness.execute(&merchant, ExecuteMsg::Restock { product_id, variant_id: None, quantity }).unwrap();
+}
+
+fn backordered(harness: &Harness, ids: &[PurchaseId]) -> Vec<u32> {
+    harness
+        .query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: ids.to_vec() })
+        .unwrap()
+        .into_iter()
+        .map(|purchase| purchase.backordered)
+        .collect()
+}
+
+#[test]
+fn restock_fills_the_front_of_the_queue_and_stocks_the_rest() {
+    let mut harness = Harness::new();
+    let product_id = preorder_product(&mut harness);
+    let ids = (0..3)
+        .map(|i| harness.purchase(&format!("buyer-{}", i), product_id, 2, 200).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(backordered(&harness, &ids), vec![2, 2, 2]);
+
+    // only reaches as far as the stock goes
+    restock(&mut harness, product_id, 3);
+    assert_eq!(backordered(&harness, &ids), vec![0, 1, 2]);
+
+    restock(&mut harness, product_id, 10);
+    assert_eq!(backordered(&harness, &ids), vec![0, 0, 0]);
+    let product = harness.query::<Vec<Product>>(QueryMsg::GetProducts { ids: vec![product_id] }).unwrap().remove(0);
+    assert_eq!(product.stock, 7);
+}
@@ -0,0 +1,87 @@
+use cosmwasm_std::{from_json, CosmosMsg, IbcMsg, Uint128};
+use shared::{ibc::ack_result, msg::{contract::warehouse::{BatchItem, BatchItemResult, IbcExecuteMsg, QueryMsg}, product::{Product, ProductId}}};
+
+use super::helpers::{refunds, Harness, NFT_CHANNEL, PAYMENT_CHANNEL};
+
+fn item(product_id: ProductId, quantity: u32, deposit: u128) -> BatchItem {
+    BatchItem {
+        product_id,
+        variant_id: None,
+        quantity,
+        deposit: Uint128::new(deposit),
+        hold_id: None,
+        coupon: None,
+    }
+}
+
+fn stock(harness: &Harness, product_id: ProductId) -> u32 {
+    harness.query::<Vec<Product>>(QueryMsg::GetProducts { ids: vec![product_id] }).unwrap()[0].stock
+}
+
+// (per-item results from the ack, refunds, receipts minted)
+fn purchase_batch(harness: &mut Harness, items: Vec<BatchItem>, atomic: bool) -> (Vec<BatchItemResult>, Vec<(String, u128)>, usize) {
+    let resp = harness.receive(PAYMENT_CHANNEL, &IbcExecuteMsg::PurchaseBatch {
+        owner: "owner".to_string(),
+        spender: "spender".to_string(),
+        items,
+        atomic,
+        referrer: None,
+        cw20: None,
+    }).unwrap();
+
+    let results = from_json(ack_result(&resp.acknowledgement.unwrap()).unwrap()).unwrap();
+    let receipts = resp.messages
+        .iter()
+        .filter(|msg| matches!(&msg.msg, CosmosMsg::Ibc(IbcMsg::SendPacket { channel_id, .. }) if channel_id == NFT_CHANNEL))
+        .count();
+
+    (results, refunds(&resp.messages), receipts)
+}
+
+#[test]
+fn atomic_batch_refunds_everything_when_one_item_fails() {
+    let mut harness = Harness::new();
+    let plenty = harness.add_product(100, 10);
+    let scarce = harness.add_product(100, 1);
+
+    let (results, refunds, receipts) = purchase_batch(&mut harness, vec![item(plenty, 2, 200), item(scarce, 2, 200), item(plenty, 1, 100)], true);
+
+    assert!(results.iter().all(|result| result.purchase_id.is_none() && result.error.is_some()));
+    assert!(results[0].error.as_ref().unwrap().contains("batch item 1"));
+    assert_eq!(refunds, vec![("spender".to_string(), 500)]);
+    assert_eq!(receipts, 0);
+    // nothing from the accepted items sticks around
+    assert_eq!(stock(&harness, plenty), 10);
+    assert_eq!(stock(&harness, scarce), 1);
+}
+
+#[test]
+fn partial_batch_only_refunds_the_rejected_items() {
+    let mut harness = Harness::new();
+    let plenty = harness.add_product(100, 10);
+    let scarce = harness.add_product(100, 1);
+
+    let (results, refunds, receipts) = purchase_batch(&mut harness, vec![item(plenty, 2, 200), item(scarce, 2, 200), item(plenty, 1, 100)], false);
+
+    assert!(results[0].purchase_id.is_some() && results[0].error.is_none());
+    assert!(results[1].purchase_id.is_none() && results[1].error.is_some());
+    assert!(results[2].purchase_id.is_some() && results[2].error.is_none());
+    assert_eq!(refunds, vec![("spender".to_string(), 200)]);
+    assert_eq!(receipts, 2);
+    assert_eq!(stock(&harness, plenty), 7);
+    assert_eq!(stock(&harness, scarce), 1);
+}
+
+#[test]
+fn fully_accepted_batch_sends_no_refund() {
+    let mut harness = Harness::new();
+    let plenty = harness.add_product(100, 10);
+
+    for atomic in [true, false] {
+        let (results, refunds, receipts) = purchase_batch(&mut harness, vec![item(plenty, 1, 100), item(plenty, 1, 100)], atomic);
+        assert!(results.iter().all(|result| result.purchase_id.is_some()));
+        assert!(refunds.is_empty());
+        assert_eq!(receipts, 2);
+    }
+    assert_eq!(stock(&harness, plenty), 6);
+}
@@ -0,0 +1,147 @@
+use cosmwasm_std::{Addr, Decimal256};
+use shared::msg::contract::warehouse::{Demand, DemandId, DemandStatus, ExecuteMsg, IbcExecuteMsg, NewBid, QueryMsg};
+
+use super::helpers::{refunds, Harness, PAYMENT_CHANNEL};
+
+fn post_demand(harness: &mut Harness, quantity: u32, max_price: u64) -> DemandId {
+    let creator = harness.deps.api.addr_make("creator");
+    let bid_deadline = harness.env.block.time.plus_seconds(100);
+    harness.execute(&creator, ExecuteMsg::PostDemand {
+        name: "demand".to_string(),
+        quantity,
+        max_price: Decimal256::from_ratio(max_price, 1u32),
+        bid_deadline,
+        reveal_seconds: None,
+    }).unwrap();
+
+    harness.query::<Vec<Demand>>(QueryMsg::ListDemands { open_only: None, limit: None, start_after: None }).unwrap().last().unwrap().id
+}
+
+fn pledge(harness: &mut Harness, demand_id: DemandId, spender: &str, quantity: u32, fees: u128) -> anyhow::Result<()> {
+    harness.receive(PAYMENT_CHANNEL, &IbcExecuteMsg::Pledge {
+        owner: format!("{}-owner", spender),
+        spender: spender.to_string(),
+        demand_id,
+        quantity,
+        fees: fees.into(),
+    })?;
+    Ok(())
+}
+
+fn bid(harness: &mut Harness, merchant: &Addr, demand_id: DemandId, price: u64) {
+    harness.execute(merchant, ExecuteMsg::SubmitBid {
+        demand_id,
+        merchant: None,
+        bid: NewBid::Open { price: Decimal256::from_ratio(price, 1u32) },
+    }).unwrap();
+}
+
+fn settle(harness: &mut Harness, demand_id: DemandId) -> (DemandStatus, Vec<(String, u128)>) {
+    let anyone = harness.deps.api.addr_make("anyone");
+    let resp = harness.execute(&anyone, ExecuteMsg::SettleDemand { demand_id }).unwrap();
+    let demand = harness.query::<Vec<Demand>>(QueryMsg::GetDemands { ids: vec![demand_id] }).unwrap().remove(0);
+
+    (demand.status, refunds(&resp.messages))
+}
+
+#[test]
+fn tied_bids_go_to_whoever_bid_first() {
+    let mut harness = Harness::new();
+    let other = harness.deps.api.addr_make("other");
+    harness.register_merchant(&other);
+    // the one that sorts last bids first, so storage order alone can't pick the winner
+    let (early, late) = match other > harness.merchant {
+        true => (other, harness.merchant.clone()),
+        false => (harness.merchant.clone(), other),
+    };
+
+    let demand_id = post_demand(&mut harness, 10, 10);
+    pledge(&mut harness, demand_id, "buyer", 2, 20).unwrap();
+    bid(&mut harness, &early, demand_id, 8);
+    harness.advance(10);
+    bid(&mut harness, &late, demand_id, 8);
+    harness.advance(100);
+
+    match settle(&mut harness, demand_id).0 {
+        DemandStatus::Awarded { merchant, price, .. } => {
+            assert_eq!(merchant, early);
+            assert_eq!(price, Decimal256::from_ratio(8u32, 1u32));
+        },
+        status => panic!("demand should be awarded, got {:?}", status),
+    }
+}
+
+#[test]
+fn lowest_bid_wins_regardless_of_order() {
+    let mut harness = Harness::new();
+    let cheap = harness.deps.api.addr_make("cheap");
+    let merchant = harness.merchant.clone();
+    harness.register_merchant(&cheap);
+
+    let demand_id = post_demand(&mut harness, 10, 10);
+    pledge(&mut harness, demand_id, "buyer", 1, 10).unwrap();
+    bid(&mut harness, &merchant, demand_id, 9);
+    harness.advance(10);
+    bid(&mut harness, &cheap, demand_id, 7);
+    harness.advance(100);
+
+    assert!(matches!(settle(&mut harness, demand_id).0, DemandStatus::Awarded { merchant, .. } if merchant == cheap));
+}
+
+#[test]
+fn award_refunds_what_the_winning_bid_does_not_need() {
+    let mut harness = Harness::new();
+    let merchant = harness.merchant.clone();
+
+    let demand_id = post_demand(&mut harness, 10, 10);
+    pledge(&mut harness, demand_id, "buyer-0", 2, 20).unwrap();
+    // paying over the max price is fine, the excess comes back too
+    pledge(&mut harness, demand_id, "buyer-1", 3, 35).unwrap();
+    bid(&mut harness, &merchant, demand_id, 6);
+    harness.advance(100);
+
+    let (status, refunds) = settle(&mut harness, demand_id);
+    assert!(matches!(status, DemandStatus::Awarded { .. }));
+    assert_eq!(refunds, vec![("buyer-0".to_string(), 8), ("buyer-1".to_string(), 17)]);
+}
+
+#[test]
+fn unfilled_demand_refunds_every_pledge_in_full() {
+    let mut harness = Harness::new();
+
+    let demand_id = post_demand(&mut harness, 10, 10);
+    pledge(&mut harness, demand_id, "buyer-0", 2, 20).unwrap();
+    pledge(&mut harness, demand_id, "buyer-1", 1, 10).unwrap();
+    harness.advance(100);
+
+    let (status, refunds) = settle(&mut harness, demand_id);
+    assert_eq!(status, DemandStatus::Unfilled);
+    assert_eq!(refunds, vec![("buyer-0".to_string(), 20), ("buyer-1".to_string(), 10)]);
+}
+
+#[test]
+fn late_settlement_refunds_instead_of_awarding() {
+    let mut harness = Harness::new();
+    let merchant = harness.merchant.clone();
+
+    let demand_id = post_demand(&mut harness, 10, 10);
+    pledge(&mut harness, demand_id, "buyer", 2, 20).unwrap();
+    bid(&mut harness, &merchant, demand_id, 5);
+    harness.advance(100 + 60 * 60 * 24 * 7);
+
+    let (status, refunds) = settle(&mut harness, demand_id);
+    assert_eq!(status, DemandStatus::Unfilled);
+    assert_eq!(refunds, vec![("buyer".to_string(), 20)]);
+}
+
+#[test]
+fn pledges_are_capped_per_demand() {
+    let mut harness = Harness::new();
+
+    let demand_id = post_demand(&mut harness, 1000, 1);
+    for i in 0..100 {
+        pledge(&mut harness, demand_id, &format!("buyer-{}", i), 1, 1).unwrap();
+    }
+    let err = pledge(&mut harness, demand_id, "buyer-100", 1, 1).unwrap_err();
+    assert!(err.to_string().contains("maximum"), "{}", err);
+}
@@ -0,0 +1,277 @@
+use cosmwasm_std::{Coin, CosmosMsg, Decimal256, Event, IbcMsg, Reply, SubMsg, SubMsgResponse, SubMsgResult, Uint128};
+use shared::msg::{contract::{payment::{IbcLifecycleComplete, SudoMsg}, warehouse::{Dispute, DisputeResolution, ExecuteMsg, GroupId, IbcExecuteMsg, MerchantProfile, NewProduct, PayoutAddress, PurchaseEscrow, QueryMsg, WithheldPayout}}, product::PricingMode, purchase::{Purchase, PurchaseId}};
+
+use crate::{entry, state::merchant::BOND_DENOM};
+
+use super::helpers::{payouts, refunds, Harness, NFT_CHANNEL, PAYMENT_CHAIN_ID, PAYMENT_CHANNEL};
+
+const DISPUTE_WINDOW_SECONDS: u64 = 60 * 60 * 24 * 7;
+
+// two buyers of a fixed price product in a shipped group, so each escrow holds exactly what they paid
+fn shipped_group(harness: &mut Harness) -> (GroupId, Vec<PurchaseId>) {
+    let product_id = harness.add_custom_product(NewProduct {
+        name: "product".to_string(),
+        price: Decimal256::from_ratio(100u32, 1u32),
+        stock: 10,
+        group_capacity: None,
+        auto_ship_on_full: false,
+        purchase_rules: Default::default(),
+        variants: Vec::new(),
+        preorder_cap: None,
+        pricing: PricingMode::Fixed,
+        referral_share: Decimal256::zero(),
+        cw20: None,
+    });
+    let ids = vec![
+        harness.purchase("buyer-0", product_id, 1, 100).unwrap(),
+        harness.purchase("buyer-1", product_id, 2, 200).unwrap(),
+    ];
+    let group_id = harness.query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: vec![ids[0]] }).unwrap()[0].group_id;
+
+    let merchant = harness.merchant.clone();
+    harness.execute(&merchant, ExecuteMsg::ShipGroup { group_id, carrier: None, tracking_ref: None, purchase_ids: None }).unwrap();
+
+    (group_id, ids)
+}
+
+fn escrow(harness: &Harness, purchase_id: PurchaseId) -> PurchaseEscrow {
+    harness.query::<Vec<PurchaseEscrow>>(QueryMsg::GetEscrows { ids: vec![purchase_id] }).unwrap().remove(0)
+}
+
+fn release_group(harness: &mut Harness, group_id: GroupId) -> anyhow::Result<cosmwasm_std::Response> {
+    let anyone = harness.deps.api.addr_make("anyone");
+    harness.execute(&anyone, ExecuteMsg::ReleaseGroup { group_id })
+}
+
+#[test]
+fn confirming_delivery_releases_the_escrow_right_away() {
+    let mut harness = Harness::new();
+    let (_, ids) = shipped_group(&mut harness);
+    let payout_address = format!("{}-payout", harness.merchant);
+
+    let resp = harness.receive(NFT_CHANNEL, &IbcExecuteMsg::ConfirmDelivery { id: ids[0] }).unwrap();
+
+    assert_eq!(payouts(&resp.messages), vec![(payout_address, 100)]);
+    let escrow = escrow(&harness, ids[0]);
+    assert!(escrow.confirmed_at.is_some() && escrow.settled_at.is_some());
+
+    // only the receipt holder (on the nft chain) can confirm, and only once
+    assert!(harness.receive(NFT_CHANNEL, &IbcExecuteMsg::ConfirmDelivery { id: ids[0] }).is_err());
+    assert!(harness.receive(PAYMENT_CHANNEL, &IbcExecuteMsg::ConfirmDelivery { id: ids[1] }).is_err());
+}
+
+#[test]
+fn group_releases_once_the_dispute_window_expires() {
+    let mut harness = Harness::new();
+    let (group_id, ids) = shipped_group(&mut harness);
+    let payout_address = format!("{}-payout", harness.merchant);
+    harness.receive(NFT_CHANNEL, &IbcExecuteMsg::ConfirmDelivery { id: ids[0] }).unwrap();
+
+    assert!(release_group(&mut harness, group_id).is_err());
+
+    harness.advance(DISPUTE_WINDOW_SECONDS);
+    let resp = release_group(&mut harness, group_id).unwrap();
+
+    // the confirmed purchase was already paid out
+    assert_eq!(payouts(&resp.messages), vec![(payout_address, 200)]);
+    assert!(release_group(&mut harness, group_id).is_err());
+}
+
+#[test]
+fn disputed_purchase_is_frozen_until_resolved_with_a_refund() {
+    let mut harness = Harness::new();
+    let (group_id, ids) = shipped_group(&mut harness);
+    let payout_address = format!("{}-payout", harness.merchant);
+
+    harness.receive(NFT_CHANNEL, &IbcExecuteMsg::OpenDispute { id: ids[1], evidence: "never arrived".to_string() }).unwrap();
+    assert!(harness.receive(NFT_CHANNEL, &IbcExecuteMsg::ConfirmDelivery { id: ids[1] }).is_err());
+
+    harness.advance(DISPUTE_WINDOW_SECONDS);
+    let resp = release_group(&mut harness, group_id).unwrap();
+    assert_eq!(payouts(&resp.messages), vec![(payout_address.clone(), 100)]);
+    assert!(escrow(&harness, ids[1]).settled_at.is_none());
+
+    // only the arbiter (the admin by default) resolves
+    let merchant = harness.merchant.clone();
+    let resolve = ExecuteMsg::ResolveDispute { purchase_id: ids[1], resolution: DisputeResolution::PartialRefund { amount: 150u128.into() } };
+    assert!(harness.execute(&merchant, resolve.clone()).is_err());
+
+    let admin = harness.admin.clone();
+    let resp = harness.execute(&admin, resolve.clone()).unwrap();
+    assert_eq!(refunds(&resp.messages), vec![("buyer-1".to_string(), 150)]);
+    assert_eq!(payouts(&resp.messages), vec![(payout_address, 50)]);
+    assert!(escrow(&harness, ids[1]).settled_at.is_some());
+
+    assert!(harness.execute(&admin, resolve).is_err());
+}
+
+#[test]
+fn full_refund_pays_the_merchant_nothing() {
+    let mut harness = Harness::new();
+    let (_, ids) = shipped_group(&mut harness);
+
+    harness.receive(NFT_CHANNEL, &IbcExecuteMsg::OpenDispute { id: ids[0], evidence: "broken".to_string() }).unwrap();
+
+    let admin = harness.admin.clone();
+    let resp = harness.execute(&admin, ExecuteMsg::ResolveDispute { purchase_id: ids[0], resolution: DisputeResolution::FullRefund }).unwrap();
+    assert_eq!(refunds(&resp.messages), vec![("buyer-0".to_string(), 100)]);
+    assert!(payouts(&resp.messages).is_empty());
+}
+
+#[test]
+fn payouts_are_withheld_until_the_merchant_has_a_payout_address() {
+    let mut harness = Harness::new();
+    let (group_id, ids) = shipped_group(&mut harness);
+    let merchant = harness.merchant.clone();
+    let profile = |payout_addresses| MerchantProfile {
+        display_name: "merchant".to_string(),
+        contact: None,
+        logo_uri: None,
+        payout_addresses,
+        coupon_pubkey: None,
+        payout_route: None,
+    };
+    harness.execute(&merchant, ExecuteMsg::UpdateMerchantProfile { merchant: None, profile: profile(Vec::new()) }).unwrap();
+
+    // the buyer isn't blocked by the merchant's missing setup
+    let resp = harness.receive(NFT_CHANNEL, &IbcExecuteMsg::ConfirmDelivery { id: ids[0] }).unwrap();
+    assert!(payouts(&resp.messages).is_empty());
+    harness.advance(DISPUTE_WINDOW_SECONDS);
+    release_group(&mut harness, group_id).unwrap();
+
+    let withheld = harness.query::<Vec<WithheldPayout>>(QueryMsg::GetWithheldPayouts { merchant: merchant.to_string() }).unwrap();
+    assert_eq!(withheld.iter().map(|payout| (payout.purchase_id, payout.amount.u128())).collect::<Vec<_>>(), vec![(ids[0], 100), (ids[1], 200)]);
+    assert!(harness.execute(&merchant, ExecuteMsg::ClaimWithheldPayouts { merchant: None }).is_err());
+
+    harness.execute(&merchant, ExecuteMsg::UpdateMerchantProfile {
+        merchant: None,
+        profile: profile(vec![PayoutAddress { chain_id: PAYMENT_CHAIN_ID.to_string(), address: "new-payout".to_string() }]),
+    }).unwrap();
+    let resp = harness.execute(&merchant, ExecuteMsg::ClaimWithheldPayouts { merchant: None }).unwrap();
+
+    assert_eq!(payouts(&resp.messages), vec![("new-payout".to_string(), 100), ("new-payout".to_string(), 200)]);
+    assert!(harness.query::<Vec<WithheldPayout>>(QueryMsg::GetWithheldPayouts { merchant: merchant.to_string() }).unwrap().is_empty());
+}
+
+const TRANSFER_CHANNEL: &str = "channel-7";
+
+// a bonded merchant with a resolved dispute on the first purchase, and a transfer channel to slash over
+fn resolved_dispute(harness: &mut Harness) -> Vec<PurchaseId> {
+    let (_, ids) = shipped_group(harness);
+    let merchant = harness.merchant.clone();
+    let admin = harness.admin.clone();
+
+    harness.execute_with_funds(&merchant, &[Coin::new(1000u128, BOND_DENOM)], ExecuteMsg::DepositBond {}).unwrap();
+    harness.execute(&admin, ExecuteMsg::UpdateConfig {
+        dispute_window_seconds: None,
+        arbiter: None,
+        payment_chain_id: None,
+        hold_seconds: None,
+        platform_fee: None,
+        payment_transfer_channel: Some(TRANSFER_CHANNEL.to_string()),
+        hold_deposit: None,
+        hold_relayers: None,
+    }).unwrap();
+    harness.receive(NFT_CHANNEL, &IbcExecuteMsg::OpenDispute { id: ids[0], evidence: "broken".to_string() }).unwrap();
+    harness.execute(&admin, ExecuteMsg::ResolveDispute { purchase_id: ids[0], resolution: DisputeResolution::FullRefund }).unwrap();
+
+    ids
+}
+
+fn slash(harness: &mut Harness, purchase_id: PurchaseId, amount: u128) -> anyhow::Result<cosmwasm_std::Response> {
+    let (admin, merchant) = (harness.admin.clone(), harness.merchant.clone());
+    harness.execute(&admin, ExecuteMsg::SlashBond { merchant: merchant.to_string(), amount: amount.into(), purchase_id })
+}
+
+fn withdraw_bond(harness: &mut Harness, amount: u128) -> anyhow::Result<cosmwasm_std::Response> {
+    let merchant = harness.merchant.clone();
+    harness.execute(&merchant, ExecuteMsg::WithdrawBond { merchant: None, amount: amount.into() })
+}
+
+fn slash_transfers(messages: &[SubMsg]) -> Vec<(String, u128)> {
+    messages
+        .iter()
+        .filter_map(|msg| match &msg.msg {
+            CosmosMsg::Ibc(IbcMsg::Transfer { to_address, amount, .. }) => Some((to_address.clone(), amount.amount.u128())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn slash_claimable(harness: &Harness, recipient: &str) -> u128 {
+    harness.query::<Uint128>(QueryMsg::GetSlashClaimable { recipient: recipient.to_string() }).unwrap().u128()
+}
+
+#[test]
+fn bond_stays_locked_until_the_dispute_is_slashed() {
+    let mut harness = Harness::new();
+    let ids = resolved_dispute(&mut harness);
+
+    // resolving alone doesn't free the bond
+    assert!(withdraw_bond(&mut harness, 1000).is_err());
+
+    // capped at the disputed 100, and only once
+    assert!(slash(&mut harness, ids[0], 101).is_err());
+    let resp = slash(&mut harness, ids[0], 60).unwrap();
+    assert_eq!(slash_transfers(&resp.messages), vec![("buyer-0".to_string(), 60)]);
+    assert!(slash(&mut harness, ids[0], 40).is_err());
+
+    let dispute = harness.query::<Vec<Dispute>>(QueryMsg::GetDisputes { ids: vec![ids[0]] }).unwrap().remove(0);
+    assert_eq!(dispute.slashed, Some(60u128.into()));
+    assert!(dispute.closed_at.is_some());
+
+    assert!(withdraw_bond(&mut harness, 941).is_err());
+    withdraw_bond(&mut harness, 940).unwrap();
+}
+
+#[test]
+fn closing_a_dispute_without_slashing_frees_the_bond() {
+    let mut harness = Harness::new();
+    let ids = resolved_dispute(&mut harness);
+    let (admin, merchant) = (harness.admin.clone(), harness.merchant.clone());
+
+    assert!(harness.execute(&merchant, ExecuteMsg::CloseDispute { purchase_id: ids[0] }).is_err());
+    harness.execute(&admin, ExecuteMsg::CloseDispute { purchase_id: ids[0] }).unwrap();
+
+    // closed disputes can't be slashed after the fact
+    assert!(slash(&mut harness, ids[0], 10).is_err());
+    withdraw_bond(&mut harness, 1000).unwrap();
+}
+
+#[test]
+fn failed_slash_transfers_are_credited_to_the_buyer() {
+    let mut harness = Harness::new();
+    let ids = resolved_dispute(&mut harness);
+    let resp = slash(&mut harness, ids[0], 60).unwrap();
+
+    // the transfer couldn't even be sent
+    entry::reply(harness.deps.as_mut(), harness.env.clone(), Reply {
+        id: resp.messages[0].id,
+        payload: resp.messages[0].payload.clone(),
+        gas_used: 0,
+        result: SubMsgResult::Err("channel is closed".to_string()),
+    }).unwrap();
+    assert_eq!(slash_claimable(&harness, "buyer-0"), 60);
+
+    // anyone can resend it, and this time it times out on the way
+    let anyone = harness.deps.api.addr_make("anyone");
+    let resp = harness.execute(&anyone, ExecuteMsg::ClaimSlashedBond { recipient: "buyer-0".to_string() }).unwrap();
+    assert_eq!(slash_transfers(&resp.messages), vec![("buyer-0".to_string(), 60)]);
+    assert_eq!(slash_claimable(&harness, "buyer-0"), 0);
+
+    entry::reply(harness.deps.as_mut(), harness.env.clone(), Reply {
+        id: resp.messages[0].id,
+        payload: resp.messages[0].payload.clone(),
+        gas_used: 0,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![Event::new("send_packet").add_attribute("packet_sequence", "3")],
+            data: None,
+            msg_responses: Vec::new(),
+        }),
+    }).unwrap();
+    entry::sudo(harness.deps.as_mut(), harness.env.clone(), SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+        channel: TRANSFER_CHANNEL.to_string(),
+        sequence: 3,
+    })).unwrap();
+    assert_eq!(slash_claimable(&harness, "buyer-0"), 60);
+}
@@ -0,0 +1,169 @@
+use cosmwasm_std::{
+    from_json, testing::{mock_dependencies, mock_env, mock_ibc_channel_connect_ack, mock_ibc_packet_recv, mock_info, MockApi, MockQuerier, MockStorage}, Addr, Coin, CosmosMsg, Decimal256, Env, IbcMsg, IbcOrder, IbcReceiveResponse, OwnedDeps, Response, SubMsg
+};
+use serde::de::DeserializeOwned;
+use shared::msg::{contract::{payment::IbcExecuteMsg as PaymentIbcExecuteMsg, warehouse::{ExecuteMsg, IbcExecuteMsg, InstantiateMsg, MerchantProfile, NewProduct, PayoutAddress, QueryMsg}}, product::ProductId, purchase::PurchaseId};
+use anyhow::Result;
+
+use crate::entry;
+
+pub const PAYMENT_CHAIN_ID: &str = "kaiyo-1";
+pub const PAYMENT_CHANNEL: &str = "channel-payment";
+pub const NFT_CHANNEL: &str = "channel-nft";
+
+// drives the contract through its entry points, with the payment and nft channels already connected
+pub struct Harness {
+    pub deps: OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    pub env: Env,
+    pub admin: Addr,
+    pub merchant: Addr,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        let merchant = deps.api.addr_make("merchant");
+
+        entry::instantiate(deps.as_mut(), env.clone(), mock_info(admin.as_str(), &[]), InstantiateMsg {
+            payment_chain_id: PAYMENT_CHAIN_ID.to_string(),
+        }).unwrap();
+
+        for (channel_id, version) in [(PAYMENT_CHANNEL, "warehouse-payment-001"), (NFT_CHANNEL, "warehouse-nft-001")] {
+            entry::ibc_channel_connect(deps.as_mut(), env.clone(), mock_ibc_channel_connect_ack(channel_id, IbcOrder::Unordered, version)).unwrap();
+        }
+
+        let mut harness = Self { deps, env, admin, merchant: merchant.clone() };
+        harness.register_merchant(&merchant);
+
+        harness
+    }
+
+    pub fn register_merchant(&mut self, merchant: &Addr) {
+        self.execute(merchant, ExecuteMsg::RegisterMerchant {
+            profile: MerchantProfile {
+                display_name: merchant.to_string(),
+                contact: None,
+                logo_uri: None,
+                payout_addresses: vec![PayoutAddress {
+                    chain_id: PAYMENT_CHAIN_ID.to_string(),
+                    address: format!("{}-payout", merchant),
+                }],
+                coupon_pubkey: None,
+                payout_route: None,
+            },
+        }).unwrap();
+    }
+
+    pub fn execute(&mut self, sender: &Addr, msg: ExecuteMsg) -> Result<Response> {
+        self.execute_with_funds(sender, &[], msg)
+    }
+
+    pub fn execute_with_funds(&mut self, sender: &Addr, funds: &[Coin], msg: ExecuteMsg) -> Result<Response> {
+        entry::execute(self.deps.as_mut(), self.env.clone(), mock_info(sender.as_str(), funds), msg)
+    }
+
+    pub fn receive(&mut self, channel_id: &str, msg: &IbcExecuteMsg) -> Result<IbcReceiveResponse> {
+        entry::ibc_packet_receive(self.deps.as_mut(), self.env.clone(), mock_ibc_packet_recv(channel_id, msg)?)
+    }
+
+    pub fn query<T: DeserializeOwned>(&self, msg: QueryMsg) -> Result<T> {
+        Ok(from_json(entry::query(self.deps.as_ref(), self.env.clone(), msg)?)?)
+    }
+
+    pub fn advance(&mut self, seconds: u64) {
+        self.env.block.time = self.env.block.time.plus_seconds(seconds);
+        self.env.block.height += seconds / 5;
+    }
+
+    pub fn add_product(&mut self, price: u64, stock: u32) -> ProductId {
+        self.add_custom_product(NewProduct {
+            name: "product".to_string(),
+            price: Decimal256::from_ratio(price, 1u32),
+            stock,
+            group_capacity: None,
+            auto_ship_on_full: false,
+            purchase_rules: Default::default(),
+            variants: Vec::new(),
+            preorder_cap: None,
+            pricing: Default::default(),
+            referral_share: Decimal256::zero(),
+            cw20: None,
+        })
+    }
+
+    pub fn add_custom_product(&mut self, product: NewProduct) -> ProductId {
+        let merchant = self.merchant.clone();
+        let resp = self.execute(&merchant, ExecuteMsg::AddProduct { merchant: None, product }).unwrap();
+
+        resp.events
+            .iter()
+            .find(|evt| evt.ty == "add-product")
+            .and_then(|evt| evt.attributes.iter().find(|attr| attr.key == "id"))
+            .map(|attr| attr.value.parse().unwrap())
+            .expect("product id in add-product event")
+    }
+
+    // a plain purchase from the payment channel
+    pub fn purchase(&mut self, spender: &str, product_id: ProductId, quantity: u32, fees: u128) -> Result<PurchaseId> {
+        self.referred_purchase(spender, product_id, quantity, fees, None)
+    }
+
+    pub fn referred_purchase(&mut self, spender: &str, product_id: ProductId, quantity: u32, fees: u128, referrer: Option<&str>) -> Result<PurchaseId> {
+        let resp = self.receive(PAYMENT_CHANNEL, &IbcExecuteMsg::Purchase {
+            owner: format!("{}-owner", spender),
+            spender: spender.to_string(),
+            product_id,
+            variant_id: None,
+            quantity,
+            hold_id: None,
+            coupon: None,
+            referrer: referrer.map(str::to_string),
+            cw20: None,
+            fees: fees.into(),
+        })?;
+
+        Ok(resp.events
+            .iter()
+            .find(|evt| evt.ty == "purchase")
+            .and_then(|evt| evt.attributes.iter().find(|attr| attr.key == "id"))
+            .map(|attr| attr.value.parse().unwrap())
+            .expect("purchase id in purchase event"))
+    }
+}
+
+// every packet the contract sent to the payment chain
+pub fn payment_packets(messages: &[SubMsg]) -> Vec<PaymentIbcExecuteMsg> {
+    messages
+        .iter()
+        .filter_map(|msg| match &msg.msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket { channel_id, data, .. }) if channel_id == PAYMENT_CHANNEL => Some(from_json(data).unwrap()),
+            _ => None,
+        })
+        .collect()
+}
+
+// (recipient, amount) of every refund sent to the payment chain
+pub fn refunds(messages: &[SubMsg]) -> Vec<(String, u128)> {
+    payment_packets(messages)
+        .into_iter()
+        .flat_map(|packet| match packet {
+            PaymentIbcExecuteMsg::Refund { refunds } => refunds,
+            _ => Vec::new(),
+        })
+        .map(|refund| (refund.recipient, refund.amount.u128()))
+        .collect()
+}
+
+// (recipient, amount) of every merchant payout sent to the payment chain
+pub fn payouts(messages: &[SubMsg]) -> Vec<(String, u128)> {
+    payment_packets(messages)
+        .into_iter()
+        .flat_map(|packet| match packet {
+            PaymentIbcExecuteMsg::Settle { payouts } => payouts,
+            _ => Vec::new(),
+        })
+        .map(|payout| (payout.recipient, payout.amount.u128()))
+        .collect()
+}
@@ -0,0 +1,81 @@
+use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, Response};
+use shared::msg::{contract::warehouse::{ExecuteMsg, HoldId}, product::ProductId};
+
+use crate::state::{config::DEFAULT_HOLD_SECONDS, merchant::BOND_DENOM};
+
+use super::helpers::Harness;
+
+const DEPOSIT: u128 = 10;
+
+fn set_hold_config(harness: &mut Harness, hold_relayers: Vec<String>) {
+    let admin = harness.admin.clone();
+    harness.execute(&admin, ExecuteMsg::UpdateConfig {
+        dispute_window_seconds: None,
+        arbiter: None,
+        payment_chain_id: None,
+        hold_seconds: None,
+        platform_fee: None,
+        payment_transfer_channel: None,
+        hold_deposit: Some(DEPOSIT.into()),
+        hold_relayers: Some(hold_relayers),
+    }).unwrap();
+}
+
+fn place_hold(harness: &mut Harness, holder: &Addr, product_id: ProductId, spender: &str) -> anyhow::Result<HoldId> {
+    let resp = harness.execute_with_funds(holder, &[Coin::new(DEPOSIT, BOND_DENOM)], ExecuteMsg::PlaceHold {
+        product_id,
+        variant_id: None,
+        quantity: 1,
+        spender: spender.to_string(),
+    })?;
+
+    Ok(resp.events
+        .iter()
+        .find(|evt| evt.ty == "hold-placed")
+        .and_then(|evt| evt.attributes.iter().find(|attr| attr.key == "id"))
+        .map(|attr| attr.value.parse().unwrap())
+        .expect("hold id in hold-placed event"))
+}
+
+fn deposit_returns(resp: &Response) -> Vec<(String, u128)> {
+    resp.messages
+        .iter()
+        .filter_map(|msg| match &msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => Some((to_address.clone(), amount[0].amount.u128())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn only_the_spender_or_a_relayer_can_place_a_hold() {
+    let mut harness = Harness::new();
+    let relayer = harness.deps.api.addr_make("relayer");
+    let buyer = harness.deps.api.addr_make("buyer");
+    let stranger = harness.deps.api.addr_make("stranger");
+    set_hold_config(&mut harness, vec![relayer.to_string()]);
+    let product_id = harness.add_product(100, 10);
+
+    assert!(place_hold(&mut harness, &stranger, product_id, buyer.as_str()).is_err());
+    place_hold(&mut harness, &buyer, product_id, buyer.as_str()).unwrap();
+    place_hold(&mut harness, &relayer, product_id, "kujira1buyer").unwrap();
+}
+
+#[test]
+fn deposit_is_refunded_in_time_and_forfeited_to_the_merchant_on_expiry() {
+    let mut harness = Harness::new();
+    let buyer = harness.deps.api.addr_make("buyer");
+    set_hold_config(&mut harness, Vec::new());
+    let product_id = harness.add_product(100, 10);
+
+    let released = place_hold(&mut harness, &buyer, product_id, buyer.as_str()).unwrap();
+    let resp = harness.execute(&buyer, ExecuteMsg::ReleaseHold { id: released }).unwrap();
+    assert_eq!(deposit_returns(&resp), vec![(buyer.to_string(), DEPOSIT)]);
+
+    place_hold(&mut harness, &buyer, product_id, buyer.as_str()).unwrap();
+    harness.advance(DEFAULT_HOLD_SECONDS);
+
+    // sweeping it yourself doesn't get it back either
+    let resp = harness.execute(&buyer, ExecuteMsg::SweepHolds { limit: None }).unwrap();
+    assert_eq!(deposit_returns(&resp), vec![(harness.merchant.to_string(), DEPOSIT)]);
+}
@@ -0,0 +1,29 @@
+use shared::msg::contract::warehouse::{ExecuteMsg, MerchantProfile, PayoutRoute};
+
+use super::helpers::{Harness, PAYMENT_CHAIN_ID};
+
+fn routed_profile(channel_id: &str) -> MerchantProfile {
+    MerchantProfile {
+        display_name: "merchant".to_string(),
+        contact: None,
+        logo_uri: None,
+        payout_addresses: Vec::new(),
+        coupon_pubkey: None,
+        payout_route: Some(PayoutRoute {
+            chain_id: PAYMENT_CHAIN_ID.to_string(),
+            channel_id: channel_id.to_string(),
+            receiver: "merchant-remote".to_string(),
+        }),
+    }
+}
+
+#[test]
+fn payout_route_needs_a_real_channel_id() {
+    let mut harness = Harness::new();
+    let merchant = harness.merchant.clone();
+
+    for channel_id in ["", "channel-", "channel-x", "transfer", "channel-1/transfer"] {
+        assert!(harness.execute(&merchant, ExecuteMsg::UpdateMerchantProfile { merchant: None, profile: routed_profile(channel_id) }).is_err(), "{}", channel_id);
+    }
+    harness.execute(&merchant, ExecuteMsg::UpdateMerchantProfile { merchant: None, profile: routed_profile("channel-42") }).unwrap();
+}
@@ -0,0 +1,54 @@
+use cosmwasm_std::{testing::MockStorage, Order, Record, Storage};
+
+use crate::state::overlay::StorageOverlay;
+
+fn record(key: &str, value: &str) -> Record {
+    (key.as_bytes().to_vec(), value.as_bytes().to_vec())
+}
+
+fn base() -> MockStorage {
+    let mut base = MockStorage::new();
+    for key in ["a", "c", "e", "g"] {
+        base.set(key.as_bytes(), key.as_bytes());
+    }
+    base
+}
+
+#[test]
+fn range_merges_writes_over_the_base() {
+    let base = base();
+    let mut overlay = StorageOverlay::new(&base);
+    overlay.set(b"b", b"new");
+    overlay.set(b"c", b"changed");
+    overlay.remove(b"e");
+    overlay.remove(b"f");
+    overlay.set(b"h", b"new");
+
+    let ascending = overlay.range(None, None, Order::Ascending).collect::<Vec<_>>();
+    assert_eq!(ascending, vec![
+        record("a", "a"),
+        record("b", "new"),
+        record("c", "changed"),
+        record("g", "g"),
+        record("h", "new"),
+    ]);
+
+    let mut descending = overlay.range(None, None, Order::Descending).collect::<Vec<_>>();
+    descending.reverse();
+    assert_eq!(descending, ascending);
+}
+
+#[test]
+fn range_respects_bounds_on_both_sides() {
+    let base = base();
+    let mut overlay = StorageOverlay::new(&base);
+    overlay.set(b"a", b"outside");
+    overlay.set(b"d", b"new");
+    overlay.set(b"g", b"outside");
+
+    let records = overlay.range(Some(b"b"), Some(b"g"), Order::Descending).collect::<Vec<_>>();
+    assert_eq!(records, vec![record("e", "e"), record("d", "new"), record("c", "c")]);
+
+    assert_eq!(overlay.range(Some(b"g"), Some(b"b"), Order::Ascending).count(), 0);
+}
+
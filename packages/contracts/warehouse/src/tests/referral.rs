@@ -0,0 +1,52 @@
+use cosmwasm_std::Decimal256;
+use shared::msg::{contract::warehouse::{ExecuteMsg, NewProduct, QueryMsg, ReferralStats}, purchase::Purchase};
+
+use super::helpers::Harness;
+
+fn leaderboard(harness: &Harness, product_id: u32, limit: Option<u32>, start_after: Option<&str>) -> Vec<(String, u128)> {
+    harness.query::<Vec<ReferralStats>>(QueryMsg::ReferralLeaderboard {
+        product_id,
+        limit,
+        start_after: start_after.map(str::to_string),
+    }).unwrap()
+        .into_iter()
+        .map(|stats| (stats.referrer, stats.earned.u128()))
+        .collect()
+}
+
+#[test]
+fn leaderboard_is_ranked_and_paginated() {
+    let mut harness = Harness::new();
+    let product_id = harness.add_custom_product(NewProduct {
+        name: "product".to_string(),
+        price: Decimal256::from_ratio(100u32, 1u32),
+        stock: 100,
+        group_capacity: None,
+        auto_ship_on_full: false,
+        purchase_rules: Default::default(),
+        variants: Vec::new(),
+        preorder_cap: None,
+        pricing: Default::default(),
+        referral_share: Decimal256::percent(50),
+        cw20: None,
+    });
+
+    // every unit gets the same discount refund, so earnings follow the units referred
+    let first = harness.referred_purchase("buyer-0", product_id, 1, 100, Some("small")).unwrap();
+    harness.referred_purchase("buyer-1", product_id, 3, 300, Some("big")).unwrap();
+    harness.referred_purchase("buyer-2", product_id, 2, 200, Some("medium")).unwrap();
+    harness.referred_purchase("buyer-3", product_id, 2, 200, Some("big")).unwrap();
+    harness.purchase("buyer-4", product_id, 1, 100).unwrap();
+
+    let group_id = harness.query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: vec![first] }).unwrap()[0].group_id;
+    let merchant = harness.merchant.clone();
+    harness.execute(&merchant, ExecuteMsg::ShipGroup { group_id, carrier: None, tracking_ref: None, purchase_ids: None }).unwrap();
+
+    let all = leaderboard(&harness, product_id, None, None);
+    assert_eq!(all.iter().map(|(referrer, _)| referrer.as_str()).collect::<Vec<_>>(), vec!["big", "medium", "small"]);
+    assert!(all.windows(2).all(|pair| pair[0].1 > pair[1].1));
+
+    assert_eq!(leaderboard(&harness, product_id, Some(1), None), all[..1]);
+    assert_eq!(leaderboard(&harness, product_id, Some(1), Some("big")), all[1..2]);
+    assert_eq!(leaderboard(&harness, product_id, None, Some("medium")), all[2..]);
+}
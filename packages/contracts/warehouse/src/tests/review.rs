@@ -0,0 +1,41 @@
+use cosmwasm_std::Addr;
+use shared::msg::{contract::warehouse::{ExecuteMsg, IbcExecuteMsg, MerchantReputation, QueryMsg}, purchase::{Purchase, PurchaseId}};
+
+use super::helpers::{Harness, NFT_CHANNEL};
+
+fn ship(harness: &mut Harness, owner: &Addr, purchase_id: PurchaseId) {
+    let purchase = harness.query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: vec![purchase_id] }).unwrap().remove(0);
+    harness.execute(owner, ExecuteMsg::ShipGroup { group_id: purchase.group_id, carrier: None, tracking_ref: None, purchase_ids: None }).unwrap();
+}
+
+fn review(harness: &mut Harness, purchase_id: PurchaseId, rating: u8) {
+    harness.receive(NFT_CHANNEL, &IbcExecuteMsg::Review { id: purchase_id, rating, comment: String::new() }).unwrap();
+}
+
+fn review_count(harness: &Harness, merchant: &str) -> u32 {
+    harness.query::<MerchantReputation>(QueryMsg::GetMerchantReputation { merchant: merchant.to_string() }).unwrap().review_count
+}
+
+#[test]
+fn reviews_go_to_the_merchant_who_shipped_across_a_product_transfer() {
+    let mut harness = Harness::new();
+    let merchant = harness.merchant.clone();
+    let new_owner = harness.deps.api.addr_make("new-owner");
+    harness.register_merchant(&new_owner);
+    let product_id = harness.add_product(100, 10);
+
+    let shipped_before = harness.purchase("buyer-0", product_id, 1, 100).unwrap();
+    ship(&mut harness, &merchant, shipped_before);
+
+    harness.execute(&merchant, ExecuteMsg::ProposeProductTransfer { product_id, new_owner: new_owner.to_string() }).unwrap();
+    harness.execute(&new_owner, ExecuteMsg::AcceptProductTransfer { product_id }).unwrap();
+
+    let shipped_after = harness.purchase("buyer-1", product_id, 1, 100).unwrap();
+    ship(&mut harness, &new_owner, shipped_after);
+
+    review(&mut harness, shipped_before, 1);
+    review(&mut harness, shipped_after, 5);
+
+    assert_eq!(review_count(&harness, merchant.as_str()), 1);
+    assert_eq!(review_count(&harness, new_owner.as_str()), 1);
+}
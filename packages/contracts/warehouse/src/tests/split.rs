@@ -0,0 +1,89 @@
+use shared::msg::{contract::warehouse::{ExecuteMsg, GroupInfo, GroupPurchaseShipment, QueryMsg}, purchase::Purchase};
+
+use super::helpers::{refunds, Harness};
+
+fn group_info(harness: &Harness, group_id: u64) -> GroupInfo {
+    harness.query::<Vec<GroupInfo>>(QueryMsg::GetGroups { ids: vec![group_id] }).unwrap().remove(0)
+}
+
+fn ship(harness: &mut Harness, group_id: u64, purchase_ids: Vec<u64>) -> anyhow::Result<cosmwasm_std::Response> {
+    let merchant = harness.merchant.clone();
+    harness.execute(&merchant, ExecuteMsg::ShipGroup {
+        group_id,
+        carrier: None,
+        tracking_ref: None,
+        purchase_ids: Some(purchase_ids),
+    })
+}
+
+#[test]
+fn partial_shipment_splits_off_a_new_group() {
+    let mut harness = Harness::new();
+    let product_id = harness.add_product(100, 10);
+    let ids = (0..3)
+        .map(|i| harness.purchase(&format!("spender-{}", i), product_id, 1, 100).unwrap())
+        .collect::<Vec<_>>();
+    let group_id = harness.query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: vec![ids[0]] }).unwrap()[0].group_id;
+
+    let resp = ship(&mut harness, group_id, vec![ids[0], ids[2]]).unwrap();
+
+    let purchases = harness.query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: ids.clone() }).unwrap();
+    let shipped_group_id = purchases[0].group_id;
+    assert_ne!(shipped_group_id, group_id);
+    assert_eq!(purchases[1].group_id, group_id);
+    assert_eq!(purchases[2].group_id, shipped_group_id);
+
+    // both keep the discount tier of the original three buyers
+    let original = group_info(&harness, group_id);
+    let shipped = group_info(&harness, shipped_group_id);
+    assert_eq!((original.count, original.tier_count), (1, 3));
+    assert_eq!((shipped.count, shipped.tier_count), (2, 3));
+    assert_eq!(shipped.split_from, Some(group_id));
+    assert!(shipped.has_shipped());
+    assert!(!original.has_shipped());
+
+    assert_eq!(original.purchases, vec![
+        GroupPurchaseShipment { purchase_id: ids[0], shipped_group_id: Some(shipped_group_id) },
+        GroupPurchaseShipment { purchase_id: ids[1], shipped_group_id: None },
+        GroupPurchaseShipment { purchase_id: ids[2], shipped_group_id: Some(shipped_group_id) },
+    ]);
+
+    // only the shipped buyers are refunded
+    let refunded = refunds(&resp.messages)
+        .into_iter()
+        .map(|(recipient, _)| recipient)
+        .collect::<Vec<_>>();
+    assert_eq!(refunded, vec!["spender-0".to_string(), "spender-2".to_string()]);
+}
+
+#[test]
+fn shipping_every_purchase_keeps_the_group() {
+    let mut harness = Harness::new();
+    let product_id = harness.add_product(100, 10);
+    let ids = (0..2)
+        .map(|i| harness.purchase(&format!("spender-{}", i), product_id, 1, 100).unwrap())
+        .collect::<Vec<_>>();
+    let group_id = harness.query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: vec![ids[0]] }).unwrap()[0].group_id;
+
+    ship(&mut harness, group_id, ids.clone()).unwrap();
+
+    let info = group_info(&harness, group_id);
+    assert!(info.has_shipped());
+    assert_eq!(info.split_from, None);
+    assert!(info.purchases.iter().all(|purchase| purchase.shipped_group_id == Some(group_id)));
+}
+
+#[test]
+fn foreign_purchase_is_rejected_even_when_the_count_matches() {
+    let mut harness = Harness::new();
+    let product_id = harness.add_product(100, 10);
+    let other_product_id = harness.add_product(100, 10);
+    let first = harness.purchase("spender-0", product_id, 1, 100).unwrap();
+    harness.purchase("spender-1", product_id, 1, 100).unwrap();
+    let foreign = harness.purchase("spender-2", other_product_id, 1, 100).unwrap();
+    let group_id = harness.query::<Vec<Purchase>>(QueryMsg::GetPurchases { ids: vec![first] }).unwrap()[0].group_id;
+
+    let err = ship(&mut harness, group_id, vec![first, foreign]).unwrap_err();
+    assert!(err.to_string().contains("is not in group"), "{}", err);
+    assert!(!group_info(&harness, group_id).has_shipped());
+}
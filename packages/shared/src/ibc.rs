@@ -1,6 +1,6 @@
 //! Ibc helpers
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{to_json_binary, Binary, IbcChannel, IbcOrder};
+use cosmwasm_std::{from_json, to_json_binary, Binary, IbcChannel, IbcOrder};
 use anyhow::{Result, bail};
 
 /// Timeout in seconds for IBC packets
@@ -38,6 +38,14 @@ pub fn validate_ibc_channel_order_and_version(
     Ok(())
 }
 
+/// Checks an ibc channel id has the `channel-<n>` form ibc-go gives them
+pub fn validate_channel_id(channel_id: &str) -> Result<()> {
+    match channel_id.strip_prefix("channel-") {
+        Some(n) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => Ok(()),
+        _ => bail!("invalid ibc channel id: {}", channel_id),
+    }
+}
+
 /// IBC ACK. See:
 /// https://github.com/cosmos/cosmos-sdk/blob/f999b1ff05a4db4a338a855713864497bedd4396/proto/ibc/core/channel/v1/channel.proto#L141-L147
 #[cw_serde]
@@ -51,8 +59,19 @@ enum Ack {
 /// IBC ACK success
 pub fn ack_success() -> Binary {
     to_json_binary(&Ack::Result(b"1".into())).unwrap()
+}
 
-    //to_json_binary(&Ack::Result(to_json_binary(&data).unwrap())).unwrap()
+/// IBC ACK success, with a structured result (already serialized)
+pub fn ack_success_with_data(data: Binary) -> Binary {
+    to_json_binary(&Ack::Result(data)).unwrap()
+}
+
+/// The result of an IBC ACK, or its error
+pub fn ack_result(ack: &Binary) -> Result<Binary> {
+    match from_json(ack)? {
+        Ack::Result(data) => Ok(data),
+        Ack::Error(err) => bail!(err),
+    }
 }
 
 /// IBC ACK failure
@@ -2,6 +2,8 @@ use cosmwasm_schema::{QueryResponses, cw_serde};
 use cosmwasm_std::{Addr, Binary, BlockInfo, IbcChannel};
 use cw_utils::Expiration;
 
+use crate::msg::purchase::PurchaseId;
+
 #[cw_serde]
 pub struct InstantiateMsg { }
 
@@ -10,7 +12,12 @@ pub enum IbcExecuteMsg {
     Mint {
         owner: String,
         metadata: Metadata,
-    }
+    },
+    /// Sent by the warehouse after a purchase is reduced, updates the receipt's `quantity` trait
+    SetQuantity {
+        purchase_id: PurchaseId,
+        quantity: u32,
+    },
 }
 
 /// Matches the CW721 standard.
@@ -68,7 +75,41 @@ pub enum ExecuteMsg {
     Burn {
         /// represented as a `String` to match the NFT spec
         token_id: String,
-    }
+    },
+
+    /************ Not part of CW721 standard ************/
+    /// Confirms the purchase was delivered, releasing the escrow to the merchant on the Warehouse contract via IBC
+    /// Only the token owner can confirm
+    ConfirmDelivery {
+        /// represented as a `String` to match the NFT spec
+        token_id: String,
+    },
+    /// Disputes the purchase before it's settled, freezing the escrow on the Warehouse contract via IBC
+    /// Only the token owner can dispute
+    OpenDispute {
+        /// represented as a `String` to match the NFT spec
+        token_id: String,
+        /// Free-form description of what went wrong, for the arbiter
+        evidence: String,
+    },
+    /// Reviews the merchant once the purchase has shipped, on the Warehouse contract via IBC
+    /// Only the token owner can review, and only once per purchase
+    Review {
+        /// represented as a `String` to match the NFT spec
+        token_id: String,
+        /// 1 to 5
+        rating: u8,
+        /// Short review
+        comment: String,
+    },
+    /// Cancels some of the purchase's units without leaving the group, on the Warehouse contract via IBC
+    /// The stock is returned and the difference refunded, only the token owner can reduce
+    ReducePurchase {
+        /// represented as a `String` to match the NFT spec
+        token_id: String,
+        /// number of units to remove, must leave at least one (burn the token to cancel entirely)
+        quantity: u32,
+    },
 }
 
 /// Matches the CW721 standard.
@@ -1,7 +1,7 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{IbcChannel, Uint128};
+use cosmwasm_std::{Addr, IbcChannel, Timestamp, Uint128};
 
-use crate::msg::{product::ProductId, purchase::{Purchase, PurchaseId}};
+use crate::msg::{contract::warehouse::{BatchItem, Coupon, DemandId, HoldId, PayoutRoute}, cw20::Cw20ReceiveMsg, product::{ProductId, VariantId}, purchase::{Purchase, PurchaseId}};
 
 #[cw_serde]
 pub enum ExecuteMsg {
@@ -10,21 +10,252 @@ pub enum ExecuteMsg {
         // The owner address, on the *Nft* chain (not necessarily the sender, nor the warehouse encoding)
         owner: String,
         product_id: ProductId,
-        quantity: u32
-    }
+        // required if the product has variants
+        #[serde(default)]
+        variant_id: Option<VariantId>,
+        quantity: u32,
+        // a hold placed on the warehouse for this sender, see the warehouse `PlaceHold`
+        #[serde(default)]
+        hold_id: Option<HoldId>,
+        // a coupon signed by the product's merchant, checked by the warehouse
+        #[serde(default)]
+        coupon: Option<Coupon>,
+        // who brought the buyer in, gets a share of the discount refund if the merchant offers one
+        #[serde(default)]
+        referrer: Option<String>,
+    },
+    /// Creates several purchase orders at once, in one IBC packet
+    /// The deposit must be exactly the sum of the items' deposits, and anything not accepted is refunded
+    PurchaseBatch {
+        // The owner address, on the *Nft* chain, which gets a receipt per item
+        owner: String,
+        items: Vec<BatchItem>,
+        // all items or none, otherwise each item stands on its own
+        #[serde(default)]
+        atomic: bool,
+        #[serde(default)]
+        referrer: Option<String>,
+    },
+    /// Pledges funds towards a warehouse demand request, sent over IBC
+    /// Refunded if the demand isn't awarded, or down to the winning price if it is
+    Pledge {
+        // The owner address, on the *Nft* chain, which gets the receipt if the demand is awarded
+        owner: String,
+        demand_id: DemandId,
+        quantity: u32,
+    },
+    /// Pre-pays `periods` purchases of a product, one every `period_seconds` starting now
+    /// The deposit must be exactly `per_period` times `periods`, each purchase is sent with `per_period`
+    Subscribe {
+        // The owner address, on the *Nft* chain, which gets a receipt per period
+        owner: String,
+        product_id: ProductId,
+        #[serde(default)]
+        variant_id: Option<VariantId>,
+        quantity: u32,
+        periods: u32,
+        period_seconds: u64,
+        per_period: Uint128,
+    },
+    /// Permissionless, places the purchase for every subscription that's due (up to the limit)
+    CrankSubscriptions {
+        limit: Option<u32>,
+    },
+    /// Subscriber only, passes on the next period without purchasing, its deposit becomes unused
+    SkipSubscriptionPeriod {
+        subscription_id: SubscriptionId,
+    },
+    /// Subscriber only, stops the subscription and credits the rest of the deposit to the claimable balance
+    CancelSubscription {
+        subscription_id: SubscriptionId,
+    },
+    /// Subscriber only, withdraws the deposit that isn't needed for the remaining periods
+    /// e.g. from skipped periods, or purchases the warehouse rejected
+    WithdrawSubscriptionDeposit {
+        subscription_id: SubscriptionId,
+    },
+    /// Purchases paid in an allowlisted cw20 token, the message is a `Cw20HookMsg`
+    Receive(Cw20ReceiveMsg),
+    /// Fee collector only, withdraws the accrued platform fees (all of them if no amount is given)
+    WithdrawFees {
+        amount: Option<Uint128>,
+        // the cw20 contract whose fees to withdraw, the native denom if not set
+        #[serde(default)]
+        cw20: Option<String>,
+    },
+    /// Withdraws from this sender's claimable balance, i.e. refunds and payouts (all of it if no amount is given)
+    Withdraw {
+        amount: Option<Uint128>,
+        // the cw20 contract to withdraw, the native denom if not set
+        #[serde(default)]
+        cw20: Option<String>,
+    },
+    /// Same as `Withdraw`, but sent to another address
+    WithdrawTo {
+        recipient: String,
+        amount: Option<Uint128>,
+        #[serde(default)]
+        cw20: Option<String>,
+    },
+    /// Admin-only, the cw20 contracts that purchases can be paid with
+    UpdateCw20Allowlist {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Admin-only
+    UpdateConfig {
+        admin: Option<String>,
+        fee_collector: Option<String>,
+        #[serde(default)]
+        auto_push: Option<bool>,
+    },
+}
+
+/// Sent as the `msg` of a cw20 `Send` to this contract, same as the native-funded messages
+#[cw_serde]
+pub enum Cw20HookMsg {
+    Purchase {
+        owner: String,
+        product_id: ProductId,
+        #[serde(default)]
+        variant_id: Option<VariantId>,
+        quantity: u32,
+        #[serde(default)]
+        hold_id: Option<HoldId>,
+        #[serde(default)]
+        coupon: Option<Coupon>,
+        #[serde(default)]
+        referrer: Option<String>,
+    },
+    PurchaseBatch {
+        owner: String,
+        items: Vec<BatchItem>,
+        #[serde(default)]
+        atomic: bool,
+        #[serde(default)]
+        referrer: Option<String>,
+    },
 }
 
 #[cw_serde]
 pub enum IbcExecuteMsg {
     Refund {
         refunds: Vec<Refund>
+    },
+    /// Pays merchants out of the escrowed purchase funds
+    Settle {
+        payouts: Vec<Payout>
     }
 }
 
 #[cw_serde]
 pub struct Refund {
     pub recipient: String,
-    pub amount: Uint128
+    pub amount: Uint128,
+    // paid back in this cw20 token, the native denom if not set
+    #[serde(default)]
+    pub cw20: Option<String>,
+}
+
+#[cw_serde]
+pub struct Payout {
+    pub recipient: String,
+    pub amount: Uint128,
+    // platform fee, withheld from the amount and added to the fee ledger
+    #[serde(default)]
+    pub fee: Uint128,
+    // paid in this cw20 token, the native denom if not set
+    #[serde(default)]
+    pub cw20: Option<String>,
+    // sent on over ICS-20 instead of to the recipient, native payouts only
+    #[serde(default)]
+    pub route: Option<PayoutRoute>,
+}
+
+/// Funds pushed straight to the recipient, credited to their claimable balance if the send fails
+#[cw_serde]
+pub struct Delivery {
+    pub recipient: String,
+    pub amount: Uint128,
+    pub cw20: Option<String>,
+}
+
+#[cw_serde]
+pub struct ClaimableBalance {
+    // the cw20 contract, the native denom if not set
+    pub cw20: Option<String>,
+    pub amount: Uint128,
+}
+
+pub type SubscriptionId = u64;
+
+#[cw_serde]
+pub struct Subscription {
+    pub id: SubscriptionId,
+    pub subscriber: Addr,
+    // The owner address, on the *Nft* chain
+    pub owner: String,
+    pub product_id: ProductId,
+    pub variant_id: Option<VariantId>,
+    pub quantity: u32,
+    pub period_seconds: u64,
+    // sent with each period's purchase, the warehouse refunds whatever isn't needed
+    pub per_period: Uint128,
+    pub periods_remaining: u32,
+    // what's left of the deposit, including anything that came back from a failed period
+    pub deposit: Uint128,
+    // when the crank can place the next purchase
+    pub next_at: Timestamp,
+    pub status: SubscriptionStatus,
+    // one per period that made it into the warehouse
+    pub purchase_ids: Vec<PurchaseId>,
+    pub skipped: u32,
+    pub failed: u32,
+}
+
+impl Subscription {
+    // deposit that the remaining periods don't need
+    pub fn unused_deposit(&self) -> Uint128 {
+        self.deposit.saturating_sub(self.per_period * Uint128::from(self.periods_remaining))
+    }
+}
+
+#[cw_serde]
+pub enum SubscriptionStatus {
+    Active,
+    // every period has been placed or skipped
+    Completed,
+    Cancelled,
+}
+
+/// A payout sent over ICS-20, waiting for its ack
+#[cw_serde]
+pub struct InFlightTransfer {
+    // the payout address on this chain, which can withdraw it if the transfer fails
+    pub recipient: String,
+    pub amount: Uint128,
+    pub channel_id: String,
+    pub receiver: String,
+}
+
+/// Callbacks from the ibc-hooks middleware, for transfers sent with an `ibc_callback` memo
+#[cw_serde]
+pub enum SudoMsg {
+    IbcLifecycleComplete(IbcLifecycleComplete),
+}
+
+#[cw_serde]
+pub enum IbcLifecycleComplete {
+    IbcAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    IbcTimeout {
+        channel: String,
+        sequence: u64,
+    },
 }
 
 
@@ -33,11 +264,357 @@ pub struct Refund {
 pub enum QueryMsg {
     /// Get general information about the contract 
     #[returns(InfoResp)]
-    Info { }
+    Info { },
+    #[returns(Config)]
+    Config { },
+    /// Platform fees accrued and not yet withdrawn, in the native denom or the given cw20 token
+    #[returns(Uint128)]
+    FeeBalance {
+        #[serde(default)]
+        cw20: Option<String>,
+    },
+    /// The cw20 contracts that purchases can be paid with
+    #[returns(Vec<Addr>)]
+    Cw20Allowlist { },
+    #[returns(Subscription)]
+    GetSubscription {
+        subscription_id: SubscriptionId,
+    },
+    #[returns(Vec<Subscription>)]
+    ListSubscriptions {
+        subscriber: String,
+        start_after: Option<SubscriptionId>,
+        limit: Option<u32>,
+    },
+    /// Everything the address can withdraw, one entry per asset
+    #[returns(Vec<ClaimableBalance>)]
+    Balances {
+        address: String,
+    },
+    /// What the address can withdraw, in the native denom or the given cw20 token
+    #[returns(Uint128)]
+    Claimable {
+        address: String,
+        #[serde(default)]
+        cw20: Option<String>,
+    },
 }
 
 
 #[cw_serde]
 pub struct InfoResp {
     pub ibc_channel: Option<IbcChannel>
-}
\ No newline at end of file
+}
+
+#[cw_serde]
+pub struct Config {
+    pub admin: Addr,
+    // can withdraw the platform fees
+    pub fee_collector: Addr,
+    // refunds and payouts are sent right away, instead of waiting to be withdrawn
+    // anything that fails to send is still credited to the claimable balance
+    #[serde(default)]
+    pub auto_push: bool,
+}
+
+pub mod event {
+    use cosmwasm_std::{Event, Uint128};
+    use anyhow::{Error, anyhow};
+    use crate::{event::CosmwasmEventExt, msg::contract::warehouse::BatchItemResult};
+    use super::{Subscription, SubscriptionId, SubscriptionStatus};
+
+    /// Event emitted when the warehouse acks a purchase batch
+    #[derive(Debug)]
+    pub struct PurchaseBatchEvent {
+        pub spender: String,
+        pub results: Vec<BatchItemResult>,
+    }
+
+    impl PurchaseBatchEvent {
+        pub const KEY: &'static str = "purchase-batch";
+    }
+
+    impl From<PurchaseBatchEvent> for Event {
+        fn from(src: PurchaseBatchEvent) -> Self {
+            Event::new(PurchaseBatchEvent::KEY).add_attributes(vec![
+                ("spender", src.spender),
+                ("results", serde_json::to_string(&src.results).unwrap()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for PurchaseBatchEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", PurchaseBatchEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, PurchaseBatchEvent::KEY));
+            }
+
+            Ok(PurchaseBatchEvent {
+                spender: evt.string_attr("spender")?,
+                results: evt.json_attr("results")?,
+            })
+        }
+    }
+
+    /// Event emitted when a merchant payout is sent, after the platform fee is withheld
+    #[derive(Debug)]
+    pub struct PayoutEvent {
+        pub recipient: String,
+        // what the recipient received
+        pub amount: Uint128,
+        pub fee: Uint128,
+        // set if the payout was sent on over ICS-20
+        pub channel: Option<String>,
+    }
+
+    impl PayoutEvent {
+        pub const KEY: &'static str = "payout";
+    }
+
+    impl From<PayoutEvent> for Event {
+        fn from(src: PayoutEvent) -> Self {
+            let mut evt = Event::new(PayoutEvent::KEY).add_attributes(vec![
+                ("recipient", src.recipient),
+                ("amount", src.amount.to_string()),
+                ("fee", src.fee.to_string()),
+            ]);
+            if let Some(channel) = src.channel {
+                evt = evt.add_attribute("channel", channel);
+            }
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for PayoutEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", PayoutEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, PayoutEvent::KEY));
+            }
+
+            Ok(PayoutEvent {
+                recipient: evt.string_attr("recipient")?,
+                amount: evt.string_attr("amount")?.parse()?,
+                fee: evt.string_attr("fee")?.parse()?,
+                channel: evt.try_map_attr("channel", |x| x.to_string()),
+            })
+        }
+    }
+
+    /// Event emitted when the fee collector withdraws platform fees
+    #[derive(Debug)]
+    pub struct FeesWithdrawnEvent {
+        pub recipient: String,
+        pub amount: Uint128,
+    }
+
+    impl FeesWithdrawnEvent {
+        pub const KEY: &'static str = "fees-withdrawn";
+    }
+
+    impl From<FeesWithdrawnEvent> for Event {
+        fn from(src: FeesWithdrawnEvent) -> Self {
+            Event::new(FeesWithdrawnEvent::KEY).add_attributes(vec![
+                ("recipient", src.recipient),
+                ("amount", src.amount.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for FeesWithdrawnEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", FeesWithdrawnEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, FeesWithdrawnEvent::KEY));
+            }
+
+            Ok(FeesWithdrawnEvent {
+                recipient: evt.string_attr("recipient")?,
+                amount: evt.string_attr("amount")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when a payout transfer fails or times out, and the funds are left to withdraw instead
+    #[derive(Debug)]
+    pub struct PayoutTransferFailedEvent {
+        pub recipient: String,
+        pub amount: Uint128,
+        pub channel: String,
+        pub sequence: u64,
+    }
+
+    impl PayoutTransferFailedEvent {
+        pub const KEY: &'static str = "payout-transfer-failed";
+    }
+
+    impl From<PayoutTransferFailedEvent> for Event {
+        fn from(src: PayoutTransferFailedEvent) -> Self {
+            Event::new(PayoutTransferFailedEvent::KEY).add_attributes(vec![
+                ("recipient", src.recipient),
+                ("amount", src.amount.to_string()),
+                ("channel", src.channel),
+                ("sequence", src.sequence.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for PayoutTransferFailedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", PayoutTransferFailedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, PayoutTransferFailedEvent::KEY));
+            }
+
+            Ok(PayoutTransferFailedEvent {
+                recipient: evt.string_attr("recipient")?,
+                amount: evt.string_attr("amount")?.parse()?,
+                channel: evt.string_attr("channel")?,
+                sequence: evt.string_attr("sequence")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when a claimable balance is withdrawn
+    #[derive(Debug)]
+    pub struct WithdrawEvent {
+        pub recipient: String,
+        pub amount: Uint128,
+        pub cw20: Option<String>,
+    }
+
+    impl WithdrawEvent {
+        pub const KEY: &'static str = "withdraw";
+    }
+
+    impl From<WithdrawEvent> for Event {
+        fn from(src: WithdrawEvent) -> Self {
+            let mut evt = Event::new(WithdrawEvent::KEY).add_attributes(vec![
+                ("recipient", src.recipient),
+                ("amount", src.amount.to_string()),
+            ]);
+            if let Some(cw20) = src.cw20 {
+                evt = evt.add_attribute("cw20", cw20);
+            }
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for WithdrawEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", WithdrawEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, WithdrawEvent::KEY));
+            }
+
+            Ok(WithdrawEvent {
+                recipient: evt.string_attr("recipient")?,
+                amount: evt.string_attr("amount")?.parse()?,
+                cw20: evt.try_map_attr("cw20", |x| x.to_string()),
+            })
+        }
+    }
+
+    /// Event emitted when funds are credited to a claimable balance
+    #[derive(Debug)]
+    pub struct CreditEvent {
+        pub recipient: String,
+        pub amount: Uint128,
+        pub cw20: Option<String>,
+    }
+
+    impl CreditEvent {
+        pub const KEY: &'static str = "credit";
+    }
+
+    impl From<CreditEvent> for Event {
+        fn from(src: CreditEvent) -> Self {
+            let mut evt = Event::new(CreditEvent::KEY).add_attributes(vec![
+                ("recipient", src.recipient),
+                ("amount", src.amount.to_string()),
+            ]);
+            if let Some(cw20) = src.cw20 {
+                evt = evt.add_attribute("cw20", cw20);
+            }
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for CreditEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", CreditEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, CreditEvent::KEY));
+            }
+
+            Ok(CreditEvent {
+                recipient: evt.string_attr("recipient")?,
+                amount: evt.string_attr("amount")?.parse()?,
+                cw20: evt.try_map_attr("cw20", |x| x.to_string()),
+            })
+        }
+    }
+
+    /// Event emitted whenever a subscription changes, i.e. created, cranked, skipped, cancelled, or a period's ack
+    #[derive(Debug)]
+    pub struct SubscriptionEvent {
+        pub subscription_id: SubscriptionId,
+        pub subscriber: String,
+        pub status: SubscriptionStatus,
+        pub periods_remaining: u32,
+        pub deposit: Uint128,
+    }
+
+    impl SubscriptionEvent {
+        pub const KEY: &'static str = "subscription";
+    }
+
+    impl From<&Subscription> for SubscriptionEvent {
+        fn from(src: &Subscription) -> Self {
+            SubscriptionEvent {
+                subscription_id: src.id,
+                subscriber: src.subscriber.to_string(),
+                status: src.status.clone(),
+                periods_remaining: src.periods_remaining,
+                deposit: src.deposit,
+            }
+        }
+    }
+
+    impl From<SubscriptionEvent> for Event {
+        fn from(src: SubscriptionEvent) -> Self {
+            Event::new(SubscriptionEvent::KEY).add_attributes(vec![
+                ("subscription-id", src.subscription_id.to_string()),
+                ("subscriber", src.subscriber),
+                ("status", serde_json::to_string(&src.status).unwrap()),
+                ("periods-remaining", src.periods_remaining.to_string()),
+                ("deposit", src.deposit.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for SubscriptionEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", SubscriptionEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, SubscriptionEvent::KEY));
+            }
+
+            Ok(SubscriptionEvent {
+                subscription_id: evt.string_attr("subscription-id")?.parse()?,
+                subscriber: evt.string_attr("subscriber")?,
+                status: evt.json_attr("status")?,
+                periods_remaining: evt.string_attr("periods-remaining")?.parse()?,
+                deposit: evt.string_attr("deposit")?.parse()?,
+            })
+        }
+    }
+}
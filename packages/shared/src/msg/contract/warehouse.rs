@@ -1,15 +1,219 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Coin, Decimal256, IbcChannel, Uint128};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal256, IbcChannel, Timestamp, Uint128};
 
-use crate::msg::{product::{Product, ProductId}, purchase::{Purchase, PurchaseId}};
+use crate::msg::{product::{GroupCapacity, PricingMode, Product, ProductId, ProductVariant, PurchaseRules, VariantId}, purchase::{Purchase, PurchaseId}};
+use crate::msg::contract::payment::SubscriptionId;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    // chain id of the *Payment* chain, used to pick the merchant's payout address
+    pub payment_chain_id: String,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    // only needed if the stored config doesn't have one yet
+    #[serde(default)]
+    pub payment_chain_id: Option<String>,
+}
 
 #[cw_serde]
 pub enum ExecuteMsg {
+    /// Registers the sender as a merchant, which is required before listing products
+    RegisterMerchant {
+        profile: MerchantProfile,
+    },
+    /// Requires the Admin role when acting on behalf of another merchant
+    UpdateMerchantProfile {
+        merchant: Option<String>,
+        profile: MerchantProfile,
+    },
+    /// Adds the sent funds (in the bond denom) to the sender's security bond
+    DepositBond { },
+    /// Not allowed while the merchant has disputes that haven't been slashed or closed, the bond is always sent back to the merchant
+    /// Requires the Finance role when acting on behalf of another merchant
+    WithdrawBond {
+        merchant: Option<String>,
+        amount: Uint128,
+    },
+    /// Delegates roles for the merchant to another address, replacing any previous grant
+    /// Requires the Admin role when acting on behalf of another merchant
+    GrantRoles {
+        merchant: Option<String>,
+        delegate: String,
+        roles: Vec<MerchantRole>,
+        expires: Option<Timestamp>,
+    },
+    /// Requires the Admin role when acting on behalf of another merchant
+    RevokeRoles {
+        merchant: Option<String>,
+        delegate: String,
+    },
+    /// Admin-only
+    SetMerchantVerified {
+        merchant: String,
+        verified: bool,
+    },
+    /// Admin-only, picks which of the platform fee tiers applies to the merchant (the default fee if none)
+    SetMerchantTier {
+        merchant: String,
+        tier: Option<String>,
+    },
+    /// Arbiter-only, sends part of a merchant's bond to the buyer of one of their resolved disputes, which closes it
+    /// At most the disputed amount, and only once per dispute
+    /// The bond goes over the configured `payment_transfer_channel` to the purchase's spender
+    SlashBond {
+        merchant: String,
+        amount: Uint128,
+        purchase_id: PurchaseId,
+    },
+    /// Resends slashed bond that failed to reach the recipient, see `QueryMsg::GetSlashClaimable`
+    /// Anyone can call this
+    ClaimSlashedBond {
+        recipient: String,
+    },
+    /// Pays out settlements that were held back while the merchant had no payout address
+    /// Requires the Finance role when acting on behalf of another merchant
+    ClaimWithheldPayouts {
+        merchant: Option<String>,
+    },
+    /// Requires the Catalog role when acting on behalf of another merchant
     AddProduct {
+        merchant: Option<String>,
         product: NewProduct,
     },
+    /// First step of moving a product (and its unshipped groups) to another merchant, replaces any previous proposal
+    /// Requires the Admin role when not sent by the product owner
+    ProposeProductTransfer {
+        product_id: ProductId,
+        new_owner: String,
+    },
+    /// Second step, sent by the proposed owner (or one of their Admin delegates)
+    AcceptProductTransfer {
+        product_id: ProductId,
+    },
+    /// Requires the Admin role when not sent by the product owner
+    CancelProductTransfer {
+        product_id: ProductId,
+    },
+    /// Adds stock, filling any backorders first (oldest purchase first)
+    /// Requires the Catalog role when not sent by the product owner
+    Restock {
+        product_id: ProductId,
+        variant_id: Option<VariantId>,
+        quantity: u32,
+    },
+    /// Reserves stock for the spender until the hold expires (see `Config::hold_seconds`)
+    /// A purchase that references the hold consumes it instead of taking fresh stock
+    /// Must be sent with the `Config::hold_deposit`, which goes back to the holder if the hold is used or released in time
+    /// and to the merchant if it expires
+    /// Only the spender themselves or one of the `Config::hold_relayers` can place a hold
+    /// The product's purchase rules apply to the spender, and a holder can only have a few holds open at once
+    PlaceHold {
+        product_id: ProductId,
+        variant_id: Option<VariantId>,
+        quantity: u32,
+        // the spender address, on the *Payment* chain, that will pay for the hold
+        spender: String,
+    },
+    /// Sent by the holder to give up the hold early
+    ReleaseHold {
+        id: HoldId,
+    },
+    /// Returns the stock of expired holds and forfeits their deposits to the merchant, anyone can call this
+    SweepHolds {
+        limit: Option<u32>,
+    },
+    /// Asks merchants to bid on supplying a product, buyers pledge funds towards it from the *Payment* chain
+    /// Anyone can post a demand request
+    PostDemand {
+        name: String,
+        // total units wanted, pledges stop once it's reached
+        quantity: u32,
+        // the most buyers will pay per unit, bids above this don't qualify
+        max_price: Decimal256,
+        // bids (and pledges) close at this time
+        bid_deadline: Timestamp,
+        // allows sealed bids, which are then revealed during this many seconds after the bid deadline
+        reveal_seconds: Option<u64>,
+    },
+    /// Places (or replaces) the merchant's bid, before the bid deadline
+    /// Requires the Catalog role when acting on behalf of another merchant
+    SubmitBid {
+        demand_id: DemandId,
+        merchant: Option<String>,
+        bid: NewBid,
+    },
+    /// Reveals a sealed bid, after the bid deadline but before the reveal deadline
+    /// Requires the Catalog role when acting on behalf of another merchant
+    RevealBid {
+        demand_id: DemandId,
+        merchant: Option<String>,
+        price: Decimal256,
+        salt: String,
+    },
+    /// Once bidding (and revealing) is over, awards the demand to the lowest qualifying bid
+    /// The winner gets a product and a group with every pledge as a purchase, otherwise all pledges are refunded
+    /// If the award fails, or nobody settles within a week of closing, it settles as unfilled instead
+    /// Anyone can call this
+    SettleDemand {
+        demand_id: DemandId,
+    },
+    /// Group management requires the Shipping role when not sent by the merchant
+    /// Stops the group from accepting new purchases, the next purchase opens a new group
+    LockGroup {
+        group_id: GroupId,
+    },
+    /// Marks the group as packed and ready to ship
+    PackGroup {
+        group_id: GroupId,
+    },
+    /// Ships the group, refunding each buyer the difference from the discounted price
+    /// If only some purchases are given, they're split off into a new group and shipped,
+    /// the rest stay behind and both keep the discount of the original group
     ShipGroup {
         group_id: GroupId,
+        carrier: Option<String>,
+        tracking_ref: Option<String>,
+        #[serde(default)]
+        purchase_ids: Option<Vec<PurchaseId>>,
+    },
+    /// Marks the group as delivered to the buyers
+    DeliverGroup {
+        group_id: GroupId,
+    },
+    /// Closes out the group, nothing further happens after this
+    CompleteGroup {
+        group_id: GroupId,
+    },
+    /// Releases the escrow for every unsettled purchase in a shipped group, once the dispute window has expired
+    /// Anyone can call this
+    ReleaseGroup {
+        group_id: GroupId,
+    },
+    /// Arbiter-only, settles a disputed purchase
+    ResolveDispute {
+        purchase_id: PurchaseId,
+        resolution: DisputeResolution,
+    },
+    /// Arbiter-only, closes a resolved dispute without slashing, which frees up the merchant's bond
+    CloseDispute {
+        purchase_id: PurchaseId,
+    },
+    /// Admin-only
+    UpdateConfig {
+        dispute_window_seconds: Option<u64>,
+        arbiter: Option<String>,
+        payment_chain_id: Option<String>,
+        hold_seconds: Option<u64>,
+        platform_fee: Option<PlatformFee>,
+        #[serde(default)]
+        payment_transfer_channel: Option<String>,
+        #[serde(default)]
+        hold_deposit: Option<Uint128>,
+        // replaces the whole list
+        #[serde(default)]
+        hold_relayers: Option<Vec<String>>,
     },
 }
 
@@ -23,14 +227,88 @@ pub enum IbcExecuteMsg {
         spender: String,
         // The product ID to purchase
         product_id: ProductId, 
+        // required if the product has variants
+        #[serde(default)]
+        variant_id: Option<VariantId>,
         // the quantity of products to purchase
         quantity: u32,
+        // consume this hold rather than taking fresh stock
+        #[serde(default)]
+        hold_id: Option<HoldId>,
+        // signed by the product's merchant
+        #[serde(default)]
+        coupon: Option<Coupon>,
+        // who brought the buyer in, on the *Payment* chain
+        #[serde(default)]
+        referrer: Option<String>,
+        // set if the fees are in a cw20 token rather than the native denom
+        #[serde(default)]
+        cw20: Option<String>,
         // fees sent
         fees: Uint128 
     },
     RemovePurchase {
         id: PurchaseId
     },
+    /// Several purchases paid for with one deposit, the ack carries a `BatchItemResult` per item
+    PurchaseBatch {
+        // The owner address, on the *Nft* chain, which gets a receipt per item
+        owner: String,
+        // The spender address, on the *Payment* chain
+        spender: String,
+        items: Vec<BatchItem>,
+        // all items or none, otherwise each item stands on its own
+        atomic: bool,
+        #[serde(default)]
+        referrer: Option<String>,
+        // set if the deposits are in a cw20 token rather than the native denom
+        #[serde(default)]
+        cw20: Option<String>,
+    },
+    /// Funds put towards a demand request, refunded unless the demand is awarded
+    Pledge {
+        // The owner address, on the *Nft* chain, which gets the receipt if the demand is awarded
+        owner: String,
+        // The spender address, on the *Payment* chain
+        spender: String,
+        demand_id: DemandId,
+        quantity: u32,
+        fees: Uint128,
+    },
+    /// One period of a payment-chain subscription, placed into the product's current group
+    /// Whatever the fees don't need is refunded, and the ack carries the `PurchaseId`
+    SubscriptionPurchase {
+        // The owner address, on the *Nft* chain
+        owner: String,
+        // The subscriber address, on the *Payment* chain
+        spender: String,
+        subscription_id: SubscriptionId,
+        product_id: ProductId,
+        #[serde(default)]
+        variant_id: Option<VariantId>,
+        quantity: u32,
+        fees: Uint128,
+    },
+    /// Sent by the receipt NFT holder to remove some units from a pending purchase, keeping its place in the group
+    ReducePurchase {
+        id: PurchaseId,
+        quantity: u32,
+    },
+    /// Sent by the receipt NFT holder once the goods have arrived, releases the escrow to the merchant
+    ConfirmDelivery {
+        id: PurchaseId
+    },
+    /// Sent by the receipt NFT holder before settlement, freezes the escrow until the arbiter resolves it
+    OpenDispute {
+        id: PurchaseId,
+        evidence: String,
+    },
+    /// Sent by the receipt NFT holder once the group has shipped, one review per purchase
+    Review {
+        id: PurchaseId,
+        rating: u8,
+        comment: String,
+    },
 }
 
 #[cw_serde]
@@ -38,7 +316,48 @@ pub struct NewProduct {
     pub name: String,
     // max price per item, will be reduced for each person in the OrderGroup
     pub price: Decimal256,
-    pub stock: u32
+    // must be zero when there are variants, since stock is then tracked per variant
+    pub stock: u32,
+    // when set, a group locks once it reaches this size and the next purchase opens a new group
+    pub group_capacity: Option<GroupCapacity>,
+    // ship the group automatically as soon as it fills up
+    #[serde(default)]
+    pub auto_ship_on_full: bool,
+    // anti-hoarding rules, enforced per spender
+    #[serde(default)]
+    pub purchase_rules: PurchaseRules,
+    #[serde(default)]
+    pub variants: Vec<ProductVariant>,
+    // enables pre-orders, see `Product::preorder_cap`
+    #[serde(default)]
+    pub preorder_cap: Option<u32>,
+    #[serde(default)]
+    pub pricing: PricingMode,
+    // see `Product::referral_share`
+    #[serde(default)]
+    pub referral_share: Decimal256,
+    // see `Product::cw20`
+    #[serde(default)]
+    pub cw20: Option<String>,
+}
+
+impl NewProduct {
+    pub fn into_product(self, id: ProductId) -> Product {
+        Product {
+            id,
+            name: self.name,
+            price: self.price,
+            stock: self.stock,
+            group_capacity: self.group_capacity,
+            auto_ship_on_full: self.auto_ship_on_full,
+            purchase_rules: self.purchase_rules,
+            variants: self.variants,
+            preorder_cap: self.preorder_cap,
+            pricing: self.pricing,
+            referral_share: self.referral_share,
+            cw20: self.cw20,
+        }
+    }
 }
 
 #[cw_serde]
@@ -73,9 +392,147 @@ pub enum QueryMsg {
     GetGroups { 
         ids: Vec<GroupId>,
     },
+    /// Returns the escrow state for the given (shipped) purchase ids
+    #[returns(Vec<PurchaseEscrow>)]
+    GetEscrows { 
+        ids: Vec<PurchaseId>,
+    },
+    /// Returns the disputes for the given purchase ids
+    #[returns(Vec<Dispute>)]
+    GetDisputes { 
+        ids: Vec<PurchaseId>,
+    },
+    /// Returns all disputes, or only the unresolved ones 
+    #[returns(Vec<Dispute>)]
+    ListDisputes { 
+        unresolved_only: Option<bool>,
+        limit: Option<u32>,
+        start_after: Option<PurchaseId>
+    },
+    /// Settlements held back until the merchant sets a payout address for the *Payment* chain
+    #[returns(Vec<WithheldPayout>)]
+    GetWithheldPayouts {
+        merchant: String,
+    },
+    /// Slashed bond owed to a recipient on the *Payment* chain, after its transfer failed
+    #[returns(Uint128)]
+    GetSlashClaimable {
+        recipient: String,
+    },
+    /// Returns the aggregated review score for a merchant
+    #[returns(MerchantReputation)]
+    GetMerchantReputation { 
+        merchant: String,
+    },
+    /// Returns the reviews left for a merchant, oldest purchases first
+    #[returns(Vec<Review>)]
+    ListMerchantReviews { 
+        merchant: String,
+        limit: Option<u32>,
+        start_after: Option<PurchaseId>
+    },
+    /// Returns the registered merchant
+    #[returns(Merchant)]
+    GetMerchant { 
+        merchant: String,
+    },
+    /// Returns the proposed new owner of the product, if a transfer is pending
+    #[returns(Option<Addr>)]
+    GetProductTransfer { 
+        product_id: ProductId,
+    },
+    /// Returns the holds with the given ids
+    #[returns(Vec<Hold>)]
+    GetHolds { 
+        ids: Vec<HoldId>,
+    },
+    /// Returns all active holds (including expired ones that haven't been swept yet)
+    #[returns(Vec<Hold>)]
+    ListHolds { 
+        spender: Option<String>,
+        limit: Option<u32>,
+        start_after: Option<HoldId>
+    },
+    /// Returns the demand requests with the given ids
+    #[returns(Vec<Demand>)]
+    GetDemands { 
+        ids: Vec<DemandId>,
+    },
+    /// Returns all demand requests
+    #[returns(Vec<Demand>)]
+    ListDemands { 
+        open_only: Option<bool>,
+        limit: Option<u32>,
+        start_after: Option<DemandId>
+    },
+    /// Returns the bids on a demand request, sealed bids only show their commitment until revealed
+    #[returns(Vec<Bid>)]
+    ListBids { 
+        demand_id: DemandId,
+        limit: Option<u32>,
+        start_after: Option<String>
+    },
+    /// Returns the pledges towards a demand request
+    #[returns(Vec<Pledge>)]
+    ListPledges { 
+        demand_id: DemandId,
+        limit: Option<u32>,
+        start_after: Option<u32>
+    },
+    /// Dry-runs a purchase, showing what it would cost and whether it would go through
+    #[returns(PurchaseQuote)]
+    QuotePurchase {
+        product_id: ProductId,
+        // required if the product has variants
+        #[serde(default)]
+        variant_id: Option<VariantId>,
+        quantity: u32,
+        // the spender address, on the *Payment* chain, so that per-buyer rules are checked
+        #[serde(default)]
+        spender: Option<String>,
+        #[serde(default)]
+        coupon: Option<Coupon>,
+    },
+    /// Returns the referrers of a product's shipped purchases, most rewarded first (30 at a time by default)
+    /// Paginated by the last referrer of the previous page
+    #[returns(Vec<ReferralStats>)]
+    ReferralLeaderboard {
+        product_id: ProductId,
+        limit: Option<u32>,
+        #[serde(default)]
+        start_after: Option<String>,
+    },
+    /// Returns the referrers of the purchases currently in a group
+    #[returns(Vec<GroupReferral>)]
+    GetGroupReferrals {
+        group_id: GroupId,
+    },
+    /// Returns how many times a merchant's coupon has been used
+    #[returns(u32)]
+    GetCouponUses {
+        merchant: String,
+        nonce: u64,
+    },
+    /// Returns the merchant's delegates and their roles, including expired grants
+    #[returns(Vec<Delegate>)]
+    ListDelegates { 
+        merchant: String,
+        limit: Option<u32>,
+        start_after: Option<String>
+    },
+    /// Returns all registered merchants
+    #[returns(Vec<Merchant>)]
+    ListMerchants { 
+        verified_only: Option<bool>,
+        limit: Option<u32>,
+        start_after: Option<String>
+    },
     /// Get general information about the contract 
     #[returns(InfoResp)]
     Info { },
+    /// Get the contract configuration
+    #[returns(Config)]
+    Config { },
 }
 
 #[cw_serde]
@@ -83,6 +540,412 @@ pub struct InfoResp {
     pub ibc_payment_channel: Option<IbcChannel>
 }
 
+#[cw_serde]
+pub struct Config {
+    pub admin: Addr,
+    // resolves disputes between buyers and merchants
+    pub arbiter: Addr,
+    // chain id of the *Payment* chain, used to pick the merchant's payout address
+    pub payment_chain_id: String,
+    // ICS-20 channel from this chain to the *Payment* chain, slashed bonds are sent over it
+    #[serde(default)]
+    pub payment_transfer_channel: Option<String>,
+    // how long after shipping the buyers have to confirm or dispute, before the merchant can be paid regardless
+    pub dispute_window_seconds: u64,
+    // how long an inventory hold lasts before it can be swept
+    pub hold_seconds: u64,
+    // in the bond denom, locked for as long as a hold is open so holds can't be spammed for free
+    #[serde(default)]
+    pub hold_deposit: Uint128,
+    // may place holds for any spender, everyone else can only place holds for themselves
+    #[serde(default)]
+    pub hold_relayers: Vec<Addr>,
+    // withheld from merchant payouts by the *Payment* contract
+    #[serde(default)]
+    pub platform_fee: PlatformFee,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PlatformFee {
+    // in basis points, for merchants without a tier (or whose tier isn't listed)
+    pub fee_bps: u16,
+    pub tiers: Vec<TierFee>,
+}
+
+#[cw_serde]
+pub struct TierFee {
+    pub tier: String,
+    pub fee_bps: u16,
+}
+
+impl PlatformFee {
+    pub fn fee_bps(&self, tier: Option<&str>) -> u16 {
+        tier
+            .and_then(|tier| self.tiers.iter().find(|x| x.tier == tier))
+            .map_or(self.fee_bps, |x| x.fee_bps)
+    }
+
+    /// The part of a merchant payout that goes to the platform
+    pub fn fee(&self, amount: Uint128, tier: Option<&str>) -> Uint128 {
+        amount.multiply_ratio(self.fee_bps(tier), 10_000u32)
+    }
+}
+
+pub type DemandId = u64;
+
+/// A buyer-side request for merchants to bid on, see `ExecuteMsg::PostDemand`
+#[cw_serde]
+pub struct Demand {
+    pub id: DemandId,
+    pub creator: Addr,
+    pub name: String,
+    pub quantity: u32,
+    pub max_price: Decimal256,
+    pub bid_deadline: Timestamp,
+    // only set if sealed bids are allowed
+    pub reveal_deadline: Option<Timestamp>,
+    // total units pledged so far
+    pub pledged: u32,
+    pub status: DemandStatus,
+}
+
+impl Demand {
+    /// When the demand can be settled
+    pub fn closes_at(&self) -> Timestamp {
+        self.reveal_deadline.unwrap_or(self.bid_deadline)
+    }
+}
+
+#[cw_serde]
+pub enum DemandStatus {
+    Open,
+    /// The winning merchant now has a product, with every pledge as a purchase in its group
+    Awarded {
+        merchant: Addr,
+        price: Decimal256,
+        product_id: ProductId,
+        group_id: GroupId,
+    },
+    /// Nobody bid low enough (or nobody pledged, or the award failed or came too late), every pledge was refunded
+    Unfilled,
+}
+
+#[cw_serde]
+pub enum NewBid {
+    /// Price per unit, visible to everyone
+    Open {
+        price: Decimal256,
+    },
+    /// Hex-encoded sha256 of "{price}:{salt}", revealed with `ExecuteMsg::RevealBid`
+    Sealed {
+        commitment: String,
+    },
+}
+
+#[cw_serde]
+pub struct Bid {
+    pub merchant: Addr,
+    // set for open bids, or sealed bids once they're revealed
+    pub price: Option<Decimal256>,
+    pub commitment: Option<String>,
+    pub submitted_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct Pledge {
+    pub owner: String,
+    pub spender: String,
+    pub quantity: u32,
+    pub fees: Uint128,
+}
+
+pub type HoldId = u64;
+
+/// Stock reserved for a spender, waiting on a purchase
+#[cw_serde]
+pub struct Hold {
+    pub id: HoldId,
+    // who placed the hold, and can release it early
+    pub holder: Addr,
+    // the spender address, on the *Payment* chain, whose purchase can consume the hold
+    pub spender: String,
+    pub product_id: ProductId,
+    pub variant_id: Option<VariantId>,
+    pub quantity: u32,
+    pub expires: Timestamp,
+    // refunded to the holder when the hold ends
+    #[serde(default)]
+    pub deposit: Uint128,
+}
+
+/// A discount signed off-chain by the merchant, attached to a purchase
+#[cw_serde]
+pub struct Coupon {
+    pub payload: CouponPayload,
+    // secp256k1 signature, by the merchant's coupon key, of the sha256 of the payload's JSON
+    pub signature: Binary,
+}
+
+#[cw_serde]
+pub struct CouponPayload {
+    pub product_id: ProductId,
+    pub discount: CouponDiscount,
+    // each purchase it's attached to is one use
+    pub max_uses: u32,
+    pub expires: Timestamp,
+    // identifies the coupon, uses are tracked per merchant and nonce
+    pub nonce: u64,
+}
+
+/// Taken off the price of each unit, never below zero
+#[cw_serde]
+pub enum CouponDiscount {
+    // e.g. 10 for 10% off
+    Percent(Decimal256),
+    Amount(Decimal256),
+}
+
+impl CouponDiscount {
+    pub fn per_unit(&self, unit_price: Decimal256) -> Decimal256 {
+        match self {
+            CouponDiscount::Percent(percent) => unit_price * (*percent * Decimal256::percent(1)).min(Decimal256::one()),
+            CouponDiscount::Amount(amount) => (*amount).min(unit_price),
+        }
+    }
+}
+
+/// One purchase in a `PurchaseBatch`
+#[cw_serde]
+pub struct BatchItem {
+    pub product_id: ProductId,
+    #[serde(default)]
+    pub variant_id: Option<VariantId>,
+    pub quantity: u32,
+    // this item's share of the batch deposit, refunded if the item isn't accepted
+    pub deposit: Uint128,
+    #[serde(default)]
+    pub hold_id: Option<HoldId>,
+    #[serde(default)]
+    pub coupon: Option<Coupon>,
+}
+
+/// Sent back in the ack of a `PurchaseBatch`, in the same order as the items
+#[cw_serde]
+pub struct BatchItemResult {
+    pub product_id: ProductId,
+    // set if the item was accepted
+    pub purchase_id: Option<PurchaseId>,
+    // set if it wasn't, and its deposit was refunded
+    pub error: Option<String>,
+}
+
+/// See `QueryMsg::QuotePurchase`
+#[cw_serde]
+pub struct PurchaseQuote {
+    // what to attach to the purchase, anything the group discount takes off is refunded at ship time
+    pub deposit: Uint128,
+    // per unit, before the group discount
+    pub unit_price: Decimal256,
+    // the group the purchase would join, and the price per unit there once it has
+    pub group_id: Option<GroupId>,
+    pub group_price: Option<Decimal256>,
+    // per unit if one more buyer joins after this purchase
+    pub next_tier_price: Option<Decimal256>,
+    // stock left after the purchase (or now, if it would be rejected)
+    pub remaining_stock: u32,
+    // units that would wait on a restock
+    pub backordered: u32,
+    // withheld from the merchant's share at the group price
+    pub platform_fee_bps: u16,
+    pub platform_fee: Uint128,
+    // why the purchase would be rejected, if it would be
+    pub error: Option<String>,
+}
+
+/// A referrer's totals for a product, only counting shipped purchases
+#[cw_serde]
+pub struct ReferralStats {
+    pub referrer: String,
+    pub purchases: u32,
+    pub units: u32,
+    // paid out of the referred buyers' discount refunds
+    pub earned: Uint128,
+}
+
+#[cw_serde]
+pub struct GroupReferral {
+    pub referrer: String,
+    pub purchase_ids: Vec<PurchaseId>,
+    pub units: u32,
+}
+
+/// Funds owed to the merchant for a shipped purchase, held on the *Payment* chain until released
+#[cw_serde]
+pub struct PurchaseEscrow {
+    pub purchase_id: PurchaseId,
+    pub group_id: GroupId,
+    pub amount: Uint128,
+    // once this passes, the escrow can be released without the buyer confirming
+    pub release_at: Timestamp,
+    pub confirmed_at: Option<Timestamp>,
+    pub settled_at: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct Dispute {
+    pub purchase_id: PurchaseId,
+    pub evidence: String,
+    pub opened_at: Timestamp,
+    pub resolution: Option<DisputeResolution>,
+    pub resolved_at: Option<Timestamp>,
+    // the escrow when the dispute was opened, which caps any slash
+    #[serde(default)]
+    pub amount: Uint128,
+    // set if the merchant's bond was slashed for it
+    #[serde(default)]
+    pub slashed: Option<Uint128>,
+    // once closed (slashed or not) it no longer locks the merchant's bond
+    #[serde(default)]
+    pub closed_at: Option<Timestamp>,
+}
+
+/// A slashed bond sent over ICS-20, waiting for its ack
+#[cw_serde]
+pub struct InFlightSlash {
+    // the purchase's spender, on the *Payment* chain
+    pub recipient: String,
+    pub amount: Uint128,
+    pub channel_id: String,
+}
+
+#[cw_serde]
+pub struct MerchantProfile {
+    pub display_name: String,
+    pub contact: Option<String>,
+    pub logo_uri: Option<String>,
+    // where settlements are paid out, one per payment chain
+    pub payout_addresses: Vec<PayoutAddress>,
+    // compressed secp256k1 public key that coupons must be signed with, no coupons are accepted without one
+    #[serde(default)]
+    pub coupon_pubkey: Option<Binary>,
+    // forwards settlements from the payment chain to another chain, over ICS-20
+    // a failed transfer is kept on the payment chain for the payout address to withdraw
+    #[serde(default)]
+    pub payout_route: Option<PayoutRoute>,
+}
+
+#[cw_serde]
+pub struct WithheldPayout {
+    pub purchase_id: PurchaseId,
+    // the whole escrow, including the fee
+    pub amount: Uint128,
+    pub fee: Uint128,
+    pub cw20: Option<String>,
+}
+
+#[cw_serde]
+pub struct PayoutAddress {
+    pub chain_id: String,
+    pub address: String,
+}
+
+#[cw_serde]
+pub struct PayoutRoute {
+    // the chain the proceeds end up on
+    pub chain_id: String,
+    // the ICS-20 channel on the payment chain that leads there
+    pub channel_id: String,
+    // the merchant's address on that chain
+    pub receiver: String,
+}
+
+#[cw_serde]
+pub struct Merchant {
+    pub address: Addr,
+    pub profile: MerchantProfile,
+    // set by the admin
+    pub verified: bool,
+    // set by the admin, see `PlatformFee`
+    pub tier: Option<String>,
+    // security bond, can be slashed by the arbiter
+    pub bond: Uint128,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum MerchantRole {
+    // everything, including managing the profile and other delegates
+    Admin,
+    // adding products
+    Catalog,
+    // moving groups through the shipment lifecycle
+    Shipping,
+    // withdrawing the bond
+    Finance,
+}
+
+#[cw_serde]
+pub struct Delegate {
+    pub address: Addr,
+    pub roles: Vec<MerchantRole>,
+    // the grant stops working at this time, if set
+    pub expires: Option<Timestamp>,
+}
+
+impl Delegate {
+    pub fn has_role(&self, role: MerchantRole, now: Timestamp) -> bool {
+        if self.expires.is_some_and(|expires| now >= expires) {
+            return false;
+        }
+        self.roles.iter().any(|r| *r == role || *r == MerchantRole::Admin)
+    }
+}
+
+#[cw_serde]
+pub struct Review {
+    pub purchase_id: PurchaseId,
+    pub product_id: ProductId,
+    pub merchant: Addr,
+    // 1 to 5
+    pub rating: u8,
+    pub comment: String,
+    pub timestamp: Timestamp,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct MerchantReputation {
+    pub review_count: u32,
+    // sum of all ratings
+    pub rating_total: u64,
+    // out of 5, None until the first review
+    #[serde(default)]
+    pub average_rating: Option<Decimal256>,
+}
+
+impl MerchantReputation {
+    pub fn average_rating(&self) -> Option<Decimal256> {
+        if self.review_count == 0 {
+            None
+        } else {
+            Some(Decimal256::from_ratio(self.rating_total, self.review_count))
+        }
+    }
+}
+
+#[cw_serde]
+pub enum DisputeResolution {
+    /// The buyer gets back everything that was held in escrow
+    FullRefund,
+    /// The buyer gets back this amount, and the rest of the escrow goes to the merchant
+    PartialRefund {
+        amount: Uint128,
+    },
+    /// The merchant gets the escrow, as if the buyer had confirmed delivery
+    ReleaseToMerchant,
+}
+
 #[cw_serde]
 pub struct PurchaseError {
     // The owner address, on the *Warehouse* chain (not necessarily the spender)
@@ -91,8 +954,10 @@ pub struct PurchaseError {
     pub spender: String,
     // The product ID to purchase
     pub product_id: ProductId, 
+    pub variant_id: Option<VariantId>,
     // the quantity of products to purchase
     pub quantity: u32,
+    pub hold_id: Option<HoldId>,
     // fees sent
     pub fees: Uint128,
     // reason
@@ -101,7 +966,7 @@ pub struct PurchaseError {
 
 impl std::fmt::Display for PurchaseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "purchase error: owner: {}, spender: {}, product_id: {}, quantity: {}, funds: {:?}, reason: {}", self.owner, self.spender, self.product_id, self.quantity, self.fees, self.reason)
+        write!(f, "purchase error: owner: {}, spender: {}, product_id: {}, variant_id: {:?}, quantity: {}, funds: {:?}, reason: {}", self.owner, self.spender, self.product_id, self.variant_id, self.quantity, self.fees, self.reason)
     }
 }
 impl std::error::Error for PurchaseError {}
@@ -111,29 +976,134 @@ pub type GroupId = u64;
 #[cw_serde]
 pub struct GroupInfo {
     pub id: GroupId,
+    // number of buyers (purchases) in the group
     pub count: u32,
+    // total number of units across all purchases in the group
+    pub units: u32,
     pub product: Product,
-    pub has_shipped: bool
+    // current stage of the shipment lifecycle
+    pub status: GroupStatus,
+    // every status the group has been in, oldest first
+    pub status_history: Vec<GroupStatusChange>,
+    // remaining room in the group, in terms of the product's `group_capacity` (None if unlimited)
+    pub remaining_capacity: Option<u32>,
+    // units still waiting on a restock, the group can't ship until this is zero
+    pub backordered: u32,
+    // the number of buyers the discount is based on, at least `count` but kept from before any partial shipment
+    pub tier_count: u32,
+    // set if this group was split off another one by a partial shipment
+    pub split_from: Option<GroupId>,
+    // every purchase that was ever in this group, including ones split off by a partial shipment
+    #[serde(default)]
+    pub purchases: Vec<GroupPurchaseShipment>,
+}
+
+#[cw_serde]
+pub struct GroupPurchaseShipment {
+    pub purchase_id: PurchaseId,
+    // the group it shipped with (this one, or the group it was split into), None while it's still waiting to ship
+    pub shipped_group_id: Option<GroupId>,
+}
+
+/// Shipment lifecycle of a group, always moves forward in this order (though stages may be skipped)
+#[cw_serde]
+pub enum GroupStatus {
+    /// Accepting purchases
+    Pending,
+    /// No longer accepting purchases
+    Locked,
+    /// Packed and ready to ship
+    Packed,
+    /// Handed over to the carrier, buyers have been refunded down to the group price
+    Shipped {
+        carrier: Option<String>,
+        tracking_ref: Option<String>,
+    },
+    /// Arrived at the buyers
+    Delivered,
+    /// Closed out
+    Completed,
+}
+
+impl GroupStatus {
+    /// Position in the lifecycle, used to make sure transitions only move forward
+    pub fn stage(&self) -> u8 {
+        match self {
+            GroupStatus::Pending => 0,
+            GroupStatus::Locked => 1,
+            GroupStatus::Packed => 2,
+            GroupStatus::Shipped { .. } => 3,
+            GroupStatus::Delivered => 4,
+            GroupStatus::Completed => 5,
+        }
+    }
+
+    pub fn has_shipped(&self) -> bool {
+        self.stage() >= GroupStatus::Shipped { carrier: None, tracking_ref: None }.stage()
+    }
+}
+
+impl std::fmt::Display for GroupStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupStatus::Pending => write!(f, "pending"),
+            GroupStatus::Locked => write!(f, "locked"),
+            GroupStatus::Packed => write!(f, "packed"),
+            GroupStatus::Shipped { carrier, tracking_ref } => {
+                write!(f, "shipped")?;
+                if let Some(carrier) = carrier {
+                    write!(f, " via {}", carrier)?;
+                }
+                if let Some(tracking_ref) = tracking_ref {
+                    write!(f, " ({})", tracking_ref)?;
+                }
+                Ok(())
+            },
+            GroupStatus::Delivered => write!(f, "delivered"),
+            GroupStatus::Completed => write!(f, "completed"),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct GroupStatusChange {
+    pub status: GroupStatus,
+    pub timestamp: Timestamp,
 }
 
 impl GroupInfo {
+    pub fn has_shipped(&self) -> bool {
+        self.status.has_shipped()
+    }
+
     pub fn discount_perc(&self) -> Decimal256 {
+        if self.product.pricing != PricingMode::GroupDiscount {
+            return Decimal256::zero();
+        }
+
         let one = Decimal256::one();
-        let count = Decimal256::from_ratio(self.count, 1u32);
+        let count = Decimal256::from_ratio(self.tier_count, 1u32);
 
         // TEST ME
         (one - (one / (one + count))) * Decimal256::percent(100)
     }
 
     pub fn cost_per_item(&self) -> Decimal256 {
-        self.product.price * (Decimal256::one() - self.discount_perc())
+        self.discounted_price(self.product.price)
+    }
+
+    /// Applies the group's discount to any unit price, e.g. one that includes a variant's price delta
+    pub fn discounted_price(&self, unit_price: Decimal256) -> Decimal256 {
+        unit_price * (Decimal256::one() - self.discount_perc())
     }
 }
 
 pub mod event {
-    use cosmwasm_std::{Addr, Event};
+    use cosmwasm_std::{Addr, Decimal256, Event, Timestamp, Uint128};
     use anyhow::{Error, anyhow};
-    use crate::{event::CosmwasmEventExt, msg::{product::Product, purchase::Purchase}};
+    use crate::{event::CosmwasmEventExt, msg::{product::{Product, ProductId}, purchase::{Purchase, PurchaseId}}};
+
+    use super::{DemandId, DemandStatus, DisputeResolution, GroupId, GroupStatus, Hold, HoldId, MerchantRole};
 
     /// Event emitted when a new product is added to the warehouse 
     #[derive(Debug)]
@@ -147,12 +1117,25 @@ pub mod event {
 
     impl From<AddProductEvent> for Event {
         fn from(src: AddProductEvent) -> Self {
-            Event::new(AddProductEvent::KEY).add_attributes(vec![
+            let mut evt = Event::new(AddProductEvent::KEY).add_attributes(vec![
                 ("id", src.product.id.to_string()),
                 ("name", src.product.name.to_string()),
                 ("price", src.product.price.to_string()),
                 ("stock", src.product.stock.to_string()),
-            ])
+                ("group-capacity", serde_json::to_string(&src.product.group_capacity).unwrap()),
+                ("auto-ship-on-full", src.product.auto_ship_on_full.to_string()),
+                ("purchase-rules", serde_json::to_string(&src.product.purchase_rules).unwrap()),
+                ("variants", serde_json::to_string(&src.product.variants).unwrap()),
+                ("preorder-cap", serde_json::to_string(&src.product.preorder_cap).unwrap()),
+                ("pricing", serde_json::to_string(&src.product.pricing).unwrap()),
+                ("referral-share", src.product.referral_share.to_string()),
+            ]);
+
+            if let Some(cw20) = src.product.cw20 {
+                evt = evt.add_attribute("cw20", cw20);
+            }
+
+            evt
         }
     }
 
@@ -170,6 +1153,14 @@ pub mod event {
                     name: evt.string_attr("name")?,
                     price: evt.string_attr("price")?.parse()?,
                     stock: evt.string_attr("stock")?.parse()?,
+                    group_capacity: evt.json_attr("group-capacity")?,
+                    auto_ship_on_full: evt.string_attr("auto-ship-on-full")?.parse()?,
+                    purchase_rules: evt.json_attr("purchase-rules")?,
+                    variants: evt.json_attr("variants")?,
+                    preorder_cap: evt.json_attr("preorder-cap")?,
+                    pricing: evt.json_attr("pricing")?,
+                    referral_share: evt.string_attr("referral-share")?.parse()?,
+                    cw20: evt.try_map_attr("cw20", |x| x.to_string()),
                 }
             })
         }
@@ -188,13 +1179,32 @@ pub mod event {
 
     impl From<PurchaseEvent> for Event {
         fn from(src: PurchaseEvent) -> Self {
-            Event::new(PurchaseEvent::KEY).add_attributes(vec![
+            let mut evt = Event::new(PurchaseEvent::KEY).add_attributes(vec![
                 ("id", src.purchase.id.to_string()),
                 ("product-id", src.purchase.product_id.to_string()),
                 ("group-id", src.purchase.group_id.to_string()),
                 ("quantity", src.purchase.quantity.to_string()),
+                ("backordered", src.purchase.backordered.to_string()),
                 ("spender", src.purchase.spender.to_string()),
-            ])
+            ]);
+
+            if let Some(variant_id) = src.purchase.variant_id {
+                evt = evt.add_attribute("variant-id", variant_id.to_string());
+            }
+            if let Some(price) = src.purchase.price {
+                evt = evt.add_attribute("price", price.to_string());
+            }
+            if !src.purchase.discount.is_zero() {
+                evt = evt.add_attribute("discount", src.purchase.discount.to_string());
+            }
+            if let Some(referrer) = src.purchase.referrer {
+                evt = evt.add_attribute("referrer", referrer);
+            }
+            if let Some(cw20) = src.purchase.cw20 {
+                evt = evt.add_attribute("cw20", cw20);
+            }
+
+            evt
         }
     }
 
@@ -210,11 +1220,1243 @@ pub mod event {
                 purchase: Purchase{
                     id: evt.string_attr("id")?.parse()?,
                     product_id: evt.string_attr("product-id")?.parse()?,
+                    variant_id: evt.try_map_attr("variant-id", |x| x.parse()).transpose()?,
                     group_id: evt.string_attr("group-id")?.parse()?,
                     quantity: evt.string_attr("quantity")?.parse()?,
+                    backordered: evt.string_attr("backordered")?.parse()?,
                     spender: evt.string_attr("spender")?.parse()?,
+                    price: evt.try_map_attr("price", |x| x.parse()).transpose()?,
+                    discount: evt.try_map_attr("discount", |x| x.parse()).transpose()?.unwrap_or_default(),
+                    referrer: evt.try_map_attr("referrer", |x| x.to_string()),
+                    cw20: evt.try_map_attr("cw20", |x| x.to_string()),
                 }
             })
         }
     }
+
+
+    /// Event emitted when a group reaches its capacity and is locked
+    #[derive(Debug)]
+    pub struct GroupFilledEvent {
+        pub group_id: GroupId,
+        pub product_id: ProductId,
+        pub count: u32,
+        pub units: u32,
+    }
+
+    impl GroupFilledEvent {
+        pub const KEY: &'static str = "group-filled";
+    }
+
+    impl From<GroupFilledEvent> for Event {
+        fn from(src: GroupFilledEvent) -> Self {
+            Event::new(GroupFilledEvent::KEY).add_attributes(vec![
+                ("group-id", src.group_id.to_string()),
+                ("product-id", src.product_id.to_string()),
+                ("count", src.count.to_string()),
+                ("units", src.units.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for GroupFilledEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", GroupFilledEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, GroupFilledEvent::KEY));
+            }
+
+            Ok(GroupFilledEvent {
+                group_id: evt.string_attr("group-id")?.parse()?,
+                product_id: evt.string_attr("product-id")?.parse()?,
+                count: evt.string_attr("count")?.parse()?,
+                units: evt.string_attr("units")?.parse()?,
+            })
+        }
+    }
+
+
+    /// Event emitted when a group stops accepting purchases
+    #[derive(Debug)]
+    pub struct GroupLockedEvent {
+        pub group_id: GroupId,
+        pub timestamp: Timestamp,
+    }
+
+    impl GroupLockedEvent {
+        pub const KEY: &'static str = "group-locked";
+    }
+
+    impl From<GroupLockedEvent> for Event {
+        fn from(src: GroupLockedEvent) -> Self {
+            Event::new(GroupLockedEvent::KEY).add_attributes(vec![
+                ("group-id", src.group_id.to_string()),
+                ("timestamp", src.timestamp.nanos().to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for GroupLockedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", GroupLockedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, GroupLockedEvent::KEY));
+            }
+
+            Ok(GroupLockedEvent {
+                group_id: evt.string_attr("group-id")?.parse()?,
+                timestamp: Timestamp::from_nanos(evt.u64_attr("timestamp")?),
+            })
+        }
+    }
+
+    /// Event emitted when a group is packed and ready to ship
+    #[derive(Debug)]
+    pub struct GroupPackedEvent {
+        pub group_id: GroupId,
+        pub timestamp: Timestamp,
+    }
+
+    impl GroupPackedEvent {
+        pub const KEY: &'static str = "group-packed";
+    }
+
+    impl From<GroupPackedEvent> for Event {
+        fn from(src: GroupPackedEvent) -> Self {
+            Event::new(GroupPackedEvent::KEY).add_attributes(vec![
+                ("group-id", src.group_id.to_string()),
+                ("timestamp", src.timestamp.nanos().to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for GroupPackedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", GroupPackedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, GroupPackedEvent::KEY));
+            }
+
+            Ok(GroupPackedEvent {
+                group_id: evt.string_attr("group-id")?.parse()?,
+                timestamp: Timestamp::from_nanos(evt.u64_attr("timestamp")?),
+            })
+        }
+    }
+
+    /// Event emitted when a group is handed over to the carrier
+    #[derive(Debug)]
+    pub struct GroupShippedEvent {
+        pub group_id: GroupId,
+        pub carrier: Option<String>,
+        pub tracking_ref: Option<String>,
+        pub timestamp: Timestamp,
+    }
+
+    impl GroupShippedEvent {
+        pub const KEY: &'static str = "group-shipped";
+    }
+
+    impl From<GroupShippedEvent> for Event {
+        fn from(src: GroupShippedEvent) -> Self {
+            let mut evt = Event::new(GroupShippedEvent::KEY).add_attributes(vec![
+                ("group-id", src.group_id.to_string()),
+                ("timestamp", src.timestamp.nanos().to_string()),
+            ]);
+            if let Some(carrier) = src.carrier {
+                evt = evt.add_attribute("carrier", carrier);
+            }
+            if let Some(tracking_ref) = src.tracking_ref {
+                evt = evt.add_attribute("tracking-ref", tracking_ref);
+            }
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for GroupShippedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", GroupShippedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, GroupShippedEvent::KEY));
+            }
+
+            Ok(GroupShippedEvent {
+                group_id: evt.string_attr("group-id")?.parse()?,
+                carrier: evt.try_map_attr("carrier", |x| x.to_string()),
+                tracking_ref: evt.try_map_attr("tracking-ref", |x| x.to_string()),
+                timestamp: Timestamp::from_nanos(evt.u64_attr("timestamp")?),
+            })
+        }
+    }
+
+    /// Event emitted when a group has arrived at the buyers
+    #[derive(Debug)]
+    pub struct GroupDeliveredEvent {
+        pub group_id: GroupId,
+        pub timestamp: Timestamp,
+    }
+
+    impl GroupDeliveredEvent {
+        pub const KEY: &'static str = "group-delivered";
+    }
+
+    impl From<GroupDeliveredEvent> for Event {
+        fn from(src: GroupDeliveredEvent) -> Self {
+            Event::new(GroupDeliveredEvent::KEY).add_attributes(vec![
+                ("group-id", src.group_id.to_string()),
+                ("timestamp", src.timestamp.nanos().to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for GroupDeliveredEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", GroupDeliveredEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, GroupDeliveredEvent::KEY));
+            }
+
+            Ok(GroupDeliveredEvent {
+                group_id: evt.string_attr("group-id")?.parse()?,
+                timestamp: Timestamp::from_nanos(evt.u64_attr("timestamp")?),
+            })
+        }
+    }
+
+    /// Event emitted when a group is closed out
+    #[derive(Debug)]
+    pub struct GroupCompletedEvent {
+        pub group_id: GroupId,
+        pub timestamp: Timestamp,
+    }
+
+    impl GroupCompletedEvent {
+        pub const KEY: &'static str = "group-completed";
+    }
+
+    impl From<GroupCompletedEvent> for Event {
+        fn from(src: GroupCompletedEvent) -> Self {
+            Event::new(GroupCompletedEvent::KEY).add_attributes(vec![
+                ("group-id", src.group_id.to_string()),
+                ("timestamp", src.timestamp.nanos().to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for GroupCompletedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", GroupCompletedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, GroupCompletedEvent::KEY));
+            }
+
+            Ok(GroupCompletedEvent {
+                group_id: evt.string_attr("group-id")?.parse()?,
+                timestamp: Timestamp::from_nanos(evt.u64_attr("timestamp")?),
+            })
+        }
+    }
+
+    /// Event emitted when a buyer confirms that a purchase was delivered
+    #[derive(Debug)]
+    pub struct DeliveryConfirmedEvent {
+        pub purchase_id: PurchaseId,
+    }
+
+    impl DeliveryConfirmedEvent {
+        pub const KEY: &'static str = "delivery-confirmed";
+    }
+
+    impl From<DeliveryConfirmedEvent> for Event {
+        fn from(src: DeliveryConfirmedEvent) -> Self {
+            Event::new(DeliveryConfirmedEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for DeliveryConfirmedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", DeliveryConfirmedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, DeliveryConfirmedEvent::KEY));
+            }
+
+            Ok(DeliveryConfirmedEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when the escrow for a purchase is released to the merchant
+    #[derive(Debug)]
+    pub struct SettlementEvent {
+        pub purchase_id: PurchaseId,
+        pub recipient: String,
+        // the whole escrow, including the fee
+        pub amount: Uint128,
+        // platform fee, withheld from the amount
+        pub fee: Uint128,
+    }
+
+    impl SettlementEvent {
+        pub const KEY: &'static str = "settlement";
+    }
+
+    impl From<SettlementEvent> for Event {
+        fn from(src: SettlementEvent) -> Self {
+            Event::new(SettlementEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("recipient", src.recipient),
+                ("amount", src.amount.to_string()),
+                ("fee", src.fee.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for SettlementEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", SettlementEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, SettlementEvent::KEY));
+            }
+
+            Ok(SettlementEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                recipient: evt.string_attr("recipient")?,
+                amount: evt.string_attr("amount")?.parse()?,
+                fee: evt.string_attr("fee")?.parse()?,
+            })
+        }
+    }
+
+
+    /// Event emitted when a buyer disputes a purchase
+    #[derive(Debug)]
+    pub struct DisputeOpenedEvent {
+        pub purchase_id: PurchaseId,
+        pub evidence: String,
+    }
+
+    impl DisputeOpenedEvent {
+        pub const KEY: &'static str = "dispute-opened";
+    }
+
+    impl From<DisputeOpenedEvent> for Event {
+        fn from(src: DisputeOpenedEvent) -> Self {
+            Event::new(DisputeOpenedEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("evidence", src.evidence),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for DisputeOpenedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", DisputeOpenedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, DisputeOpenedEvent::KEY));
+            }
+
+            Ok(DisputeOpenedEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                evidence: evt.string_attr("evidence")?,
+            })
+        }
+    }
+
+    /// Event emitted when the arbiter resolves a dispute
+    #[derive(Debug)]
+    pub struct DisputeResolvedEvent {
+        pub purchase_id: PurchaseId,
+        pub resolution: DisputeResolution,
+    }
+
+    impl DisputeResolvedEvent {
+        pub const KEY: &'static str = "dispute-resolved";
+    }
+
+    impl From<DisputeResolvedEvent> for Event {
+        fn from(src: DisputeResolvedEvent) -> Self {
+            Event::new(DisputeResolvedEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("resolution", serde_json::to_string(&src.resolution).unwrap()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for DisputeResolvedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", DisputeResolvedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, DisputeResolvedEvent::KEY));
+            }
+
+            Ok(DisputeResolvedEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                resolution: evt.json_attr("resolution")?,
+            })
+        }
+    }
+
+    /// Event emitted when the arbiter closes a resolved dispute, with or without slashing the bond
+    #[derive(Debug)]
+    pub struct DisputeClosedEvent {
+        pub purchase_id: PurchaseId,
+        pub slashed: Option<Uint128>,
+    }
+
+    impl DisputeClosedEvent {
+        pub const KEY: &'static str = "dispute-closed";
+    }
+
+    impl From<DisputeClosedEvent> for Event {
+        fn from(src: DisputeClosedEvent) -> Self {
+            let mut evt = Event::new(DisputeClosedEvent::KEY).add_attribute("purchase-id", src.purchase_id.to_string());
+
+            if let Some(slashed) = src.slashed {
+                evt = evt.add_attribute("slashed", slashed.to_string());
+            }
+
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for DisputeClosedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", DisputeClosedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, DisputeClosedEvent::KEY));
+            }
+
+            Ok(DisputeClosedEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                slashed: evt.try_map_attr("slashed", |x| x.parse()).transpose()?,
+            })
+        }
+    }
+
+    /// Event emitted when a buyer reviews a merchant
+    #[derive(Debug)]
+    pub struct ReviewEvent {
+        pub purchase_id: PurchaseId,
+        pub merchant: Addr,
+        pub rating: u8,
+    }
+
+    impl ReviewEvent {
+        pub const KEY: &'static str = "review";
+    }
+
+    impl From<ReviewEvent> for Event {
+        fn from(src: ReviewEvent) -> Self {
+            Event::new(ReviewEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("merchant", src.merchant.to_string()),
+                ("rating", src.rating.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for ReviewEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", ReviewEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, ReviewEvent::KEY));
+            }
+
+            Ok(ReviewEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                rating: evt.string_attr("rating")?.parse()?,
+            })
+        }
+    }
+
+
+    /// Event emitted when a new merchant registers
+    #[derive(Debug)]
+    pub struct MerchantRegisteredEvent {
+        pub merchant: Addr,
+        pub display_name: String,
+    }
+
+    impl MerchantRegisteredEvent {
+        pub const KEY: &'static str = "merchant-registered";
+    }
+
+    impl From<MerchantRegisteredEvent> for Event {
+        fn from(src: MerchantRegisteredEvent) -> Self {
+            Event::new(MerchantRegisteredEvent::KEY).add_attributes(vec![
+                ("merchant", src.merchant.to_string()),
+                ("display-name", src.display_name),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for MerchantRegisteredEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", MerchantRegisteredEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, MerchantRegisteredEvent::KEY));
+            }
+
+            Ok(MerchantRegisteredEvent {
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                display_name: evt.string_attr("display-name")?,
+            })
+        }
+    }
+
+    /// Event emitted when the arbiter slashes a merchant's bond
+    #[derive(Debug)]
+    pub struct BondSlashedEvent {
+        pub merchant: Addr,
+        pub amount: Uint128,
+        pub purchase_id: PurchaseId,
+        // the purchase's spender, on the *Payment* chain
+        pub recipient: String,
+    }
+
+    impl BondSlashedEvent {
+        pub const KEY: &'static str = "bond-slashed";
+    }
+
+    impl From<BondSlashedEvent> for Event {
+        fn from(src: BondSlashedEvent) -> Self {
+            Event::new(BondSlashedEvent::KEY).add_attributes(vec![
+                ("merchant", src.merchant.to_string()),
+                ("amount", src.amount.to_string()),
+                ("purchase-id", src.purchase_id.to_string()),
+                ("recipient", src.recipient),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for BondSlashedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", BondSlashedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, BondSlashedEvent::KEY));
+            }
+
+            Ok(BondSlashedEvent {
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                amount: evt.string_attr("amount")?.parse()?,
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                recipient: evt.string_attr("recipient")?,
+            })
+        }
+    }
+
+    /// Event emitted when a slashed bond didn't make it to the recipient, and was credited to them to claim instead
+    #[derive(Debug)]
+    pub struct SlashTransferFailedEvent {
+        pub recipient: String,
+        pub amount: Uint128,
+    }
+
+    impl SlashTransferFailedEvent {
+        pub const KEY: &'static str = "slash-transfer-failed";
+    }
+
+    impl From<SlashTransferFailedEvent> for Event {
+        fn from(src: SlashTransferFailedEvent) -> Self {
+            Event::new(SlashTransferFailedEvent::KEY).add_attributes(vec![
+                ("recipient", src.recipient),
+                ("amount", src.amount.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for SlashTransferFailedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", SlashTransferFailedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, SlashTransferFailedEvent::KEY));
+            }
+
+            Ok(SlashTransferFailedEvent {
+                recipient: evt.string_attr("recipient")?,
+                amount: evt.string_attr("amount")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when a merchant delegates roles to another address
+    #[derive(Debug)]
+    pub struct RolesGrantedEvent {
+        pub merchant: Addr,
+        pub delegate: Addr,
+        pub roles: Vec<MerchantRole>,
+        pub expires: Option<Timestamp>,
+    }
+
+    impl RolesGrantedEvent {
+        pub const KEY: &'static str = "roles-granted";
+    }
+
+    impl From<RolesGrantedEvent> for Event {
+        fn from(src: RolesGrantedEvent) -> Self {
+            let mut evt = Event::new(RolesGrantedEvent::KEY).add_attributes(vec![
+                ("merchant", src.merchant.to_string()),
+                ("delegate", src.delegate.to_string()),
+                ("roles", serde_json::to_string(&src.roles).unwrap()),
+            ]);
+
+            if let Some(expires) = src.expires {
+                evt = evt.add_attribute("expires", expires.nanos().to_string());
+            }
+
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for RolesGrantedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", RolesGrantedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, RolesGrantedEvent::KEY));
+            }
+
+            Ok(RolesGrantedEvent {
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                delegate: evt.unchecked_addr_attr("delegate")?,
+                roles: evt.json_attr("roles")?,
+                expires: evt.try_u64_attr("expires")?.map(Timestamp::from_nanos),
+            })
+        }
+    }
+
+    /// Event emitted when a merchant revokes a delegate's roles
+    #[derive(Debug)]
+    pub struct RolesRevokedEvent {
+        pub merchant: Addr,
+        pub delegate: Addr,
+    }
+
+    impl RolesRevokedEvent {
+        pub const KEY: &'static str = "roles-revoked";
+    }
+
+    impl From<RolesRevokedEvent> for Event {
+        fn from(src: RolesRevokedEvent) -> Self {
+            Event::new(RolesRevokedEvent::KEY).add_attributes(vec![
+                ("merchant", src.merchant.to_string()),
+                ("delegate", src.delegate.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for RolesRevokedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", RolesRevokedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, RolesRevokedEvent::KEY));
+            }
+
+            Ok(RolesRevokedEvent {
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                delegate: evt.unchecked_addr_attr("delegate")?,
+            })
+        }
+    }
+
+    /// Event emitted when a product owner proposes a new owner
+    #[derive(Debug)]
+    pub struct ProductTransferProposedEvent {
+        pub product_id: ProductId,
+        pub owner: Addr,
+        pub new_owner: Addr,
+    }
+
+    impl ProductTransferProposedEvent {
+        pub const KEY: &'static str = "product-transfer-proposed";
+    }
+
+    impl From<ProductTransferProposedEvent> for Event {
+        fn from(src: ProductTransferProposedEvent) -> Self {
+            Event::new(ProductTransferProposedEvent::KEY).add_attributes(vec![
+                ("product-id", src.product_id.to_string()),
+                ("owner", src.owner.to_string()),
+                ("new-owner", src.new_owner.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for ProductTransferProposedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", ProductTransferProposedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, ProductTransferProposedEvent::KEY));
+            }
+
+            Ok(ProductTransferProposedEvent {
+                product_id: evt.string_attr("product-id")?.parse()?,
+                owner: evt.unchecked_addr_attr("owner")?,
+                new_owner: evt.unchecked_addr_attr("new-owner")?,
+            })
+        }
+    }
+
+    /// Event emitted when the new owner accepts a product transfer
+    #[derive(Debug)]
+    pub struct ProductTransferredEvent {
+        pub product_id: ProductId,
+        pub previous_owner: Addr,
+        pub owner: Addr,
+        // the unshipped groups that moved along with the product
+        pub group_ids: Vec<GroupId>,
+    }
+
+    impl ProductTransferredEvent {
+        pub const KEY: &'static str = "product-transferred";
+    }
+
+    impl From<ProductTransferredEvent> for Event {
+        fn from(src: ProductTransferredEvent) -> Self {
+            Event::new(ProductTransferredEvent::KEY).add_attributes(vec![
+                ("product-id", src.product_id.to_string()),
+                ("previous-owner", src.previous_owner.to_string()),
+                ("owner", src.owner.to_string()),
+                ("group-ids", serde_json::to_string(&src.group_ids).unwrap()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for ProductTransferredEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", ProductTransferredEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, ProductTransferredEvent::KEY));
+            }
+
+            Ok(ProductTransferredEvent {
+                product_id: evt.string_attr("product-id")?.parse()?,
+                previous_owner: evt.unchecked_addr_attr("previous-owner")?,
+                owner: evt.unchecked_addr_attr("owner")?,
+                group_ids: evt.json_attr("group-ids")?,
+            })
+        }
+    }
+
+    /// Event emitted when a restock fills (some of) a purchase's backordered units
+    #[derive(Debug)]
+    pub struct BackorderFilledEvent {
+        pub purchase_id: PurchaseId,
+        pub group_id: GroupId,
+        pub filled: u32,
+        // still waiting on stock
+        pub remaining: u32,
+    }
+
+    impl BackorderFilledEvent {
+        pub const KEY: &'static str = "backorder-filled";
+    }
+
+    impl From<BackorderFilledEvent> for Event {
+        fn from(src: BackorderFilledEvent) -> Self {
+            Event::new(BackorderFilledEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("group-id", src.group_id.to_string()),
+                ("filled", src.filled.to_string()),
+                ("remaining", src.remaining.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for BackorderFilledEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", BackorderFilledEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, BackorderFilledEvent::KEY));
+            }
+
+            Ok(BackorderFilledEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                group_id: evt.string_attr("group-id")?.parse()?,
+                filled: evt.string_attr("filled")?.parse()?,
+                remaining: evt.string_attr("remaining")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when stock is put on hold for a spender
+    #[derive(Debug)]
+    pub struct HoldPlacedEvent {
+        pub hold: Hold,
+    }
+
+    impl HoldPlacedEvent {
+        pub const KEY: &'static str = "hold-placed";
+    }
+
+    impl From<HoldPlacedEvent> for Event {
+        fn from(src: HoldPlacedEvent) -> Self {
+            let mut evt = Event::new(HoldPlacedEvent::KEY).add_attributes(vec![
+                ("id", src.hold.id.to_string()),
+                ("holder", src.hold.holder.to_string()),
+                ("spender", src.hold.spender.to_string()),
+                ("product-id", src.hold.product_id.to_string()),
+                ("quantity", src.hold.quantity.to_string()),
+                ("expires", src.hold.expires.nanos().to_string()),
+                ("deposit", src.hold.deposit.to_string()),
+            ]);
+
+            if let Some(variant_id) = src.hold.variant_id {
+                evt = evt.add_attribute("variant-id", variant_id.to_string());
+            }
+
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for HoldPlacedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", HoldPlacedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, HoldPlacedEvent::KEY));
+            }
+
+            Ok(HoldPlacedEvent {
+                hold: Hold {
+                    id: evt.string_attr("id")?.parse()?,
+                    holder: evt.unchecked_addr_attr("holder")?,
+                    spender: evt.string_attr("spender")?,
+                    product_id: evt.string_attr("product-id")?.parse()?,
+                    variant_id: evt.try_map_attr("variant-id", |x| x.parse()).transpose()?,
+                    quantity: evt.string_attr("quantity")?.parse()?,
+                    expires: Timestamp::from_nanos(evt.u64_attr("expires")?),
+                    deposit: evt.string_attr("deposit")?.parse()?,
+                }
+            })
+        }
+    }
+
+    /// Event emitted when a hold ends, either consumed by a purchase, released by the holder or swept after expiring
+    #[derive(Debug)]
+    pub struct HoldEndedEvent {
+        pub hold_id: HoldId,
+        // set if a purchase consumed the hold, otherwise its stock was returned
+        pub purchase_id: Option<PurchaseId>,
+    }
+
+    impl HoldEndedEvent {
+        pub const KEY: &'static str = "hold-ended";
+    }
+
+    impl From<HoldEndedEvent> for Event {
+        fn from(src: HoldEndedEvent) -> Self {
+            let mut evt = Event::new(HoldEndedEvent::KEY).add_attributes(vec![
+                ("hold-id", src.hold_id.to_string()),
+            ]);
+
+            if let Some(purchase_id) = src.purchase_id {
+                evt = evt.add_attribute("purchase-id", purchase_id.to_string());
+            }
+
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for HoldEndedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", HoldEndedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, HoldEndedEvent::KEY));
+            }
+
+            Ok(HoldEndedEvent {
+                hold_id: evt.string_attr("hold-id")?.parse()?,
+                purchase_id: evt.try_u64_attr("purchase-id")?,
+            })
+        }
+    }
+
+    /// Event emitted when the buyer removes some units from a pending purchase
+    #[derive(Debug)]
+    pub struct PurchaseReducedEvent {
+        pub purchase_id: PurchaseId,
+        // units removed
+        pub removed: u32,
+        // units left in the purchase
+        pub quantity: u32,
+        pub refund: Uint128,
+    }
+
+    impl PurchaseReducedEvent {
+        pub const KEY: &'static str = "purchase-reduced";
+    }
+
+    impl From<PurchaseReducedEvent> for Event {
+        fn from(src: PurchaseReducedEvent) -> Self {
+            Event::new(PurchaseReducedEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("removed", src.removed.to_string()),
+                ("quantity", src.quantity.to_string()),
+                ("refund", src.refund.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for PurchaseReducedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", PurchaseReducedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, PurchaseReducedEvent::KEY));
+            }
+
+            Ok(PurchaseReducedEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                removed: evt.string_attr("removed")?.parse()?,
+                quantity: evt.string_attr("quantity")?.parse()?,
+                refund: evt.string_attr("refund")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when some purchases are split off a group to be shipped on their own
+    #[derive(Debug)]
+    pub struct GroupSplitEvent {
+        // the group that keeps the rest of the purchases
+        pub group_id: GroupId,
+        // the new group, which ships right away
+        pub shipped_group_id: GroupId,
+        pub purchase_ids: Vec<PurchaseId>,
+    }
+
+    impl GroupSplitEvent {
+        pub const KEY: &'static str = "group-split";
+    }
+
+    impl From<GroupSplitEvent> for Event {
+        fn from(src: GroupSplitEvent) -> Self {
+            Event::new(GroupSplitEvent::KEY).add_attributes(vec![
+                ("group-id", src.group_id.to_string()),
+                ("shipped-group-id", src.shipped_group_id.to_string()),
+                ("purchase-ids", serde_json::to_string(&src.purchase_ids).unwrap()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for GroupSplitEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", GroupSplitEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, GroupSplitEvent::KEY));
+            }
+
+            Ok(GroupSplitEvent {
+                group_id: evt.string_attr("group-id")?.parse()?,
+                shipped_group_id: evt.string_attr("shipped-group-id")?.parse()?,
+                purchase_ids: evt.json_attr("purchase-ids")?,
+            })
+        }
+    }
+
+    /// Event emitted when a demand request is posted
+    #[derive(Debug)]
+    pub struct DemandPostedEvent {
+        pub demand_id: DemandId,
+        pub name: String,
+        pub quantity: u32,
+        pub max_price: Decimal256,
+        pub bid_deadline: Timestamp,
+    }
+
+    impl DemandPostedEvent {
+        pub const KEY: &'static str = "demand-posted";
+    }
+
+    impl From<DemandPostedEvent> for Event {
+        fn from(src: DemandPostedEvent) -> Self {
+            Event::new(DemandPostedEvent::KEY).add_attributes(vec![
+                ("demand-id", src.demand_id.to_string()),
+                ("name", src.name),
+                ("quantity", src.quantity.to_string()),
+                ("max-price", src.max_price.to_string()),
+                ("bid-deadline", src.bid_deadline.nanos().to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for DemandPostedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", DemandPostedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, DemandPostedEvent::KEY));
+            }
+
+            Ok(DemandPostedEvent {
+                demand_id: evt.string_attr("demand-id")?.parse()?,
+                name: evt.string_attr("name")?,
+                quantity: evt.string_attr("quantity")?.parse()?,
+                max_price: evt.string_attr("max-price")?.parse()?,
+                bid_deadline: Timestamp::from_nanos(evt.u64_attr("bid-deadline")?),
+            })
+        }
+    }
+
+    /// Event emitted when funds are pledged towards a demand request
+    #[derive(Debug)]
+    pub struct PledgeEvent {
+        pub demand_id: DemandId,
+        pub spender: String,
+        pub quantity: u32,
+        // total pledged so far
+        pub pledged: u32,
+    }
+
+    impl PledgeEvent {
+        pub const KEY: &'static str = "pledge";
+    }
+
+    impl From<PledgeEvent> for Event {
+        fn from(src: PledgeEvent) -> Self {
+            Event::new(PledgeEvent::KEY).add_attributes(vec![
+                ("demand-id", src.demand_id.to_string()),
+                ("spender", src.spender),
+                ("quantity", src.quantity.to_string()),
+                ("pledged", src.pledged.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for PledgeEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", PledgeEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, PledgeEvent::KEY));
+            }
+
+            Ok(PledgeEvent {
+                demand_id: evt.string_attr("demand-id")?.parse()?,
+                spender: evt.string_attr("spender")?,
+                quantity: evt.string_attr("quantity")?.parse()?,
+                pledged: evt.string_attr("pledged")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when a merchant bids on a demand request, the price is only included for open (or revealed) bids
+    #[derive(Debug)]
+    pub struct BidEvent {
+        pub demand_id: DemandId,
+        pub merchant: Addr,
+        pub price: Option<Decimal256>,
+    }
+
+    impl BidEvent {
+        pub const KEY: &'static str = "bid";
+    }
+
+    impl From<BidEvent> for Event {
+        fn from(src: BidEvent) -> Self {
+            let mut evt = Event::new(BidEvent::KEY).add_attributes(vec![
+                ("demand-id", src.demand_id.to_string()),
+                ("merchant", src.merchant.to_string()),
+            ]);
+
+            if let Some(price) = src.price {
+                evt = evt.add_attribute("price", price.to_string());
+            }
+
+            evt
+        }
+    }
+
+    impl TryFrom<Event> for BidEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", BidEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, BidEvent::KEY));
+            }
+
+            Ok(BidEvent {
+                demand_id: evt.string_attr("demand-id")?.parse()?,
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                price: evt.try_map_attr("price", |x| x.parse()).transpose()?,
+            })
+        }
+    }
+
+    /// Event emitted when a demand request is settled
+    #[derive(Debug)]
+    pub struct DemandSettledEvent {
+        pub demand_id: DemandId,
+        pub status: DemandStatus,
+    }
+
+    impl DemandSettledEvent {
+        pub const KEY: &'static str = "demand-settled";
+    }
+
+    impl From<DemandSettledEvent> for Event {
+        fn from(src: DemandSettledEvent) -> Self {
+            Event::new(DemandSettledEvent::KEY).add_attributes(vec![
+                ("demand-id", src.demand_id.to_string()),
+                ("status", serde_json::to_string(&src.status).unwrap()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for DemandSettledEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", DemandSettledEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, DemandSettledEvent::KEY));
+            }
+
+            Ok(DemandSettledEvent {
+                demand_id: evt.string_attr("demand-id")?.parse()?,
+                status: evt.json_attr("status")?,
+            })
+        }
+    }
+
+    /// Event emitted when a purchase uses a merchant's coupon
+    #[derive(Debug)]
+    pub struct CouponRedeemedEvent {
+        pub merchant: Addr,
+        pub nonce: u64,
+        pub purchase_id: PurchaseId,
+        // including this one
+        pub uses: u32,
+    }
+
+    impl CouponRedeemedEvent {
+        pub const KEY: &'static str = "coupon-redeemed";
+    }
+
+    impl From<CouponRedeemedEvent> for Event {
+        fn from(src: CouponRedeemedEvent) -> Self {
+            Event::new(CouponRedeemedEvent::KEY).add_attributes(vec![
+                ("merchant", src.merchant.to_string()),
+                ("nonce", src.nonce.to_string()),
+                ("purchase-id", src.purchase_id.to_string()),
+                ("uses", src.uses.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for CouponRedeemedEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", CouponRedeemedEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, CouponRedeemedEvent::KEY));
+            }
+
+            Ok(CouponRedeemedEvent {
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                nonce: evt.string_attr("nonce")?.parse()?,
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                uses: evt.string_attr("uses")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when a referrer is paid a share of a referred buyer's discount refund
+    #[derive(Debug)]
+    pub struct ReferralRewardEvent {
+        pub purchase_id: PurchaseId,
+        pub referrer: String,
+        pub amount: Uint128,
+    }
+
+    impl ReferralRewardEvent {
+        pub const KEY: &'static str = "referral-reward";
+    }
+
+    impl From<ReferralRewardEvent> for Event {
+        fn from(src: ReferralRewardEvent) -> Self {
+            Event::new(ReferralRewardEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("referrer", src.referrer),
+                ("amount", src.amount.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for ReferralRewardEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", ReferralRewardEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, ReferralRewardEvent::KEY));
+            }
+
+            Ok(ReferralRewardEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                referrer: evt.string_attr("referrer")?,
+                amount: evt.string_attr("amount")?.parse()?,
+            })
+        }
+    }
+
+    /// Event emitted when a settlement is held back because the merchant has no payout address
+    #[derive(Debug)]
+    pub struct PayoutWithheldEvent {
+        pub purchase_id: PurchaseId,
+        pub merchant: Addr,
+        pub amount: Uint128,
+    }
+
+    impl PayoutWithheldEvent {
+        pub const KEY: &'static str = "payout-withheld";
+    }
+
+    impl From<PayoutWithheldEvent> for Event {
+        fn from(src: PayoutWithheldEvent) -> Self {
+            Event::new(PayoutWithheldEvent::KEY).add_attributes(vec![
+                ("purchase-id", src.purchase_id.to_string()),
+                ("merchant", src.merchant.to_string()),
+                ("amount", src.amount.to_string()),
+            ])
+        }
+    }
+
+    impl TryFrom<Event> for PayoutWithheldEvent {
+        type Error = Error;
+
+        fn try_from(evt: Event) -> anyhow::Result<Self> {
+            if evt.ty.as_str() != format!("wasm-{}", PayoutWithheldEvent::KEY) {
+                return Err(anyhow!("unexpected event type: {}, should be {}", evt.ty, PayoutWithheldEvent::KEY));
+            }
+
+            Ok(PayoutWithheldEvent {
+                purchase_id: evt.string_attr("purchase-id")?.parse()?,
+                merchant: evt.unchecked_addr_attr("merchant")?,
+                amount: evt.string_attr("amount")?.parse()?,
+            })
+        }
+    }
 }
@@ -0,0 +1,20 @@
+//! The parts of the cw20 spec we use, kept wire-compatible with the `cw20` crate
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, Uint128};
+
+/// Sent by a cw20 contract to the recipient of a `Send`
+#[cw_serde]
+pub struct Cw20ReceiveMsg {
+    // the token holder who sent the tokens
+    pub sender: String,
+    pub amount: Uint128,
+    pub msg: Binary,
+}
+
+#[cw_serde]
+pub enum Cw20ExecuteMsg {
+    Transfer {
+        recipient: String,
+        amount: Uint128,
+    },
+}
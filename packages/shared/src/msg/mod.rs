@@ -1,3 +1,4 @@
 pub mod contract;
 pub mod product;
-pub mod purchase;
\ No newline at end of file
+pub mod purchase;
+pub mod cw20;
@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Decimal256;
+use cosmwasm_std::{Decimal256, SignedDecimal256, Timestamp};
 
 #[cw_serde]
 pub struct Product {
@@ -7,7 +7,170 @@ pub struct Product {
     pub name: String,
     // max price per item, will be reduced for each person in the OrderGroup
     pub price: Decimal256,
-    pub stock: u32
+    // when the product has variants, this is the total across all of them
+    pub stock: u32,
+    // when set, a group locks once it reaches this size and the next purchase opens a new group
+    pub group_capacity: Option<GroupCapacity>,
+    // ship the group automatically as soon as it fills up
+    pub auto_ship_on_full: bool,
+    // anti-hoarding rules, enforced per spender
+    pub purchase_rules: PurchaseRules,
+    // e.g. sizes or colors, all variants share the same groups (and so the same discount)
+    pub variants: Vec<ProductVariant>,
+    // pre-order mode, purchases beyond the current stock are backordered up to this many units
+    pub preorder_cap: Option<u32>,
+    pub pricing: PricingMode,
+    // share (0 to 1) of a referred buyer's discount refund that goes to their referrer instead
+    pub referral_share: Decimal256,
+    // the cw20 token (on the *Payment* chain) the price is in, the native purchase denom if not set
+    // purchases paid in anything else are rejected
+    #[serde(default)]
+    pub cw20: Option<String>,
+}
+
+impl Product {
+    /// Purchases must name a variant if (and only if) the product has variants
+    pub fn variant(&self, variant_id: Option<VariantId>) -> anyhow::Result<Option<&ProductVariant>> {
+        match variant_id {
+            None if self.variants.is_empty() => Ok(None),
+            None => anyhow::bail!("product {} has variants, one must be chosen", self.id),
+            Some(variant_id) => self
+                .variants
+                .get(variant_id as usize)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("product {} has no variant {}", self.id, variant_id)),
+        }
+    }
+
+    /// Stock on hand for the variant (or the product itself if it has no variants)
+    pub fn available_stock(&self, variant_id: Option<VariantId>) -> anyhow::Result<u32> {
+        Ok(match self.variant(variant_id)? {
+            Some(variant) => variant.stock,
+            None => self.stock,
+        })
+    }
+
+    /// The undiscounted price of a single item, including the variant's price delta
+    pub fn unit_price(&self, variant_id: Option<VariantId>) -> anyhow::Result<Decimal256> {
+        self.unit_price_from(self.price, variant_id)
+    }
+
+    /// Same as `unit_price`, but from a base price other than the listed one (e.g. where a dutch auction was at)
+    pub fn unit_price_from(&self, price: Decimal256, variant_id: Option<VariantId>) -> anyhow::Result<Decimal256> {
+        Ok(match self.variant(variant_id)? {
+            Some(variant) => variant.price_from(price)?,
+            None => price,
+        })
+    }
+
+    /// The base price a purchase made at the given time pays, before any variant delta
+    pub fn current_price(&self, now: Timestamp) -> Decimal256 {
+        match &self.pricing {
+            PricingMode::Dutch { floor_price, start, end, .. } => {
+                if now <= *start {
+                    self.price
+                } else if now >= *end {
+                    *floor_price
+                } else {
+                    let elapsed = now.nanos() - start.nanos();
+                    let duration = end.nanos() - start.nanos();
+                    self.price - (self.price - *floor_price) * Decimal256::from_ratio(elapsed, duration)
+                }
+            },
+            _ => self.price,
+        }
+    }
 }
 
 pub type ProductId = u32;
+
+/// Index into the product's variants
+pub type VariantId = u32;
+
+#[cw_serde]
+pub struct ProductVariant {
+    pub name: String,
+    // e.g. size: M, color: red
+    #[serde(default)]
+    pub attributes: Vec<VariantAttribute>,
+    pub stock: u32,
+    // added on top of the product price, negative for variants that are cheaper
+    #[serde(default)]
+    pub price_delta: SignedDecimal256,
+}
+
+impl ProductVariant {
+    /// The variant's price given the product's base price, which cannot go below zero
+    pub fn price_from(&self, price: Decimal256) -> anyhow::Result<Decimal256> {
+        let price = SignedDecimal256::try_from(price)? + self.price_delta;
+        if price.is_negative() {
+            anyhow::bail!("variant {} would be priced below zero", self.name);
+        }
+        Ok(Decimal256::try_from(price)?)
+    }
+}
+
+#[cw_serde]
+pub struct VariantAttribute {
+    pub name: String,
+    pub value: String,
+}
+
+/// Maximum size of a single group for a product
+#[cw_serde]
+#[derive(Copy)]
+pub enum GroupCapacity {
+    /// Total number of units across all purchases in the group
+    Units(u32),
+    /// Number of buyers (i.e. purchases) in the group
+    Buyers(u32),
+}
+
+impl GroupCapacity {
+    /// How much room is left, given the current buyer count and unit total of a group
+    pub fn remaining(&self, count: u32, units: u32) -> u32 {
+        match self {
+            GroupCapacity::Units(max) => max.saturating_sub(units),
+            GroupCapacity::Buyers(max) => max.saturating_sub(count),
+        }
+    }
+
+    /// Would a purchase of the given quantity fit, given the current buyer count and unit total of a group
+    pub fn fits(&self, count: u32, units: u32, quantity: u32) -> bool {
+        match self {
+            GroupCapacity::Units(_) => quantity <= self.remaining(count, units),
+            GroupCapacity::Buyers(_) => self.remaining(count, units) > 0,
+        }
+    }
+}
+
+/// How the price each buyer ends up paying is worked out
+#[cw_serde]
+#[derive(Default)]
+pub enum PricingMode {
+    /// The price drops as more buyers join the group
+    #[default]
+    GroupDiscount,
+    /// Everyone pays the listed price, e.g. products from a won demand request
+    Fixed,
+    /// The price falls linearly from the listed price at `start` to `floor_price` at `end`, and stays there
+    Dutch {
+        floor_price: Decimal256,
+        start: Timestamp,
+        end: Timestamp,
+        /// When the group ships, everyone is refunded down to the lowest price paid in it
+        refund_to_clearing: bool,
+    },
+}
+
+/// Merchant-configurable limits on how much a single spender can buy
+#[cw_serde]
+#[derive(Default)]
+pub struct PurchaseRules {
+    /// Maximum number of units a single spender can hold in one group
+    pub max_units_per_buyer: Option<u32>,
+    /// Maximum number of open (not yet shipped) purchases a single spender can have for this product
+    pub max_open_purchases: Option<u32>,
+    /// After cancelling a purchase, the spender must wait this many seconds before buying this product again
+    pub cancellation_cooldown_seconds: Option<u64>,
+}
@@ -1,15 +1,44 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Decimal256};
 
-use super::{contract::warehouse::GroupId, product::ProductId};
+use super::{contract::warehouse::GroupId, product::{Product, ProductId, VariantId}};
 
 #[cw_serde]
 pub struct Purchase {
     pub id: PurchaseId,
     pub product_id: ProductId,
+    #[serde(default)]
+    pub variant_id: Option<VariantId>,
     pub quantity: u32,
+    // units still waiting on a restock, the purchase can't ship until this is zero
+    #[serde(default)]
+    pub backordered: u32,
     pub spender: String,
-    pub group_id: GroupId
+    pub group_id: GroupId,
+    // the base price the purchase was made at, only set when it's not the listed price (i.e. dutch auctions)
+    #[serde(default)]
+    pub price: Option<Decimal256>,
+    // taken off each unit by a coupon
+    #[serde(default)]
+    pub discount: Decimal256,
+    // who brought the buyer in, an address on the *Payment* chain
+    #[serde(default)]
+    pub referrer: Option<String>,
+    // the cw20 contract (on the *Payment* chain) it was paid with, refunds and payouts go back in the same token
+    #[serde(default)]
+    pub cw20: Option<String>,
+}
+
+impl Purchase {
+    /// What the buyer paid per item, before any group discount
+    pub fn unit_price(&self, product: &Product) -> anyhow::Result<Decimal256> {
+        let unit_price = match self.price {
+            Some(price) => product.unit_price_from(price, self.variant_id)?,
+            None => product.unit_price(self.variant_id)?,
+        };
+
+        Ok(unit_price - self.discount)
+    }
 }
 
 pub type PurchaseId = u64;
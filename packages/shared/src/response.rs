@@ -8,15 +8,17 @@ use cw2::ContractVersion;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::ibc::{ack_fail, ack_success};
+use crate::ibc::{ack_fail, ack_success, ack_success_with_data};
 
 /// Helper data type, following builder pattern, for constructing a [Response].
+#[derive(Clone)]
 pub struct ResponseBuilder {
     resp: Response,
     event_type: EventType,
     event_type_count: HashMap<String, u32>,
 }
 
+#[derive(Clone)]
 enum EventType {
     MuteEvents,
     EmitEvents {
@@ -194,8 +196,13 @@ impl ResponseBuilder {
     }
 
     /// Turn the accumulated response into an IBC Receive success response
+    /// Any response data is sent back as the ack result
     pub fn into_ibc_recv_response_success(self) -> IbcReceiveResponse {
-        let mut resp = IbcReceiveResponse::new(ack_success());
+        let ack = match self.resp.data {
+            Some(data) => ack_success_with_data(data),
+            None => ack_success(),
+        };
+        let mut resp = IbcReceiveResponse::new(ack);
         resp.messages = self.resp.messages;
         resp.attributes = self.resp.attributes;
         resp.events = self.resp.events;